@@ -0,0 +1,29 @@
+//! Error types
+
+use solana_sdk::{program_error::ProgramError, pubkey::Pubkey};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Account source error: {0}")]
+    Source(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+
+    #[error("Account {address} is owned by {owner}, expected {expected}")]
+    IncorrectOwner {
+        address: Pubkey,
+        owner: Pubkey,
+        expected: Pubkey,
+    },
+
+    #[error("Account {address} has invalid data: {err}")]
+    InvalidAccountData { address: Pubkey, err: ProgramError },
+}
+
+impl ClientError {
+    pub fn source<E: std::error::Error + Send + Sync + 'static>(err: E) -> Self {
+        Self::Source(Box::new(err))
+    }
+}
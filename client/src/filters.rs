@@ -0,0 +1,99 @@
+//! `getProgramAccounts` filters for simpledex accounts.
+//!
+//! Offer filters match the current layout only, at the offsets in
+//! [`offer_offsets`](simpledex::state::offer_offsets).
+//! Offers in older layouts have different offsets and need to be
+//! migrated before they can be matched anyway.
+
+use simpledex::state::{offer_offsets, Offer};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+
+/// Accounts the size of a current-layout offer
+pub fn offer_size() -> RpcFilterType {
+    RpcFilterType::DataSize(Offer::LEN as u64)
+}
+
+pub fn offer_owner(owner: &Pubkey) -> RpcFilterType {
+    pubkey_at(offer_offsets::OWNER, owner)
+}
+
+pub fn offer_offer_mint(offer_mint: &Pubkey) -> RpcFilterType {
+    pubkey_at(offer_offsets::OFFER_MINT, offer_mint)
+}
+
+pub fn offer_accept_mint(accept_mint: &Pubkey) -> RpcFilterType {
+    pubkey_at(offer_offsets::ACCEPT_MINT, accept_mint)
+}
+
+/// Offers offering `offer_mint` for `accept_mint`
+pub fn offers_by_pair(offer_mint: &Pubkey, accept_mint: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        offer_size(),
+        offer_offer_mint(offer_mint),
+        offer_accept_mint(accept_mint),
+    ]
+}
+
+/// Offers owned by `owner`, across all pairs
+pub fn offers_by_owner(owner: &Pubkey) -> Vec<RpcFilterType> {
+    vec![offer_size(), offer_owner(owner)]
+}
+
+fn pubkey_at(offset: usize, pubkey: &Pubkey) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, pubkey.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::account::{Account, AccountSharedData};
+
+    use super::*;
+
+    fn account(data: Vec<u8>) -> AccountSharedData {
+        AccountSharedData::from(Account {
+            lamports: 1,
+            data,
+            owner: simpledex::id(),
+            executable: false,
+            rent_epoch: 0,
+        })
+    }
+
+    fn allows(filters: &[RpcFilterType], account: &AccountSharedData) -> bool {
+        filters.iter().all(|f| f.allows(account))
+    }
+
+    #[test]
+    fn test_filters_match_offer_layout() {
+        let offer = Offer {
+            owner: Pubkey::new_unique(),
+            offer_mint: Pubkey::new_unique(),
+            accept_mint: Pubkey::new_unique(),
+            refund_to: Pubkey::new_unique(),
+            credit_to: Pubkey::new_unique(),
+            refund_rent_to: Pubkey::new_unique(),
+            offering: 1,
+            accept_at_least: 1,
+            ..Offer::default()
+        };
+        let mut data = vec![0; Offer::LEN];
+        Offer::pack(offer, &mut data).unwrap();
+        let legacy = account(data[..Offer::LEGACY_LEN].to_vec());
+        let account = account(data);
+
+        assert!(allows(&offers_by_owner(&offer.owner), &account));
+        assert!(allows(
+            &offers_by_pair(&offer.offer_mint, &offer.accept_mint),
+            &account
+        ));
+        // other side of the market
+        assert!(!allows(
+            &offers_by_pair(&offer.accept_mint, &offer.offer_mint),
+            &account
+        ));
+        assert!(!allows(&offers_by_owner(&offer.refund_to), &account));
+
+        assert!(!allows(&offers_by_owner(&offer.owner), &legacy));
+    }
+}
@@ -0,0 +1,25 @@
+//! Off-chain client for loading simpledex offers and markets over RPC.
+//!
+//! [`SimpleDexClient`] loads typed offers from any [`AccountSource`],
+//! e.g. a nonblocking [`RpcClient`](solana_client::nonblocking::rpc_client::RpcClient).
+//! The raw `getProgramAccounts` filters are in [`filters`],
+//! [`OrderBook`] sorts and aggregates a market's offers,
+//! and [`logs`] parses the program's events out of transaction logs.
+//! [`LocalBook`] applies those events to keep a book up to date without polling,
+//! and [`route`] quotes taker swaps against a book's resting offers.
+
+pub mod error;
+pub mod filters;
+pub mod loader;
+pub mod local_book;
+pub mod logs;
+pub mod orderbook;
+pub mod router;
+pub mod source;
+
+pub use error::ClientError;
+pub use loader::{LoadedOffer, MarketOffers, OfferWithHolding, SimpleDexClient};
+pub use local_book::{Divergence, LocalBook};
+pub use orderbook::{OrderBook, Price, Side};
+pub use router::{route, Route, RouteError, SwapAmount};
+pub use source::AccountSource;
@@ -0,0 +1,181 @@
+//! Typed offer loaders
+
+use simpledex::state::{Offer, OfferVersion};
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::Account as TokenAccount;
+
+use crate::{error::ClientError, filters, source::AccountSource};
+
+/// An offer and the address it was loaded from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadedOffer {
+    pub address: Pubkey,
+    pub offer: Offer,
+    /// Offers not in [`OfferVersion::CURRENT`] must be migrated before they can be matched
+    pub version: OfferVersion,
+}
+
+impl LoadedOffer {
+    pub fn holding(&self) -> Pubkey {
+        get_associated_token_address(&self.address, &self.offer.offer_mint)
+    }
+}
+
+/// An offer with the balance of its holding account
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OfferWithHolding {
+    pub offer: LoadedOffer,
+    pub holding: Pubkey,
+    /// `offer_mint` atomics held, at least `offer.offering`
+    pub holding_amount: u64,
+}
+
+/// Both sides of a base/quote market
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarketOffers {
+    /// Offering quote tokens for base tokens
+    pub bids: Vec<LoadedOffer>,
+    /// Offering base tokens for quote tokens
+    pub asks: Vec<LoadedOffer>,
+}
+
+pub struct SimpleDexClient<S> {
+    source: S,
+    program_id: Pubkey,
+}
+
+impl<S: AccountSource> SimpleDexClient<S> {
+    pub fn new(source: S) -> Self {
+        Self::new_with_program_id(source, simpledex::id())
+    }
+
+    /// [`Self::new`] for a deployment at `program_id`
+    pub fn new_with_program_id(source: S, program_id: Pubkey) -> Self {
+        Self { source, program_id }
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    /// All current-layout offers offering `offer_mint` for `accept_mint`.
+    /// Note: getProgramAccounts is expensive for the RPC, avoid calling this often
+    pub async fn offers_by_pair(
+        &self,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+    ) -> Result<Vec<LoadedOffer>, ClientError> {
+        self.load_filtered(filters::offers_by_pair(offer_mint, accept_mint))
+            .await
+    }
+
+    /// All current-layout offers owned by `owner`, across all pairs
+    pub async fn offers_by_owner(&self, owner: &Pubkey) -> Result<Vec<LoadedOffer>, ClientError> {
+        self.load_filtered(filters::offers_by_owner(owner)).await
+    }
+
+    /// Both sides of the `base`/`quote` market
+    pub async fn market(&self, base: &Pubkey, quote: &Pubkey) -> Result<MarketOffers, ClientError> {
+        Ok(MarketOffers {
+            bids: self.offers_by_pair(quote, base).await?,
+            asks: self.offers_by_pair(base, quote).await?,
+        })
+    }
+
+    /// A single offer of any layout version, with its holding balance
+    pub async fn offer(&self, address: &Pubkey) -> Result<OfferWithHolding, ClientError> {
+        self.offers(&[*address])
+            .await?
+            .pop()
+            .flatten()
+            .ok_or(ClientError::AccountNotFound(*address))
+    }
+
+    /// Offers of any layout version, with their holding balances.
+    /// Same order as `addresses`, `None` for offers that don't exist
+    pub async fn offers(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<OfferWithHolding>>, ClientError> {
+        let accounts = self.source.get_multiple_accounts(addresses).await?;
+        let offers = addresses
+            .iter()
+            .zip(accounts)
+            .map(|(address, account)| {
+                account
+                    .map(|account| self.decode_offer(address, &account))
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let holdings: Vec<Pubkey> = offers.iter().flatten().map(|o| o.holding()).collect();
+        let mut holding_accounts = holdings
+            .iter()
+            .zip(self.source.get_multiple_accounts(&holdings).await?);
+        offers
+            .into_iter()
+            .map(|offer| {
+                let offer = match offer {
+                    Some(offer) => offer,
+                    None => return Ok(None),
+                };
+                // unwrap safety: one holding per Some offer
+                let (holding, account) = holding_accounts.next().unwrap();
+                let account = account.ok_or(ClientError::AccountNotFound(*holding))?;
+                let token_account = TokenAccount::unpack(&account.data).map_err(|err| {
+                    ClientError::InvalidAccountData {
+                        address: *holding,
+                        err,
+                    }
+                })?;
+                Ok(Some(OfferWithHolding {
+                    offer,
+                    holding: *holding,
+                    holding_amount: token_account.amount,
+                }))
+            })
+            .collect()
+    }
+
+    async fn load_filtered(
+        &self,
+        filters: Vec<solana_client::rpc_filter::RpcFilterType>,
+    ) -> Result<Vec<LoadedOffer>, ClientError> {
+        self.source
+            .get_program_accounts(&self.program_id, filters)
+            .await?
+            .iter()
+            .map(|(address, account)| self.decode_offer(address, account))
+            .collect()
+    }
+
+    fn decode_offer(
+        &self,
+        address: &Pubkey,
+        account: &Account,
+    ) -> Result<LoadedOffer, ClientError> {
+        if account.owner != self.program_id {
+            return Err(ClientError::IncorrectOwner {
+                address: *address,
+                owner: account.owner,
+                expected: self.program_id,
+            });
+        }
+        let (offer, version) = Offer::unpack_any_version(&account.data).map_err(|err| {
+            ClientError::InvalidAccountData {
+                address: *address,
+                err,
+            }
+        })?;
+        Ok(LoadedOffer {
+            address: *address,
+            offer,
+            version,
+        })
+    }
+}
@@ -0,0 +1,540 @@
+//! Order book kept up to date by applying events on top of a snapshot.
+//!
+//! Every event carries the absolute state of the offers it touches,
+//! so applying one twice is harmless. Each offer remembers the slot of its last update:
+//! events from before it are stale and skipped. Within a slot, an offer's `offering`
+//! only ever goes down and a close is final, which orders same-slot events.
+
+use core::cmp::Ordering;
+use std::collections::HashMap;
+
+use simpledex::{
+    events::{OfferEvent, SimpleDexEvent},
+    state::Offer,
+};
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+use thiserror::Error;
+
+use crate::{loader::MarketOffers, orderbook::OrderBook};
+
+/// The book missed events and should be rebuilt from a new snapshot
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum Divergence {
+    #[error("Offer {0} was matched but its creation was never seen")]
+    UnknownOffer(Pubkey),
+
+    #[error("Offer {0} was matched to a larger offering than the book has")]
+    UnexpectedState(Pubkey),
+
+    #[error("Offer {0} was created in the slot it was closed in, the order is unknown")]
+    AmbiguousOrder(Pubkey),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OfferState {
+    /// creation slot of the offer, if it was seen
+    created_at: Option<Slot>,
+    updated_at: Slot,
+    closed: bool,
+}
+
+/// [`OrderBook`] of a market that applies [`SimpleDexEvent`]s incrementally,
+/// the Rust counterpart of the TS SDK's `Market` live updates.
+///
+/// Offers created by events only have the fields in the event,
+/// `owner`, `seed` and the refund/credit accounts are left default
+/// until a snapshot reloads them. Fill stats aren't tracked either.
+#[derive(Clone, Debug)]
+pub struct LocalBook {
+    book: OrderBook,
+    /// events at or before this slot are already in the book
+    floor: Slot,
+    slot: Slot,
+    offers: HashMap<Pubkey, OfferState>,
+}
+
+impl LocalBook {
+    /// `snapshot_slot` is the slot the offers were read at,
+    /// e.g. the context slot of the `getProgramAccounts` call.
+    /// Events at or before it are skipped.
+    pub fn new(
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        snapshot_slot: Slot,
+        offers: impl IntoIterator<Item = (Pubkey, Offer)>,
+    ) -> Self {
+        let mut res = Self {
+            book: OrderBook::new(base_mint, quote_mint, Vec::new()),
+            floor: snapshot_slot,
+            slot: snapshot_slot,
+            offers: HashMap::new(),
+        };
+        for (address, offer) in offers {
+            if res.book.side_of(&offer).is_none() {
+                continue;
+            }
+            let state = OfferState {
+                created_at: Some(offer.slot),
+                updated_at: snapshot_slot,
+                closed: !res.book.insert(address, offer),
+            };
+            res.offers.insert(address, state);
+        }
+        res
+    }
+
+    pub fn from_market(
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        snapshot_slot: Slot,
+        market: &MarketOffers,
+    ) -> Self {
+        let offers = market.bids.iter().chain(&market.asks);
+        Self::new(
+            base_mint,
+            quote_mint,
+            snapshot_slot,
+            offers.map(|loaded| (loaded.address, loaded.offer)),
+        )
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// Latest slot the book has seen, from the snapshot or an event
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Applies an event of a transaction at `slot`, in the order it was logged.
+    /// Returns true if the book changed, false if the event was of another market,
+    /// stale or a duplicate.
+    ///
+    /// A [`Divergence`] means events were missed. The rest of the event
+    /// is still applied, but the book should be rebuilt from a new snapshot.
+    pub fn apply(&mut self, slot: Slot, event: &SimpleDexEvent) -> Result<bool, Divergence> {
+        if slot <= self.floor {
+            return Ok(false);
+        }
+        self.slot = self.slot.max(slot);
+        match event {
+            SimpleDexEvent::CreateOffer(e) => self.create(slot, e),
+            SimpleDexEvent::CancelOffer(e) => {
+                Ok(self.close(slot, &e.offer, &e.offer_mint, &e.accept_mint))
+            }
+            SimpleDexEvent::MatchOffers(e) => {
+                let a = self.fill(
+                    slot,
+                    &e.offering_a,
+                    &e.token_a,
+                    &e.token_b,
+                    e.offering_a_new_offering,
+                    e.offering_a_new_accept_at_least,
+                );
+                let b = self.fill(
+                    slot,
+                    &e.offering_b,
+                    &e.token_b,
+                    &e.token_a,
+                    e.offering_b_new_offering,
+                    e.offering_b_new_accept_at_least,
+                );
+                Ok(a? | b?)
+            }
+            SimpleDexEvent::TakeOffer(e) => self.fill(
+                slot,
+                &e.offer,
+                &e.offer_mint,
+                &e.accept_mint,
+                e.new_offering,
+                e.new_accept_at_least,
+            ),
+        }
+    }
+
+    /// Forgets closed offers last updated before `slot` to bound memory use.
+    /// Events at or before `slot` are skipped from then on, since stale ones
+    /// could no longer be told apart.
+    pub fn prune(&mut self, slot: Slot) {
+        self.floor = self.floor.max(slot);
+        self.offers
+            .retain(|_, state| !state.closed || state.updated_at >= slot);
+    }
+
+    fn is_of_market(&self, offer_mint: &Pubkey, accept_mint: &Pubkey) -> bool {
+        let offer = Offer {
+            offer_mint: *offer_mint,
+            accept_mint: *accept_mint,
+            ..Offer::default()
+        };
+        self.book.side_of(&offer).is_some()
+    }
+
+    fn create(&mut self, slot: Slot, e: &OfferEvent) -> Result<bool, Divergence> {
+        if !self.is_of_market(&e.offer_mint, &e.accept_mint) {
+            return Ok(false);
+        }
+        if let Some(state) = self.offers.get(&e.offer) {
+            match (state.updated_at.cmp(&slot), state.closed) {
+                (Ordering::Greater, _) => return Ok(false),
+                // a create comes before every other update of its offer
+                (Ordering::Equal, false) => return Ok(false),
+                (Ordering::Equal, true) => match state.created_at {
+                    Some(created_at) if created_at == slot => return Ok(false),
+                    // the address can only be reused once closed
+                    Some(_) => (),
+                    None => return Err(Divergence::AmbiguousOrder(e.offer)),
+                },
+                (Ordering::Less, _) => (),
+            }
+        }
+        let offer = Offer {
+            slot,
+            offering: e.offering,
+            accept_at_least: e.accept_at_least,
+            offer_mint: e.offer_mint,
+            accept_mint: e.accept_mint,
+            client_order_id: e.client_order_id,
+            memo: e.memo,
+            original_offering: e.offering,
+            original_accept_at_least: e.accept_at_least,
+            ..Offer::default()
+        };
+        let state = OfferState {
+            created_at: Some(slot),
+            updated_at: slot,
+            closed: !self.book.insert(e.offer, offer),
+        };
+        self.offers.insert(e.offer, state);
+        Ok(true)
+    }
+
+    fn close(
+        &mut self,
+        slot: Slot,
+        address: &Pubkey,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+    ) -> bool {
+        if !self.is_of_market(offer_mint, accept_mint) {
+            return false;
+        }
+        let created_at = match self.offers.get(address) {
+            Some(state) if state.updated_at > slot => return false,
+            Some(state) if state.closed && state.updated_at == slot => return false,
+            Some(state) if !state.closed => state.created_at,
+            _ => None,
+        };
+        let state = OfferState {
+            created_at,
+            updated_at: slot,
+            closed: true,
+        };
+        self.offers.insert(*address, state);
+        self.book.remove(address).is_some()
+    }
+
+    fn fill(
+        &mut self,
+        slot: Slot,
+        address: &Pubkey,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+        offering: u64,
+        accept_at_least: u64,
+    ) -> Result<bool, Divergence> {
+        if offering == 0 || accept_at_least == 0 {
+            return Ok(self.close(slot, address, offer_mint, accept_mint));
+        }
+        if !self.is_of_market(offer_mint, accept_mint) {
+            return Ok(false);
+        }
+        let state = match self.offers.get_mut(address) {
+            Some(state) if state.updated_at > slot => return Ok(false),
+            // a close is final within a slot
+            Some(state) if state.closed && state.updated_at == slot => return Ok(false),
+            Some(state) if !state.closed => state,
+            _ => return Err(Divergence::UnknownOffer(*address)),
+        };
+        let mut offer = match self.book.get(address) {
+            Some(entry) => entry.offer,
+            None => return Err(Divergence::UnknownOffer(*address)),
+        };
+        if offering > offer.offering {
+            return match state.updated_at == slot {
+                // an earlier match in the same slot
+                true => Ok(false),
+                false => Err(Divergence::UnexpectedState(*address)),
+            };
+        }
+        state.updated_at = slot;
+        if offering == offer.offering && accept_at_least == offer.accept_at_least {
+            return Ok(false);
+        }
+        offer.offering = offering;
+        offer.accept_at_least = accept_at_least;
+        self.book.insert(*address, offer);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use simpledex::{
+        events::{MatchOffersEvent, TakeOfferEvent},
+        quote::Receipt,
+        state::MEMO_LEN,
+        types::OfferSeq,
+    };
+    use simpledex_test_utils::Market;
+
+    use super::*;
+
+    fn book(m: &Market) -> LocalBook {
+        LocalBook::new(m.base, m.quote, 10, Vec::new())
+    }
+
+    fn create(e: &OfferEvent) -> SimpleDexEvent {
+        SimpleDexEvent::CreateOffer(*e)
+    }
+
+    fn cancel(e: &OfferEvent) -> SimpleDexEvent {
+        SimpleDexEvent::CancelOffer(*e)
+    }
+
+    /// match of `a` and `b` leaving them with the given `(offering, accept_at_least)`
+    fn matched(
+        a: &OfferEvent,
+        a_new: (u64, u64),
+        b: &OfferEvent,
+        b_new: (u64, u64),
+        slot: Slot,
+    ) -> SimpleDexEvent {
+        SimpleDexEvent::MatchOffers(MatchOffersEvent {
+            token_a: a.offer_mint,
+            token_b: b.offer_mint,
+            receipt: Receipt {
+                a_to_b: a.offering - a_new.0,
+                b_to_a: b.offering - b_new.0,
+                a_to_matcher: 0,
+                b_to_matcher: 0,
+                a_fee: 0,
+                b_fee: 0,
+                a_bonus: 0,
+                b_bonus: 0,
+                a_seq: OfferSeq::Neither,
+            },
+            offering_a: a.offer,
+            offering_a_new_offering: a_new.0,
+            offering_a_new_accept_at_least: a_new.1,
+            offering_a_client_order_id: 0,
+            offering_a_memo: [0; MEMO_LEN],
+            offering_b: b.offer,
+            offering_b_new_offering: b_new.0,
+            offering_b_new_accept_at_least: b_new.1,
+            offering_b_client_order_id: 0,
+            offering_b_memo: [0; MEMO_LEN],
+            matcher_a: Pubkey::new_unique(),
+            matcher_b: Pubkey::new_unique(),
+            slot,
+        })
+    }
+
+    /// take of `e` leaving it with the given `(offering, accept_at_least)`
+    fn taken(e: &OfferEvent, new: (u64, u64), slot: Slot) -> SimpleDexEvent {
+        SimpleDexEvent::TakeOffer(TakeOfferEvent {
+            offer: e.offer,
+            offer_mint: e.offer_mint,
+            accept_mint: e.accept_mint,
+            taker: Pubkey::new_unique(),
+            amount_in: e.accept_at_least - new.1,
+            amount_out: e.offering - new.0,
+            fee: 0,
+            fee_account: Pubkey::new_unique(),
+            new_offering: new.0,
+            new_accept_at_least: new.1,
+            client_order_id: 0,
+            memo: [0; MEMO_LEN],
+            slot,
+        })
+    }
+
+    fn offering(book: &LocalBook, e: &OfferEvent) -> Option<u64> {
+        book.book().get(&e.offer).map(|entry| entry.offer.offering)
+    }
+
+    #[test]
+    fn test_in_order() {
+        let m = Market::new();
+        let mut book = book(&m);
+        let bid = m.bid_event(100, 10);
+        let ask = m.ask_event(20, 150);
+        assert_eq!(book.apply(11, &create(&bid)), Ok(true));
+        assert_eq!(book.apply(12, &create(&ask)), Ok(true));
+        assert!(book.book().is_crossed());
+        assert_eq!(book.book().best_bid().unwrap().slot, 11);
+
+        let event = matched(&bid, (0, 0), &ask, (10, 50), 13);
+        assert_eq!(book.apply(13, &event), Ok(true));
+        assert_eq!(offering(&book, &bid), None);
+        assert_eq!(offering(&book, &ask), Some(10));
+        assert_eq!(book.book().best_ask().unwrap().offer.accept_at_least, 50);
+
+        assert_eq!(book.apply(14, &cancel(&ask)), Ok(true));
+        assert!(book.book().asks().is_empty());
+        assert_eq!(book.slot(), 14);
+    }
+
+    #[test]
+    fn test_taken() {
+        let m = Market::new();
+        let mut book = book(&m);
+        let ask = m.ask_event(20, 150);
+        assert_eq!(book.apply(11, &create(&ask)), Ok(true));
+        assert_eq!(book.apply(12, &taken(&ask, (10, 75), 12)), Ok(true));
+        assert_eq!(offering(&book, &ask), Some(10));
+        assert_eq!(book.book().best_ask().unwrap().offer.accept_at_least, 75);
+        // duplicate
+        assert_eq!(book.apply(12, &taken(&ask, (10, 75), 12)), Ok(false));
+        assert_eq!(book.apply(13, &taken(&ask, (0, 0), 13)), Ok(true));
+        assert!(book.book().asks().is_empty());
+    }
+
+    #[test]
+    fn test_stale_and_duplicate() {
+        let m = Market::new();
+        let mut book = book(&m);
+        let bid = m.bid_event(100, 10);
+        let ask = m.ask_event(20, 150);
+        let first = matched(&bid, (50, 5), &ask, (15, 100), 13);
+        let second = matched(&bid, (20, 2), &ask, (12, 80), 14);
+
+        // snapshot already has these
+        assert_eq!(book.apply(10, &create(&bid)), Ok(false));
+        assert!(book.book().bids().is_empty());
+
+        for event in [create(&bid), create(&ask)] {
+            assert_eq!(book.apply(11, &event), Ok(true));
+            assert_eq!(book.apply(11, &event), Ok(false));
+        }
+        assert_eq!(book.apply(14, &second), Ok(true));
+        assert_eq!(book.apply(13, &first), Ok(false));
+        assert_eq!(book.apply(11, &create(&bid)), Ok(false));
+        assert_eq!(book.apply(14, &second), Ok(false));
+        assert_eq!(offering(&book, &bid), Some(20));
+        assert_eq!(offering(&book, &ask), Some(12));
+
+        assert_eq!(book.apply(15, &cancel(&bid)), Ok(true));
+        assert_eq!(book.apply(15, &cancel(&bid)), Ok(false));
+        assert_eq!(book.apply(14, &second), Ok(false));
+        assert_eq!(offering(&book, &bid), None);
+    }
+
+    #[test]
+    fn test_same_slot_out_of_order() {
+        let m = Market::new();
+        let mut book = book(&m);
+        let bid = m.bid_event(100, 10);
+        let ask = m.ask_event(20, 150);
+        let first = matched(&bid, (50, 5), &ask, (15, 100), 12);
+        let second = matched(&bid, (20, 2), &ask, (12, 80), 12);
+        for event in [create(&bid), create(&ask)] {
+            book.apply(11, &event).unwrap();
+        }
+        assert_eq!(book.apply(12, &second), Ok(true));
+        assert_eq!(book.apply(12, &first), Ok(false));
+        assert_eq!(offering(&book, &bid), Some(20));
+
+        // create, match and cancel all in one slot, delivered backwards
+        let other = m.ask_event(10, 100);
+        let fill = matched(&bid, (10, 1), &other, (5, 50), 13);
+        assert_eq!(book.apply(13, &cancel(&other)), Ok(false));
+        assert_eq!(book.apply(13, &fill), Ok(true));
+        assert_eq!(
+            book.apply(13, &create(&other)),
+            Err(Divergence::AmbiguousOrder(other.offer))
+        );
+        assert_eq!(offering(&book, &other), None);
+        assert_eq!(offering(&book, &bid), Some(10));
+    }
+
+    #[test]
+    fn test_recreated() {
+        let m = Market::new();
+        let bid = m.bid_event(100, 10);
+        let mut book = LocalBook::new(
+            m.base,
+            m.quote,
+            10,
+            vec![(
+                bid.offer,
+                Offer {
+                    slot: 5,
+                    offering: bid.offering,
+                    accept_at_least: bid.accept_at_least,
+                    offer_mint: bid.offer_mint,
+                    accept_mint: bid.accept_mint,
+                    ..Offer::default()
+                },
+            )],
+        );
+        assert_eq!(offering(&book, &bid), Some(100));
+        assert_eq!(book.apply(11, &cancel(&bid)), Ok(true));
+        let recreated = OfferEvent {
+            offering: 200,
+            ..bid
+        };
+        assert_eq!(book.apply(11, &create(&recreated)), Ok(true));
+        assert_eq!(offering(&book, &bid), Some(200));
+        assert_eq!(book.book().get(&bid.offer).unwrap().slot, 11);
+    }
+
+    #[test]
+    fn test_divergence() {
+        let m = Market::new();
+        let mut book = book(&m);
+        let bid = m.bid_event(100, 10);
+        let ask = m.ask_event(20, 150);
+        book.apply(11, &create(&bid)).unwrap();
+
+        // ask's create was missed, the bid is still updated
+        let event = matched(&bid, (50, 5), &ask, (10, 75), 12);
+        assert_eq!(
+            book.apply(12, &event),
+            Err(Divergence::UnknownOffer(ask.offer))
+        );
+        assert_eq!(offering(&book, &bid), Some(50));
+
+        // a close and re-create of the bid were missed
+        book.apply(13, &create(&ask)).unwrap();
+        let event = matched(&bid, (80, 8), &ask, (10, 75), 14);
+        assert_eq!(
+            book.apply(14, &event),
+            Err(Divergence::UnexpectedState(bid.offer))
+        );
+        assert_eq!(offering(&book, &bid), Some(50));
+        assert_eq!(offering(&book, &ask), Some(10));
+    }
+
+    #[test]
+    fn test_other_market_and_prune() {
+        let m = Market::new();
+        let mut book = book(&m);
+        let other = m.offer_event(m.base, Pubkey::new_unique(), 10, 10);
+        let other_b = m.offer_event(other.accept_mint, m.base, 10, 10);
+        assert_eq!(book.apply(11, &create(&other)), Ok(false));
+        let event = matched(&other, (5, 5), &other_b, (5, 5), 12);
+        assert_eq!(book.apply(12, &event), Ok(false));
+        assert_eq!(book.apply(12, &cancel(&other)), Ok(false));
+
+        let bid = m.bid_event(100, 10);
+        book.apply(11, &create(&bid)).unwrap();
+        book.apply(12, &cancel(&bid)).unwrap();
+        book.prune(13);
+        assert!(book.offers.is_empty());
+        assert_eq!(book.apply(12, &create(&bid)), Ok(false));
+        assert_eq!(book.apply(13, &create(&bid)), Ok(false));
+        assert_eq!(book.apply(14, &create(&bid)), Ok(true));
+    }
+}
@@ -0,0 +1,561 @@
+//! Parses simpledex events out of transaction logs.
+//!
+//! Each successful CreateOffer, CancelOffer, MatchOffers and TakeOffer emits a packed
+//! `Program data:` event, and with the `csv-logs` program feature also a
+//! `Program log: CREATE:...`, `CANCEL:...`, `MATCH:...` or `TAKE:...` line.
+//! [`parse_transaction_logs`] reads both, attributing each line to the program
+//! that emitted it so CPIs into other programs can't spoof events.
+
+use core::{convert::TryInto, str::FromStr};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use simpledex::{
+    events::{MatchOffersEvent, OfferEvent, SimpleDexEvent, TakeOfferEvent},
+    packun::DeserializePacked,
+    quote::Receipt,
+    state::MEMO_LEN,
+    types::OfferSeq,
+};
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+const PROGRAM_LOG_PREFIX: &str = "Program log: ";
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+const PROGRAM_RETURN_PREFIX: &str = "Program return: ";
+const LOG_TRUNCATED: &str = "Log truncated";
+
+const OFFER_FIELDS: [&str; 6] = [
+    "offer",
+    "offer_mint",
+    "offering",
+    "accept_mint",
+    "accept_at_least",
+    "client_order_id",
+];
+
+const MATCH_FIELDS: [&str; 20] = [
+    "token_a",
+    "a_to_b",
+    "token_b",
+    "b_to_a",
+    "offering_a",
+    "offering_a_new_offering",
+    "offering_a_new_accept_at_least",
+    "offering_b",
+    "offering_b_new_offering",
+    "offering_b_new_accept_at_least",
+    "a_fee",
+    "a_bonus",
+    "b_fee",
+    "b_bonus",
+    "a_seq",
+    "matcher_a",
+    "matcher_b",
+    "slot",
+    "offering_a_client_order_id",
+    "offering_b_client_order_id",
+];
+
+const TAKE_FIELDS: [&str; 12] = [
+    "offer",
+    "offer_mint",
+    "amount_out",
+    "accept_mint",
+    "amount_in",
+    "fee",
+    "new_offering",
+    "new_accept_at_least",
+    "taker",
+    "fee_account",
+    "slot",
+    "client_order_id",
+];
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum LogParseError {
+    #[error("{kind} log has {found} fields, expected {expected}")]
+    FieldCount {
+        kind: &'static str,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("{kind} log field {field} is invalid: {value}")]
+    InvalidField {
+        kind: &'static str,
+        field: &'static str,
+        value: String,
+    },
+
+    #[error("Program data is not a valid event: {0}")]
+    InvalidData(String),
+
+    #[error("{0} without a matching invoke")]
+    UnbalancedInvocation(String),
+
+    #[error("Logs were truncated, events may be missing")]
+    Truncated,
+}
+
+/// Parses a single csv log line, e.g. `Program log: CREATE:...`.
+/// Returns `None` for lines that aren't simpledex csv logs.
+///
+/// Csv logs carry less than the packed events: memos are zeroed,
+/// and the receipt's `to_matcher` amounts are derived as fee + bonus.
+pub fn parse_csv_log(line: &str) -> Result<Option<SimpleDexEvent>, LogParseError> {
+    let msg = match line.strip_prefix(PROGRAM_LOG_PREFIX) {
+        Some(msg) => msg,
+        None => return Ok(None),
+    };
+    let (kind, body) = match msg.split_once(':') {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+    let event = match kind {
+        "CREATE" => SimpleDexEvent::CreateOffer(parse_offer_event("CREATE", body)?),
+        "CANCEL" => SimpleDexEvent::CancelOffer(parse_offer_event("CANCEL", body)?),
+        "MATCH" => SimpleDexEvent::MatchOffers(parse_match_event(body)?),
+        "TAKE" => SimpleDexEvent::TakeOffer(parse_take_event(body)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+/// Parses a single `Program data: <BASE64>` line emitted by simpledex.
+/// Returns `None` for other lines.
+pub fn parse_data_log(line: &str) -> Result<Option<SimpleDexEvent>, LogParseError> {
+    let data = match line.strip_prefix(PROGRAM_DATA_PREFIX) {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let invalid = |err: String| LogParseError::InvalidData(err);
+    let bytes = STANDARD
+        .decode(data.trim())
+        .map_err(|e| invalid(e.to_string()))?;
+    let mut reader = bytes.as_slice();
+    let event = SimpleDexEvent::read_bytes(&mut reader).map_err(|e| invalid(e.to_string()))?;
+    if !reader.is_empty() {
+        return Err(invalid(format!("{} trailing bytes", reader.len())));
+    }
+    Ok(Some(event))
+}
+
+/// Events emitted by `program_id` in a successful transaction's logs, in order.
+///
+/// Logs are attributed to the innermost program invocation, so lines
+/// logged by other programs, including CPIs made by simpledex, are ignored.
+/// An invocation's packed event is used if present, otherwise its csv log.
+/// Failed transactions have no effects, so they have no events.
+pub fn parse_transaction_logs<S: AsRef<str>>(
+    logs: &[S],
+    program_id: &Pubkey,
+) -> Result<Vec<SimpleDexEvent>, LogParseError> {
+    struct Frame {
+        program_id: Pubkey,
+        csv: Vec<SimpleDexEvent>,
+        data: Vec<SimpleDexEvent>,
+    }
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut res = Vec::new();
+    for line in logs {
+        let line = line.as_ref();
+        if line == LOG_TRUNCATED {
+            return Err(LogParseError::Truncated);
+        }
+        if let Some(invoked) = program_status(line, "invoke [") {
+            stack.push(Frame {
+                program_id: invoked,
+                csv: Vec::new(),
+                data: Vec::new(),
+            });
+            continue;
+        }
+        if program_status(line, "failed").is_some() {
+            return Ok(Vec::new());
+        }
+        if let Some(succeeded) = program_status(line, "success") {
+            let frame = match stack.pop() {
+                Some(frame) if frame.program_id == succeeded => frame,
+                _ => return Err(LogParseError::UnbalancedInvocation(line.to_owned())),
+            };
+            match frame.data.is_empty() {
+                true => res.extend(frame.csv),
+                false => res.extend(frame.data),
+            }
+            continue;
+        }
+        let frame = match stack.last_mut() {
+            Some(frame) if frame.program_id == *program_id => frame,
+            _ => continue,
+        };
+        if let Some(event) = parse_csv_log(line)? {
+            frame.csv.push(event);
+        } else if let Some(event) = parse_data_log(line)? {
+            frame.data.push(event);
+        }
+    }
+    Ok(res)
+}
+
+/// The program id of a `Program <id> <status>...` line.
+/// Lines a program logged, e.g. `Program log: failed`, are never statuses.
+fn program_status(line: &str, status: &str) -> Option<Pubkey> {
+    if [
+        PROGRAM_LOG_PREFIX,
+        PROGRAM_DATA_PREFIX,
+        PROGRAM_RETURN_PREFIX,
+    ]
+    .iter()
+    .any(|prefix| line.starts_with(prefix))
+    {
+        return None;
+    }
+    let rest = line.strip_prefix("Program ")?;
+    let (program_id, rest) = rest.split_once(' ')?;
+    match rest.starts_with(status) {
+        true => program_id.parse().ok(),
+        false => None,
+    }
+}
+
+/// Splits a csv log body into exactly `N` fields
+fn split_fields<'a, const N: usize>(
+    kind: &'static str,
+    body: &'a str,
+) -> Result<[&'a str; N], LogParseError> {
+    let fields: Vec<&str> = body.split(',').collect();
+    let found = fields.len();
+    fields.try_into().map_err(|_| LogParseError::FieldCount {
+        kind,
+        expected: N,
+        found,
+    })
+}
+
+fn parse_field<T: FromStr>(
+    kind: &'static str,
+    names: &[&'static str],
+    fields: &[&str],
+    i: usize,
+) -> Result<T, LogParseError> {
+    fields[i].parse().map_err(|_| LogParseError::InvalidField {
+        kind,
+        field: names[i],
+        value: fields[i].to_owned(),
+    })
+}
+
+fn parse_offer_event(kind: &'static str, body: &str) -> Result<OfferEvent, LogParseError> {
+    let fields = split_fields::<6>(kind, body)?;
+    // closures can't be generic, so one per field type
+    let pubkey = |i| parse_field::<Pubkey>(kind, &OFFER_FIELDS, &fields, i);
+    let amount = |i| parse_field::<u64>(kind, &OFFER_FIELDS, &fields, i);
+    Ok(OfferEvent {
+        offer: pubkey(0)?,
+        offer_mint: pubkey(1)?,
+        offering: amount(2)?,
+        accept_mint: pubkey(3)?,
+        accept_at_least: amount(4)?,
+        client_order_id: amount(5)?,
+        memo: [0; MEMO_LEN],
+    })
+}
+
+fn parse_match_event(body: &str) -> Result<MatchOffersEvent, LogParseError> {
+    const KIND: &str = "MATCH";
+    let fields = split_fields::<20>(KIND, body)?;
+    let pubkey = |i| parse_field::<Pubkey>(KIND, &MATCH_FIELDS, &fields, i);
+    let amount = |i| parse_field::<u64>(KIND, &MATCH_FIELDS, &fields, i);
+    let a_seq = match parse_field::<u8>(KIND, &MATCH_FIELDS, &fields, 14)? {
+        0 => OfferSeq::Maker,
+        1 => OfferSeq::Taker,
+        2 => OfferSeq::Neither,
+        _ => {
+            return Err(LogParseError::InvalidField {
+                kind: KIND,
+                field: MATCH_FIELDS[14],
+                value: fields[14].to_owned(),
+            })
+        }
+    };
+    let (a_fee, a_bonus, b_fee, b_bonus) = (amount(10)?, amount(11)?, amount(12)?, amount(13)?);
+    Ok(MatchOffersEvent {
+        token_a: pubkey(0)?,
+        token_b: pubkey(2)?,
+        receipt: Receipt {
+            a_to_b: amount(1)?,
+            b_to_a: amount(3)?,
+            // the program pays fee + bonus to the matcher, so neither sum can overflow
+            a_to_matcher: a_fee.saturating_add(a_bonus),
+            b_to_matcher: b_fee.saturating_add(b_bonus),
+            a_fee,
+            b_fee,
+            a_bonus,
+            b_bonus,
+            a_seq,
+        },
+        offering_a: pubkey(4)?,
+        offering_a_new_offering: amount(5)?,
+        offering_a_new_accept_at_least: amount(6)?,
+        offering_a_client_order_id: amount(18)?,
+        offering_a_memo: [0; MEMO_LEN],
+        offering_b: pubkey(7)?,
+        offering_b_new_offering: amount(8)?,
+        offering_b_new_accept_at_least: amount(9)?,
+        offering_b_client_order_id: amount(19)?,
+        offering_b_memo: [0; MEMO_LEN],
+        matcher_a: pubkey(15)?,
+        matcher_b: pubkey(16)?,
+        slot: amount(17)?,
+    })
+}
+
+fn parse_take_event(body: &str) -> Result<TakeOfferEvent, LogParseError> {
+    const KIND: &str = "TAKE";
+    let fields = split_fields::<12>(KIND, body)?;
+    let pubkey = |i| parse_field::<Pubkey>(KIND, &TAKE_FIELDS, &fields, i);
+    let amount = |i| parse_field::<u64>(KIND, &TAKE_FIELDS, &fields, i);
+    Ok(TakeOfferEvent {
+        offer: pubkey(0)?,
+        offer_mint: pubkey(1)?,
+        accept_mint: pubkey(3)?,
+        taker: pubkey(8)?,
+        amount_in: amount(4)?,
+        amount_out: amount(2)?,
+        fee: amount(5)?,
+        fee_account: pubkey(9)?,
+        new_offering: amount(6)?,
+        new_accept_at_least: amount(7)?,
+        client_order_id: amount(11)?,
+        memo: [0; MEMO_LEN],
+        slot: amount(10)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use simpledex::{events::CsvLog, packun::SerializePacked};
+
+    use super::*;
+
+    fn offer_event() -> OfferEvent {
+        OfferEvent {
+            offer: Pubkey::new_unique(),
+            offer_mint: Pubkey::new_unique(),
+            offering: 1_000_000_000,
+            accept_mint: Pubkey::new_unique(),
+            accept_at_least: 100_000_000,
+            client_order_id: u64::MAX,
+            memo: [0; MEMO_LEN],
+        }
+    }
+
+    fn match_event() -> MatchOffersEvent {
+        MatchOffersEvent {
+            token_a: Pubkey::new_unique(),
+            token_b: Pubkey::new_unique(),
+            receipt: Receipt {
+                a_to_b: 1_000_000_000,
+                b_to_a: 99_950_000,
+                a_to_matcher: 3,
+                b_to_matcher: 50_100,
+                a_fee: 1,
+                b_fee: 100,
+                a_bonus: 2,
+                b_bonus: 50_000,
+                a_seq: OfferSeq::Neither,
+            },
+            offering_a: Pubkey::new_unique(),
+            offering_a_new_offering: 0,
+            offering_a_new_accept_at_least: 0,
+            offering_a_client_order_id: 1,
+            offering_a_memo: [0; MEMO_LEN],
+            offering_b: Pubkey::new_unique(),
+            offering_b_new_offering: 10_000_000,
+            offering_b_new_accept_at_least: 100_000_000,
+            offering_b_client_order_id: 2,
+            offering_b_memo: [0; MEMO_LEN],
+            matcher_a: Pubkey::new_unique(),
+            matcher_b: Pubkey::new_unique(),
+            slot: 131_542_731,
+        }
+    }
+
+    fn take_event() -> TakeOfferEvent {
+        TakeOfferEvent {
+            offer: Pubkey::new_unique(),
+            offer_mint: Pubkey::new_unique(),
+            accept_mint: Pubkey::new_unique(),
+            taker: Pubkey::new_unique(),
+            amount_in: 50_000_000,
+            amount_out: 500_000_000,
+            fee: 50_000,
+            fee_account: Pubkey::new_unique(),
+            new_offering: 500_000_000,
+            new_accept_at_least: 50_000_000,
+            client_order_id: 3,
+            memo: [0; MEMO_LEN],
+            slot: 131_542_731,
+        }
+    }
+
+    fn csv_line(event: &SimpleDexEvent) -> String {
+        format!("{}{}", PROGRAM_LOG_PREFIX, CsvLog(event))
+    }
+
+    fn data_line(event: &SimpleDexEvent) -> String {
+        let mut data = vec![0; SimpleDexEvent::PACKED_LEN_MAX];
+        let mut writer = std::io::Cursor::new(data.as_mut_slice());
+        event.write_bytes(&mut writer).unwrap();
+        let len = writer.position() as usize;
+        format!("{}{}", PROGRAM_DATA_PREFIX, STANDARD.encode(&data[..len]))
+    }
+
+    #[test]
+    fn test_csv_roundtrip() {
+        for event in [
+            SimpleDexEvent::CreateOffer(offer_event()),
+            SimpleDexEvent::CancelOffer(offer_event()),
+            SimpleDexEvent::MatchOffers(match_event()),
+            SimpleDexEvent::TakeOffer(take_event()),
+        ] {
+            assert_eq!(parse_csv_log(&csv_line(&event)).unwrap(), Some(event));
+        }
+    }
+
+    #[test]
+    fn test_readme_example() {
+        let line = "Program log: MATCH:So11111111111111111111111111111111111111112,1000000000,EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v,100000000,4Rf9mGD7FeYknun5JczX5nGLTfQuS1GRjNVfkEMKE92b,0,0,9oKrJ9iiEnCC7bewcRFbcdo4LKL2PhUEqcu8gH2eDbVM,10000000,100000000,0,0,100000,0,0,7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU,HN7cABqLq46Es1jh92dQQisAq662SmxELLLsHHe4YWrH,131542731,0,0";
+        let event = match parse_csv_log(line).unwrap() {
+            Some(SimpleDexEvent::MatchOffers(event)) => event,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(event.receipt.b_fee, 100_000);
+        assert_eq!(event.receipt.b_to_matcher, 100_000);
+        assert_eq!(event.receipt.a_seq, OfferSeq::Maker);
+        assert_eq!(event.slot, 131_542_731);
+        assert_eq!(csv_line(&SimpleDexEvent::MatchOffers(event)), line);
+
+        let line = "Program log: TAKE:4Rf9mGD7FeYknun5JczX5nGLTfQuS1GRjNVfkEMKE92b,So11111111111111111111111111111111111111112,100000000,EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v,10000000,10000,900000000,90000000,7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU,HN7cABqLq46Es1jh92dQQisAq662SmxELLLsHHe4YWrH,131542731,0";
+        let event = match parse_csv_log(line).unwrap() {
+            Some(SimpleDexEvent::TakeOffer(event)) => event,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(event.amount_in, 10_000_000);
+        assert_eq!(event.fee, 10_000);
+        assert_eq!(event.new_offering, 900_000_000);
+        assert_eq!(csv_line(&SimpleDexEvent::TakeOffer(event)), line);
+    }
+
+    #[test]
+    fn test_malformed_csv() {
+        let event = SimpleDexEvent::CreateOffer(offer_event());
+        let line = csv_line(&event);
+        assert!(matches!(
+            parse_csv_log(&format!("{},1", line)),
+            Err(LogParseError::FieldCount {
+                kind: "CREATE",
+                expected: 6,
+                found: 7
+            })
+        ));
+        let bad_amount = line.replace("1000000000", "1e9");
+        assert!(matches!(
+            parse_csv_log(&bad_amount),
+            Err(LogParseError::InvalidField {
+                field: "offering",
+                ..
+            })
+        ));
+        let bad_seq = csv_line(&SimpleDexEvent::MatchOffers(match_event())).replace(",2,", ",3,");
+        assert!(matches!(
+            parse_csv_log(&bad_seq),
+            Err(LogParseError::InvalidField { field: "a_seq", .. })
+        ));
+        assert_eq!(parse_csv_log("Program log: CreateOffer"), Ok(None));
+        assert_eq!(
+            parse_csv_log("Program log: Instruction: Transfer"),
+            Ok(None)
+        );
+        assert!(parse_data_log("Program data: AAAA").is_err());
+    }
+
+    #[test]
+    fn test_transaction_logs() {
+        let dex = simpledex::id();
+        let token = spl_token::id().to_string();
+        let other = Pubkey::new_unique().to_string();
+        let create = SimpleDexEvent::CreateOffer(offer_event());
+        let cancel = SimpleDexEvent::CancelOffer(offer_event());
+        let matched = SimpleDexEvent::MatchOffers(match_event());
+        let logs = vec![
+            format!("Program {} invoke [1]", dex),
+            "Program log: CreateOffer".to_owned(),
+            format!("Program {} invoke [2]", token),
+            "Program log: Instruction: Transfer".to_owned(),
+            // other programs' logs are ignored
+            csv_line(&cancel),
+            format!("Program {} consumed 4645 of 180000 compute units", token),
+            format!("Program {} success", token),
+            csv_line(&create),
+            data_line(&create),
+            format!("Program return: {} AAAA", dex),
+            format!("Program {} consumed 30000 of 200000 compute units", dex),
+            format!("Program {} success", dex),
+            // simpledex CPI'd into by another program, csv logs only
+            format!("Program {} invoke [1]", other),
+            format!("Program {} invoke [2]", dex),
+            csv_line(&matched),
+            format!("Program {} success", dex),
+            csv_line(&cancel),
+            format!("Program {} success", other),
+            format!("Program {} invoke [1]", dex),
+            data_line(&cancel),
+            format!("Program {} success", dex),
+        ];
+        let events = parse_transaction_logs(&logs, &dex).unwrap();
+        assert_eq!(events, [create, matched, cancel.clone()]);
+
+        let mut failed = logs.clone();
+        failed.push(format!("Program {} invoke [1]", dex));
+        failed.push(format!("Program {} failed: custom program error: 0x1", dex));
+        assert_eq!(parse_transaction_logs(&failed, &dex), Ok(Vec::new()));
+
+        let mut truncated = logs.clone();
+        truncated.insert(3, LOG_TRUNCATED.to_owned());
+        assert_eq!(
+            parse_transaction_logs(&truncated, &dex),
+            Err(LogParseError::Truncated)
+        );
+
+        assert!(matches!(
+            parse_transaction_logs(&logs[1..], &dex),
+            Err(LogParseError::UnbalancedInvocation(_))
+        ));
+    }
+
+    #[test]
+    fn test_logged_statuses() {
+        let dex = simpledex::id();
+        let other = Pubkey::new_unique().to_string();
+        let create = SimpleDexEvent::CreateOffer(offer_event());
+        let logs = vec![
+            format!("Program {} invoke [1]", dex),
+            format!("Program {} invoke [2]", other),
+            // programs can log anything, these aren't statuses
+            format!("Program log: failed {}", other),
+            "Program log: success".to_owned(),
+            format!("Program data: {} success", other),
+            format!("Program {} success", other),
+            "Program log: failed to find a better price, resting".to_owned(),
+            data_line(&create),
+            format!("Program return: {} success", dex),
+            format!("Program {} success", dex),
+        ];
+        assert_eq!(parse_transaction_logs(&logs, &dex), Ok(vec![create]));
+        assert_eq!(program_status(&logs[0], "invoke ["), Some(dex));
+        assert_eq!(program_status("Program log: invoke [1]", "invoke ["), None);
+        assert_eq!(program_status("Program 1234 success", "success"), None);
+    }
+}
@@ -0,0 +1,590 @@
+//! Order book of a base/quote market, built from its offers.
+//!
+//! Bids offer quote tokens for base tokens, asks offer base tokens for quote tokens.
+//! Prices are exact rationals of quote atomics per base atomic,
+//! taken from each offer's `offering` and `accept_at_least`.
+
+use core::cmp::Ordering;
+
+use simpledex::state::{Offer, OfferFields};
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+
+use crate::loader::MarketOffers;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+/// Exact price in quote atomics per base atomic, always in lowest terms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Price {
+    num: u128,
+    denom: u128,
+}
+
+impl Price {
+    /// `quote_atomics / base_atomics`, `None` if `base_atomics` is 0
+    pub fn new(quote_atomics: u64, base_atomics: u64) -> Option<Self> {
+        match base_atomics {
+            0 => None,
+            _ => Some(Self::from_parts(quote_atomics.into(), base_atomics.into())),
+        }
+    }
+
+    /// Limit price of an offer on `side`, `None` for closed offers
+    pub fn of_offer(side: Side, offer: &Offer) -> Option<Self> {
+        let (quote, base) = match side {
+            Side::Bid => (offer.offering, offer.accept_at_least),
+            Side::Ask => (offer.accept_at_least, offer.offering),
+        };
+        match quote {
+            0 => None,
+            _ => Self::new(quote, base),
+        }
+    }
+
+    fn from_parts(num: u128, denom: u128) -> Self {
+        let gcd = gcd(num, denom);
+        Self {
+            num: num / gcd,
+            denom: denom / gcd,
+        }
+    }
+
+    pub fn num(&self) -> u128 {
+        self.num
+    }
+
+    pub fn denom(&self) -> u128 {
+        self.denom
+    }
+
+    /// Lossy, for display only
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.denom as f64
+    }
+
+    /// Largest multiple of `tick` <= self
+    fn floor_to(&self, tick: &Self) -> Self {
+        let (ticks, _rem) = self.div_ticks(tick);
+        Self::from_parts(ticks * tick.num, tick.denom)
+    }
+
+    /// Smallest multiple of `tick` >= self
+    fn ceil_to(&self, tick: &Self) -> Self {
+        let (ticks, rem) = self.div_ticks(tick);
+        let ticks = match rem {
+            0 => ticks,
+            _ => ticks + 1,
+        };
+        Self::from_parts(ticks * tick.num, tick.denom)
+    }
+
+    /// Quotient and remainder of self / tick.
+    /// Overflow safety: prices built by `new()` have u64 parts,
+    /// so both products fit in a u128, as does the tick-aligned numerator
+    /// `ticks * tick.num <= self.num * tick.denom / self.denom + tick.num`
+    fn div_ticks(&self, tick: &Self) -> (u128, u128) {
+        let num = self.num * tick.denom;
+        let denom = self.denom * tick.num;
+        (num / denom, num % denom)
+    }
+}
+
+impl Ord for Price {
+    /// Exact comparison without overflow, by comparing the
+    /// continued fraction expansions of both prices term by term
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (mut a, mut b, mut c, mut d) = (self.num, self.denom, other.num, other.denom);
+        loop {
+            // compare a/b with c/d
+            let (q_ab, r_ab) = (a / b, a % b);
+            let (q_cd, r_cd) = (c / d, c % d);
+            if q_ab != q_cd {
+                return q_ab.cmp(&q_cd);
+            }
+            match (r_ab, r_cd) {
+                (0, 0) => return Ordering::Equal,
+                (0, _) => return Ordering::Less,
+                (_, 0) => return Ordering::Greater,
+                // r_ab/b cmp r_cd/d == d/r_cd cmp b/r_ab
+                _ => {
+                    let (next_b, next_d) = (r_cd, r_ab);
+                    a = d;
+                    c = b;
+                    b = next_b;
+                    d = next_d;
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// A single offer in the book
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct L3Entry {
+    pub address: Pubkey,
+    pub price: Price,
+    /// Base atomics: `offering` for asks, `accept_at_least` for bids
+    pub base_size: u64,
+    /// Quote atomics: `accept_at_least` for asks, `offering` for bids
+    pub quote_size: u64,
+    pub slot: Slot,
+    pub offer: Offer,
+}
+
+/// All offers at a price level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct L2Level {
+    pub price: Price,
+    pub base_size: u128,
+    pub quote_size: u128,
+    pub offers: usize,
+}
+
+/// Bids and asks of a base/quote market, best price first,
+/// oldest first among offers at the same price
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderBook {
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    bids: Vec<L3Entry>,
+    asks: Vec<L3Entry>,
+}
+
+impl OrderBook {
+    /// Offers of other pairs and closed offers are left out
+    pub fn new(
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        offers: impl IntoIterator<Item = (Pubkey, Offer)>,
+    ) -> Self {
+        let mut res = Self {
+            base_mint,
+            quote_mint,
+            bids: Vec::new(),
+            asks: Vec::new(),
+        };
+        for (address, offer) in offers {
+            res.insert(address, offer);
+        }
+        res
+    }
+
+    pub fn from_market(base_mint: Pubkey, quote_mint: Pubkey, market: &MarketOffers) -> Self {
+        let offers = market.bids.iter().chain(&market.asks);
+        Self::new(
+            base_mint,
+            quote_mint,
+            offers.map(|loaded| (loaded.address, loaded.offer)),
+        )
+    }
+
+    pub fn base_mint(&self) -> &Pubkey {
+        &self.base_mint
+    }
+
+    pub fn quote_mint(&self) -> &Pubkey {
+        &self.quote_mint
+    }
+
+    /// Which side of this market `offer` is on, `None` if it's of another pair
+    pub fn side_of(&self, offer: &Offer) -> Option<Side> {
+        if offer.offer_mint == self.quote_mint && offer.accept_mint == self.base_mint {
+            Some(Side::Bid)
+        } else if offer.offer_mint == self.base_mint && offer.accept_mint == self.quote_mint {
+            Some(Side::Ask)
+        } else {
+            None
+        }
+    }
+
+    /// Inserts or replaces the offer at `address`.
+    /// Returns false and removes any existing entry if the offer is closed,
+    /// or leaves the book unchanged if it's of another pair
+    pub fn insert(&mut self, address: Pubkey, offer: Offer) -> bool {
+        let side = match self.side_of(&offer) {
+            Some(side) => side,
+            None => return false,
+        };
+        self.remove(&address);
+        let price = match Price::of_offer(side, &offer) {
+            Some(price) if !offer.is_closed() => price,
+            _ => return false,
+        };
+        let (base_size, quote_size) = match side {
+            Side::Bid => (offer.accept_at_least, offer.offering),
+            Side::Ask => (offer.offering, offer.accept_at_least),
+        };
+        let entry = L3Entry {
+            address,
+            price,
+            base_size,
+            quote_size,
+            slot: offer.slot,
+            offer,
+        };
+        let entries = self.side_mut(side);
+        let i = entries.partition_point(|e| priority(side, e, &entry) == Ordering::Less);
+        entries.insert(i, entry);
+        true
+    }
+
+    /// Removes the offer at `address`, if present
+    pub fn remove(&mut self, address: &Pubkey) -> Option<L3Entry> {
+        for side in [Side::Bid, Side::Ask] {
+            let entries = self.side_mut(side);
+            if let Some(i) = entries.iter().position(|e| &e.address == address) {
+                return Some(entries.remove(i));
+            }
+        }
+        None
+    }
+
+    pub fn get(&self, address: &Pubkey) -> Option<&L3Entry> {
+        self.bids
+            .iter()
+            .chain(&self.asks)
+            .find(|e| &e.address == address)
+    }
+
+    /// L3 view, best price first
+    pub fn side(&self, side: Side) -> &[L3Entry] {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut Vec<L3Entry> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    pub fn bids(&self) -> &[L3Entry] {
+        &self.bids
+    }
+
+    pub fn asks(&self) -> &[L3Entry] {
+        &self.asks
+    }
+
+    pub fn best_bid(&self) -> Option<&L3Entry> {
+        self.bids.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&L3Entry> {
+        self.asks.first()
+    }
+
+    /// True if the best bid is at or above the best ask, i.e. there are offers to match,
+    /// same as the program's match condition
+    pub fn is_crossed(&self) -> bool {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => bid.price >= ask.price,
+            _ => false,
+        }
+    }
+
+    /// Best ask - best bid, `None` if either side is empty or the best bid is above the best ask.
+    /// Zero if the book is locked, which [is crossed](Self::is_crossed) too
+    pub fn spread(&self) -> Option<Price> {
+        let (bid, ask) = (self.best_bid()?.price, self.best_ask()?.price);
+        if bid > ask {
+            return None;
+        }
+        // u64 parts: both products and the denominator fit in a u128
+        let num = ask.num * bid.denom - bid.num * ask.denom;
+        Some(Price::from_parts(num, ask.denom * bid.denom))
+    }
+
+    /// (best bid + best ask) / 2, `None` if either side is empty.
+    /// Also `None` in the unlikely case the exact mid doesn't fit,
+    /// which needs both denominators close to `u64::MAX`
+    pub fn mid(&self) -> Option<Price> {
+        let (bid, ask) = (self.best_bid()?.price, self.best_ask()?.price);
+        let gcd = gcd(bid.denom, ask.denom);
+        let num = (bid.num * (ask.denom / gcd)).checked_add(ask.num * (bid.denom / gcd))?;
+        let denom = (bid.denom / gcd).checked_mul(ask.denom)?.checked_mul(2)?;
+        Some(Price::from_parts(num, denom))
+    }
+
+    /// L2 view with a level per distinct price, best price first
+    pub fn l2(&self, side: Side) -> Vec<L2Level> {
+        aggregate(self.side(side).iter().map(|e| (e.price, e)))
+    }
+
+    /// L2 view with prices bucketed to multiples of `tick`, best price first.
+    /// Bids are rounded down and asks up, so a level's price is never
+    /// better than that of the offers in it.
+    ///
+    /// E.g. a tick of `Price::new(1, 10u64.pow(base_decimals))` gives levels
+    /// 1 quote atomic per whole base token apart, like the TS SDK's `Market.getL2()`
+    pub fn l2_with_tick(&self, side: Side, tick: &Price) -> Vec<L2Level> {
+        aggregate(self.side(side).iter().map(|e| {
+            let price = match side {
+                Side::Bid => e.price.floor_to(tick),
+                Side::Ask => e.price.ceil_to(tick),
+            };
+            (price, e)
+        }))
+    }
+
+    /// Base atomics available at `price` or better,
+    /// i.e. what an opposing offer with limit `price` could fill against
+    pub fn depth_at(&self, side: Side, price: &Price) -> u128 {
+        self.side(side)
+            .iter()
+            .take_while(|e| match side {
+                Side::Bid => &e.price >= price,
+                Side::Ask => &e.price <= price,
+            })
+            .map(|e| u128::from(e.base_size))
+            .sum()
+    }
+}
+
+/// Ordering of offers on `side` by priority: best price first, then oldest first.
+/// Address is the final tie-break so the order is deterministic
+fn priority(side: Side, a: &L3Entry, b: &L3Entry) -> Ordering {
+    let by_price = match side {
+        Side::Bid => b.price.cmp(&a.price),
+        Side::Ask => a.price.cmp(&b.price),
+    };
+    by_price
+        .then(a.slot.cmp(&b.slot))
+        .then(a.address.cmp(&b.address))
+}
+
+/// Merges consecutive entries with the same price into levels
+fn aggregate<'a>(entries: impl Iterator<Item = (Price, &'a L3Entry)>) -> Vec<L2Level> {
+    let mut res: Vec<L2Level> = Vec::new();
+    for (price, entry) in entries {
+        match res.last_mut() {
+            Some(level) if level.price == price => {
+                level.base_size += u128::from(entry.base_size);
+                level.quote_size += u128::from(entry.quote_size);
+                level.offers += 1;
+            }
+            _ => res.push(L2Level {
+                price,
+                base_size: entry.base_size.into(),
+                quote_size: entry.quote_size.into(),
+                offers: 1,
+            }),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use simpledex_test_utils::Market;
+
+    use super::*;
+
+    fn price(quote: u64, base: u64) -> Price {
+        Price::new(quote, base).unwrap()
+    }
+
+    fn addresses(entries: &[L3Entry]) -> Vec<Pubkey> {
+        entries.iter().map(|e| e.address).collect()
+    }
+
+    #[test]
+    fn test_sorted_by_price_then_slot() {
+        let m = Market::new();
+        let bid_low = m.bid(100, 10, 1);
+        let bid_high_new = m.bid(300, 20, 5);
+        let bid_high_old = m.bid(150, 10, 2);
+        let ask_high = m.ask(10, 300, 1);
+        let ask_low_new = m.ask(10, 200, 3);
+        let ask_low_old = m.ask(20, 400, 2);
+        let other_pair = m.offer(m.base, Pubkey::new_unique(), 10, 10, 0);
+        let closed = m.ask(0, 0, 0);
+        let book = OrderBook::new(
+            m.base,
+            m.quote,
+            vec![
+                bid_low,
+                bid_high_new,
+                bid_high_old,
+                ask_high,
+                ask_low_new,
+                ask_low_old,
+                other_pair,
+                closed,
+            ],
+        );
+
+        assert_eq!(
+            addresses(book.bids()),
+            [bid_high_old.0, bid_high_new.0, bid_low.0]
+        );
+        assert_eq!(
+            addresses(book.asks()),
+            [ask_low_old.0, ask_low_new.0, ask_high.0]
+        );
+        assert_eq!(book.best_bid().unwrap().price, price(15, 1));
+        assert_eq!(book.best_ask().unwrap().price, price(20, 1));
+        assert!(!book.is_crossed());
+        assert_eq!(book.spread(), Some(price(5, 1)));
+        assert_eq!(book.mid(), Some(price(35, 2)));
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let m = Market::new();
+        let (address, ask) = m.ask(10, 200, 1);
+        let mut book = OrderBook::new(m.base, m.quote, vec![(address, ask)]);
+        let bid = m.bid(250, 10, 2);
+        assert!(book.insert(bid.0, bid.1));
+        assert!(book.is_crossed());
+        assert_eq!(book.spread(), None);
+
+        // partially filled: same price, smaller size
+        let filled = Offer {
+            offering: 5,
+            accept_at_least: 100,
+            ..ask
+        };
+        assert!(book.insert(address, filled));
+        assert_eq!(book.asks().len(), 1);
+        assert_eq!(book.get(&address).unwrap().base_size, 5);
+
+        let closed = Offer {
+            offering: 0,
+            accept_at_least: 0,
+            ..ask
+        };
+        assert!(!book.insert(address, closed));
+        assert!(book.asks().is_empty());
+        assert_eq!(book.remove(&bid.0).unwrap().address, bid.0);
+        assert_eq!(book.remove(&bid.0), None);
+    }
+
+    #[test]
+    fn test_locked_is_crossed() {
+        let m = Market::new();
+        // 20 quote per base on both sides, which the program matches
+        let ask = m.ask(10, 200, 1);
+        let bid = m.bid(100, 5, 2);
+        assert!(simpledex::quote::MatchQuote::calc(&ask.1, &bid.1).is_ok());
+        let book = OrderBook::new(m.base, m.quote, vec![ask, bid]);
+        assert!(book.is_crossed());
+        assert_eq!(book.spread(), Some(price(0, 1)));
+    }
+
+    #[test]
+    fn test_l2_and_depth() {
+        let m = Market::new();
+        let book = OrderBook::new(
+            m.base,
+            m.quote,
+            vec![
+                m.ask(10, 200, 1),
+                // same price as above in other terms
+                m.ask(5, 100, 2),
+                m.ask(10, 205, 3),
+                m.ask(10, 290, 4),
+                m.bid(190, 10, 1),
+                m.bid(185, 10, 2),
+                m.bid(100, 10, 3),
+            ],
+        );
+
+        let l2 = book.l2(Side::Ask);
+        assert_eq!(
+            l2.iter()
+                .map(|l| (l.price, l.base_size, l.offers))
+                .collect::<Vec<_>>(),
+            [
+                (price(20, 1), 15, 2),
+                (price(41, 2), 10, 1),
+                (price(29, 1), 10, 1)
+            ]
+        );
+        assert_eq!(l2[0].quote_size, 300);
+
+        // 5 quote atomics per base atomic
+        let tick = price(5, 1);
+        let asks = book.l2_with_tick(Side::Ask, &tick);
+        assert_eq!(
+            asks.iter()
+                .map(|l| (l.price, l.base_size))
+                .collect::<Vec<_>>(),
+            [(price(20, 1), 15), (price(25, 1), 10), (price(30, 1), 10)]
+        );
+        let bids = book.l2_with_tick(Side::Bid, &tick);
+        assert_eq!(
+            bids.iter()
+                .map(|l| (l.price, l.base_size))
+                .collect::<Vec<_>>(),
+            [(price(15, 1), 20), (price(10, 1), 10)]
+        );
+
+        assert_eq!(book.depth_at(Side::Ask, &price(20, 1)), 15);
+        assert_eq!(book.depth_at(Side::Ask, &price(21, 1)), 25);
+        assert_eq!(book.depth_at(Side::Ask, &price(1, 1)), 0);
+        assert_eq!(book.depth_at(Side::Bid, &price(185, 10)), 20);
+        assert_eq!(book.depth_at(Side::Bid, &price(0, 1)), 30);
+    }
+
+    #[test]
+    fn test_price_exact_where_floats_are_not() {
+        let a = price(u64::MAX, u64::MAX - 1);
+        let b = price(u64::MAX - 1, u64::MAX - 2);
+        assert_eq!(a.to_f64(), b.to_f64());
+        assert!(a < b);
+        assert_eq!(price(6, 4), price(3, 2));
+        assert_eq!(Price::new(1, 0), None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_price_cmp_matches_cross_multiplication(
+            a in 0..=u64::MAX,
+            b in 1..=u64::MAX,
+            c in 0..=u64::MAX,
+            d in 1..=u64::MAX,
+        ) {
+            let expected = (a as u128 * d as u128).cmp(&(c as u128 * b as u128));
+            prop_assert_eq!(price(a, b).cmp(&price(c, d)), expected);
+        }
+
+        #[test]
+        fn test_tick_bucketing_brackets_price(
+            quote in 0..=u64::MAX,
+            base in 1..=u64::MAX,
+            tick_num in 1..=u64::MAX,
+            tick_denom in 1..=u64::MAX,
+        ) {
+            let p = price(quote, base);
+            let tick = price(tick_num, tick_denom);
+            let floor = p.floor_to(&tick);
+            let ceil = p.ceil_to(&tick);
+            prop_assert!(floor <= p && p <= ceil);
+            prop_assert!(floor == ceil || floor < p);
+        }
+    }
+}
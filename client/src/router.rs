@@ -0,0 +1,503 @@
+//! Routing taker swaps across a market's resting offers.
+//!
+//! A swap is a taker offer created after the resting offers it consumes,
+//! then matched with each of them, best price first. [`route`] simulates those matches
+//! with [`MatchQuote::calc`], the program's own code, so the quote includes the taker fees
+//! from `calc_fee` and the rounding of `min_willing_to_receive_for` exactly.
+//!
+//! The taker offer's limit price is the worst price it consumes, so offers at better prices
+//! leave an excess, half of which goes to the matcher as a bonus, see [`Route::matcher_out`].
+//! Executing the route with the taker's own token accounts as the matcher's gets the fees
+//! and bonus back.
+//!
+//! Routes consume at most [`MAX_ROUTE_STEPS`] resting offers, so that all of a route's
+//! instructions fit in a single transaction.
+
+use simpledex::{
+    error::SimpleDexError,
+    instructions::{CreateOfferAccounts, MatchOffersAccounts},
+    pda::try_find_offer_pda_with_program_id,
+    quote::MatchQuote,
+    state::{Offer, OfferFields, MEMO_LEN},
+};
+use solana_sdk::{
+    clock::Slot, instruction::Instruction, program_error::ProgramError, pubkey::Pubkey,
+};
+use thiserror::Error;
+
+use crate::orderbook::{L3Entry, OrderBook, Price, Side};
+
+/// Most resting offers a route consumes.
+/// CreateOffer and a MatchOffers per step with makers that share no accounts
+/// fit in a legacy transaction up to this many steps.
+pub const MAX_ROUTE_STEPS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapAmount {
+    /// Spend exactly this much of the taker's token, fees excluded
+    ExactIn(u64),
+    /// Receive at least this much of the other token
+    ExactOut(u64),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RouteError {
+    #[error("Swap amount must be positive")]
+    ZeroAmount,
+
+    #[error("Not enough resting offers to fill the swap")]
+    InsufficientLiquidity,
+}
+
+/// A match of the taker offer, as offering_a, with a resting offer, as offering_b
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RouteStep {
+    pub maker: L3Entry,
+    pub quote: MatchQuote,
+}
+
+/// A taker offer and the resting offers to match it with, in order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Route {
+    /// The taker's side: bids pay quote tokens for base tokens, asks the other way around
+    pub side: Side,
+    pub offer_mint: Pubkey,
+    pub accept_mint: Pubkey,
+    /// The taker offer's `offering`, its holding is funded with this plus `calc_fee` of it
+    pub offering: u64,
+    /// The taker offer's `accept_at_least`
+    pub accept_at_least: u64,
+    pub steps: Vec<RouteStep>,
+    /// `offer_mint` tokens spent, fees included. The rest of the holding is refunded.
+    pub amount_in: u64,
+    /// `accept_mint` tokens credited to the taker
+    pub amount_out: u64,
+    /// Taker fees paid, in `offer_mint`
+    pub fee: u64,
+    /// `offer_mint` tokens paid to the matcher: the fees plus any bonus
+    pub matcher_in: u64,
+    /// `accept_mint` tokens paid to the matcher as bonus
+    pub matcher_out: u64,
+    /// Quote atomics per base atomic, fees included
+    pub price: Price,
+}
+
+/// Best route for the taker on `side` to swap `amount` against `book`'s best
+/// [`MAX_ROUTE_STEPS`] resting offers.
+///
+/// `slot` is the slot the taker offer will be created in, which should be later than
+/// every resting offer's so the taker pays the full taker fee and the makers none.
+pub fn route(
+    book: &OrderBook,
+    side: Side,
+    amount: SwapAmount,
+    slot: Slot,
+) -> Result<Route, RouteError> {
+    let (makers, offer_mint, accept_mint) = match side {
+        Side::Bid => (book.asks(), *book.quote_mint(), *book.base_mint()),
+        Side::Ask => (book.bids(), *book.base_mint(), *book.quote_mint()),
+    };
+    let makers = &makers[..makers.len().min(MAX_ROUTE_STEPS)];
+    // as CreateOffer creates it: later matches derive its limit from the original terms
+    let taker = |offering, accept_at_least| Offer {
+        slot,
+        offering,
+        accept_at_least,
+        offer_mint,
+        accept_mint,
+        original_offering: offering,
+        original_accept_at_least: accept_at_least,
+        ..Offer::default()
+    };
+    let fill = |offering, accept_at_least| simulate(makers, taker(offering, accept_at_least));
+    // what the makers offer and want, the most the taker can get and spend
+    let total = |f: fn(&Offer) -> u64| {
+        makers
+            .iter()
+            .map(|maker| u128::from(f(&maker.offer)))
+            .sum::<u128>()
+            .min(u64::MAX.into()) as u64
+    };
+
+    let (offering, accept_at_least) = match amount {
+        SwapAmount::ExactIn(0) | SwapAmount::ExactOut(0) => return Err(RouteError::ZeroAmount),
+        // the tightest limit that's still filled
+        SwapAmount::ExactIn(offering) => {
+            // any offering fills the first maker with a low enough limit,
+            // overpaying it, so check there's enough to fill
+            if total(|o| o.accept_at_least) < offering {
+                return Err(RouteError::InsufficientLiquidity);
+            }
+            let accept = last_feasible(1, total(|o| o.offering), |accept| {
+                fill(offering, accept).is_some()
+            })
+            .ok_or(RouteError::InsufficientLiquidity)?;
+            (offering, accept)
+        }
+        // the smallest offering that's still filled
+        SwapAmount::ExactOut(accept) => {
+            let max_offering = total(|o| o.accept_at_least);
+            let offering =
+                first_feasible(1, max_offering, |offering| fill(offering, accept).is_some())
+                    .ok_or(RouteError::InsufficientLiquidity)?;
+            (offering, accept)
+        }
+    };
+    // feasible, just checked
+    let steps = fill(offering, accept_at_least).unwrap();
+
+    let sum = |f: fn(&MatchQuote) -> u64| steps.iter().map(|step| f(&step.quote)).sum::<u64>();
+    let fee = sum(|q| q.receipt.a_fee);
+    let amount_in = sum(|q| q.amt_a_gives) + fee;
+    let amount_out = sum(|q| q.receipt.b_to_a);
+    // amount_out > 0 since the taker offer was filled
+    let price = match side {
+        Side::Bid => Price::new(amount_in, amount_out),
+        Side::Ask => Price::new(amount_out, amount_in),
+    }
+    .ok_or(RouteError::InsufficientLiquidity)?;
+    Ok(Route {
+        side,
+        offer_mint,
+        accept_mint,
+        offering,
+        accept_at_least,
+        amount_in,
+        amount_out,
+        fee,
+        matcher_in: sum(|q| q.receipt.a_to_matcher),
+        matcher_out: sum(|q| q.receipt.b_to_matcher),
+        price,
+        steps,
+    })
+}
+
+impl Route {
+    /// CreateOffer for the taker offer, then a MatchOffers per step.
+    /// `accounts` must be for this route's mints and `seed`.
+    /// `matcher_in` and `matcher_out` are the token accounts of `offer_mint` and `accept_mint`
+    /// the fees and bonus are paid to.
+    ///
+    /// Makers at a better price than the taker offer's limit give half of their excess to
+    /// the matcher, see [`Route::matcher_out`]. Unless `matcher_in` and `matcher_out` are
+    /// the taker's own accounts, the taker pays that bonus on top of the fees.
+    /// To pay each maker's own price instead, take them one by one with TakeOffer.
+    ///
+    /// Send them in a single transaction to swap all or nothing:
+    /// if the book changed, a match fails and so does the transaction.
+    /// They fit in one since a route has at most [`MAX_ROUTE_STEPS`] steps.
+    pub fn instructions(
+        &self,
+        accounts: &CreateOfferAccounts,
+        seed: u16,
+        matcher_in: &Pubkey,
+        matcher_out: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Vec<Instruction>, ProgramError> {
+        let (_offer, bump) = try_find_offer_pda_with_program_id(
+            &accounts.owner,
+            &self.offer_mint,
+            &self.accept_mint,
+            seed,
+            program_id,
+        )?;
+        let taker = Offer {
+            offering: self.offering,
+            accept_at_least: self.accept_at_least,
+            seed,
+            bump,
+            owner: accounts.owner,
+            offer_mint: self.offer_mint,
+            accept_mint: self.accept_mint,
+            refund_to: accounts.refund_to,
+            credit_to: accounts.credit_to,
+            refund_rent_to: accounts.refund_rent_to,
+            original_offering: self.offering,
+            original_accept_at_least: self.accept_at_least,
+            ..Offer::default()
+        };
+        let mut res = vec![accounts.instruction_with_program_id(
+            program_id,
+            seed,
+            self.offering,
+            self.accept_at_least,
+            0,
+            [0; MEMO_LEN],
+        )?];
+        for step in &self.steps {
+            let match_accounts = MatchOffersAccounts::new_with_program_id(
+                &taker,
+                &step.maker.offer,
+                matcher_in,
+                matcher_out,
+                program_id,
+            )?;
+            res.push(match_accounts.instruction_with_program_id(program_id)?);
+        }
+        Ok(res)
+    }
+}
+
+/// Matches `taker` with `makers` in order until it's filled,
+/// `None` if it isn't filled by the makers that cross it
+fn simulate(makers: &[L3Entry], mut taker: Offer) -> Option<Vec<RouteStep>> {
+    let mut steps = Vec::new();
+    for maker in makers {
+        if taker.is_closed() {
+            break;
+        }
+        let quote = match MatchQuote::calc(&taker, &maker.offer) {
+            Ok(quote) => quote,
+            // makers are sorted by price, so no later one crosses either
+            Err(SimpleDexError::OffersDontMatch) => break,
+            Err(_) => return None,
+        };
+        taker.offering = quote.offering_a_new_offering;
+        taker.accept_at_least = quote.offering_a_new_accept_at_least;
+        steps.push(RouteStep {
+            maker: *maker,
+            quote,
+        });
+    }
+    match taker.is_closed() {
+        true => Some(steps),
+        false => None,
+    }
+}
+
+/// Largest x in `lo..=hi` for which `feasible` holds, assuming it holds up to some x
+fn last_feasible(lo: u64, hi: u64, feasible: impl Fn(u64) -> bool) -> Option<u64> {
+    if lo > hi || !feasible(lo) {
+        return None;
+    }
+    // feasible(lo), hi is the largest candidate
+    let (mut lo, mut hi) = (lo, hi);
+    while lo < hi {
+        // rounded up, so lo always moves
+        let mid = hi - (hi - lo) / 2;
+        match feasible(mid) {
+            true => lo = mid,
+            false => hi = mid - 1,
+        }
+    }
+    Some(lo)
+}
+
+/// Smallest x in `lo..=hi` for which `feasible` holds, assuming it holds from some x on
+fn first_feasible(lo: u64, hi: u64, feasible: impl Fn(u64) -> bool) -> Option<u64> {
+    if lo > hi || !feasible(hi) {
+        return None;
+    }
+    let (mut lo, mut hi) = (lo, hi);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match feasible(mid) {
+            true => hi = mid,
+            false => lo = mid + 1,
+        }
+    }
+    Some(lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use simpledex::pda::try_find_offer_pda;
+    use simpledex_test_utils::Market;
+    use solana_sdk::{message::Message, packet::PACKET_DATA_SIZE, signature::SIGNATURE_BYTES};
+
+    use super::*;
+
+    fn order_book(m: &Market, offers: &[(Pubkey, Offer)]) -> (OrderBook, Vec<Pubkey>) {
+        let addresses = offers.iter().map(|(address, _)| *address).collect();
+        (
+            OrderBook::new(m.base, m.quote, offers.iter().copied()),
+            addresses,
+        )
+    }
+
+    fn makers(route: &Route) -> Vec<Pubkey> {
+        route.steps.iter().map(|step| step.maker.address).collect()
+    }
+
+    /// `offer` of its own owner, sharing no accounts with other offers
+    fn own_accounts((address, offer): (Pubkey, Offer)) -> (Pubkey, Offer) {
+        let owner = Pubkey::new_unique();
+        let (_, bump) =
+            try_find_offer_pda(&owner, &offer.offer_mint, &offer.accept_mint, 0).unwrap();
+        let offer = Offer {
+            owner,
+            bump,
+            refund_to: Pubkey::new_unique(),
+            credit_to: Pubkey::new_unique(),
+            refund_rent_to: Pubkey::new_unique(),
+            ..offer
+        };
+        (address, offer)
+    }
+
+    fn transaction_size(ixs: &[Instruction], payer: &Pubkey) -> usize {
+        let message = Message::new(ixs, Some(payer));
+        1 + SIGNATURE_BYTES * usize::from(message.header.num_required_signatures)
+            + message.serialize().len()
+    }
+
+    #[test]
+    fn test_route_exact_in() {
+        let m = Market::new();
+        let (book, asks) = order_book(
+            &m,
+            &[
+                // 100 base at 10 quote each, then 100 at 12
+                m.ask(100, 1_000, 1),
+                m.ask(100, 1_200, 2),
+                m.bid(500, 100, 3),
+            ],
+        );
+        let route = route(&book, Side::Bid, SwapAmount::ExactIn(1_600), 10).unwrap();
+        assert_eq!(makers(&route), asks[..2]);
+        assert_eq!((route.offer_mint, route.accept_mint), (m.quote, m.base));
+        // 1_000 quote for the first ask, the other 600 for 50 base of the second
+        assert_eq!(route.offering, 1_600);
+        // the tightest limit filled by the second ask: ceil(133 * 600 / 1_600) = 50
+        assert_eq!(route.accept_at_least, 133);
+        // 100 base from the first ask minus half the excess over
+        // ceil(133 * 1_000 / 1_600) = 84, then 50 from the second
+        assert_eq!(route.amount_out, 92 + 50);
+        assert_eq!(route.matcher_out, 8);
+        // calc_fee(1_000) + calc_fee(600), fees round half up
+        assert_eq!(route.fee, 2);
+        assert_eq!(route.matcher_in, 2);
+        assert_eq!(route.amount_in, 1_602);
+        assert_eq!(route.price, Price::new(1_602, 142).unwrap());
+        assert!(route.steps[0].quote.closes_b && !route.steps[1].quote.closes_b);
+    }
+
+    #[test]
+    fn test_route_exact_out() {
+        let m = Market::new();
+        let (book, bids) = order_book(
+            &m,
+            &[
+                // 100 base at 10 quote each, then 100 at 9
+                m.bid(1_000, 100, 1),
+                m.bid(900, 100, 2),
+            ],
+        );
+        let route = route(&book, Side::Ask, SwapAmount::ExactOut(1_450), 10).unwrap();
+        assert_eq!(makers(&route), bids);
+        assert_eq!((route.offer_mint, route.accept_mint), (m.base, m.quote));
+        // 100 base for the first bid, 62 for the second:
+        // at 161 base, the second bid would need ceil(1_450 * 61 / 161) = 550 for 61 base
+        assert_eq!(route.offering, 162);
+        assert_eq!(route.accept_at_least, 1_450);
+        assert_eq!(route.amount_in, 162);
+        // 1_000 from the first minus half the excess over ceil(1_450 * 100 / 162) = 896,
+        // then ceil(1_450 * 62 / 162) = 555 from the second
+        assert_eq!(route.amount_out, 948 + 555);
+        assert_eq!(route.matcher_out, 52);
+        assert_eq!(route.fee, 0);
+        assert_eq!(route.price, Price::new(1_503, 162).unwrap());
+    }
+
+    #[test]
+    fn test_route_errors() {
+        let m = Market::new();
+        let (book, _) = order_book(&m, &[m.ask(100, 1_000, 1)]);
+        for amount in [SwapAmount::ExactIn(0), SwapAmount::ExactOut(0)] {
+            assert_eq!(
+                route(&book, Side::Bid, amount, 10),
+                Err(RouteError::ZeroAmount)
+            );
+        }
+        for amount in [SwapAmount::ExactIn(1_001), SwapAmount::ExactOut(101)] {
+            assert_eq!(
+                route(&book, Side::Bid, amount, 10),
+                Err(RouteError::InsufficientLiquidity)
+            );
+        }
+        // no bids
+        assert_eq!(
+            route(&book, Side::Ask, SwapAmount::ExactIn(1), 10),
+            Err(RouteError::InsufficientLiquidity)
+        );
+        // exactly the whole ask
+        let all = route(&book, Side::Bid, SwapAmount::ExactIn(1_000), 10).unwrap();
+        assert_eq!((all.amount_out, all.matcher_out), (100, 0));
+    }
+
+    #[test]
+    fn test_route_matches_onchain_terms() {
+        let m = Market::new();
+        // resting offers as loaded from chain, with their original terms set
+        let asks: Vec<(Pubkey, Offer)> = [(3, 50, 1), (11, 170, 2), (19, 330, 3)]
+            .iter()
+            .map(|&(base, quote, slot)| {
+                let (address, ask) = m.ask(base, quote, slot);
+                let ask = Offer {
+                    original_offering: ask.offering,
+                    original_accept_at_least: ask.accept_at_least,
+                    ..ask
+                };
+                (address, ask)
+            })
+            .collect();
+        let (book, _) = order_book(&m, &asks);
+        let route = route(&book, Side::Bid, SwapAmount::ExactIn(400), 10).unwrap();
+        assert_eq!(route.steps.len(), 3);
+
+        // the taker offer as CreateOffer creates it, updated after each match like MatchOffers does
+        let mut taker = Offer {
+            slot: 10,
+            offering: route.offering,
+            accept_at_least: route.accept_at_least,
+            offer_mint: m.quote,
+            accept_mint: m.base,
+            original_offering: route.offering,
+            original_accept_at_least: route.accept_at_least,
+            ..Offer::default()
+        };
+        for step in &route.steps {
+            let quote = MatchQuote::calc(&taker, &step.maker.offer).unwrap();
+            assert_eq!(quote, step.quote);
+            taker = taker.update_offer_matched(quote.amt_a_gives).unwrap();
+        }
+        assert!(taker.is_closed());
+    }
+
+    #[test]
+    fn test_route_max_steps() {
+        let m = Market::new();
+        let offers: Vec<(Pubkey, Offer)> = (0..=MAX_ROUTE_STEPS as u64)
+            .map(|i| own_accounts(m.ask(100, 1_000, i)))
+            .collect();
+        let (book, asks) = order_book(&m, &offers);
+        let base = 100 * MAX_ROUTE_STEPS as u64;
+        // the last ask is out of reach
+        assert_eq!(
+            route(&book, Side::Bid, SwapAmount::ExactOut(base + 1), 10),
+            Err(RouteError::InsufficientLiquidity)
+        );
+        let route = route(&book, Side::Bid, SwapAmount::ExactOut(base), 10).unwrap();
+        assert_eq!(makers(&route), asks[..MAX_ROUTE_STEPS]);
+
+        let owner = Pubkey::new_unique();
+        let accounts = CreateOfferAccounts::new(&owner, &m.quote, &m.base, 0).unwrap();
+        let (matcher_in, matcher_out) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ixs = route
+            .instructions(&accounts, 0, &matcher_in, &matcher_out, &simpledex::id())
+            .unwrap();
+        assert_eq!(ixs.len(), 1 + MAX_ROUTE_STEPS);
+        assert!(transaction_size(&ixs, &owner) <= PACKET_DATA_SIZE);
+
+        // one more step doesn't fit
+        let mut longer = route.clone();
+        longer.steps.push(RouteStep {
+            maker: book.asks()[MAX_ROUTE_STEPS],
+            quote: route.steps[0].quote,
+        });
+        let ixs = longer
+            .instructions(&accounts, 0, &matcher_in, &matcher_out, &simpledex::id())
+            .unwrap();
+        assert!(transaction_size(&ixs, &owner) > PACKET_DATA_SIZE);
+    }
+}
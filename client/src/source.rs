@@ -0,0 +1,66 @@
+//! Where accounts are loaded from
+
+use async_trait::async_trait;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::RpcFilterType,
+    rpc_request::MAX_MULTIPLE_ACCOUNTS,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::error::ClientError;
+
+/// The RPC methods the loaders need
+#[async_trait]
+pub trait AccountSource {
+    /// Same order as `pubkeys`, `None` for accounts that don't exist
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError>;
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError>;
+}
+
+#[async_trait]
+impl AccountSource for RpcClient {
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError> {
+        let mut res = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            res.extend(
+                RpcClient::get_multiple_accounts(self, chunk)
+                    .await
+                    .map_err(ClientError::source)?,
+            );
+        }
+        Ok(res)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                // the default base58 encoding is limited to 128 bytes
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        self.get_program_accounts_with_config(program_id, config)
+            .await
+            .map_err(ClientError::source)
+    }
+}
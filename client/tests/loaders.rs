@@ -0,0 +1,135 @@
+mod helpers;
+
+use helpers::BanksSource;
+use helpers::{create_funded_token_account, create_mint, create_offer, process, program_test};
+use simpledex::{instructions::cancel_offer, state::OfferVersion};
+use simpledex_client::{ClientError, SimpleDexClient};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+#[tokio::test]
+async fn success_load_market_and_offers() {
+    let (mut client, payer, recent_blockhash) = program_test().start().await;
+    let base = create_mint(&mut client, &recent_blockhash, &payer).await;
+    let quote = create_mint(&mut client, &recent_blockhash, &payer).await;
+    let other = create_mint(&mut client, &recent_blockhash, &payer).await;
+
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mut token_accounts = Vec::new();
+    for owner in [&alice, &bob] {
+        let mut accounts = Vec::new();
+        for mint in [&base, &quote, &other] {
+            accounts.push(
+                create_funded_token_account(
+                    &mut client,
+                    &recent_blockhash,
+                    &payer,
+                    mint,
+                    &owner.pubkey(),
+                    1_000,
+                )
+                .await,
+            );
+        }
+        token_accounts.push(accounts);
+    }
+    let (alice_base, alice_quote, alice_other) = (
+        token_accounts[0][0],
+        token_accounts[0][1],
+        token_accounts[0][2],
+    );
+    let (bob_base, bob_quote) = (token_accounts[1][0], token_accounts[1][1]);
+
+    let mut offers = Vec::new();
+    // (owner, pay_from, credit_to, offer_mint, accept_mint, seed, offering)
+    for (owner, pay_from, credit_to, offer_mint, accept_mint, seed, offering) in [
+        (&alice, alice_base, alice_quote, &base, &quote, 0, 100),
+        (&alice, alice_base, alice_quote, &base, &quote, 1, 200),
+        (&alice, alice_quote, alice_base, &quote, &base, 2, 300),
+        (&bob, bob_quote, bob_base, &quote, &base, 0, 400),
+        (&alice, alice_other, alice_quote, &other, &quote, 3, 500),
+    ] {
+        offers.push(
+            create_offer(
+                &mut client,
+                &recent_blockhash,
+                &payer,
+                owner,
+                &pay_from,
+                &credit_to,
+                offer_mint,
+                accept_mint,
+                seed,
+                offering,
+                10,
+            )
+            .await,
+        );
+    }
+
+    // token accounts aren't owned by the program and must be skipped
+    let known_accounts = offers
+        .iter()
+        .chain(token_accounts.iter().flatten())
+        .copied()
+        .collect();
+    let dex = SimpleDexClient::new(BanksSource {
+        client: client.clone(),
+        known_accounts,
+    });
+
+    let market = dex.market(&base, &quote).await.unwrap();
+    let addresses = |offers: &[simpledex_client::LoadedOffer]| {
+        offers.iter().map(|o| o.address).collect::<Vec<_>>()
+    };
+    assert_eq!(addresses(&market.asks), offers[0..2]);
+    assert_eq!(addresses(&market.bids), offers[2..4]);
+    assert!(market
+        .asks
+        .iter()
+        .all(|o| o.offer.offer_mint == base && o.version == OfferVersion::CURRENT));
+
+    let alices = dex.offers_by_owner(&alice.pubkey()).await.unwrap();
+    assert_eq!(
+        addresses(&alices),
+        [offers[0], offers[1], offers[2], offers[4]]
+    );
+    let bobs = dex.offers_by_owner(&bob.pubkey()).await.unwrap();
+    assert_eq!(addresses(&bobs), [offers[3]]);
+
+    let loaded = dex.offer(&offers[1]).await.unwrap();
+    assert_eq!(loaded.offer.offer.offering, 200);
+    assert_eq!(loaded.offer.offer.owner, alice.pubkey());
+    assert_eq!(loaded.holding, loaded.offer.holding());
+    assert_eq!(loaded.holding_amount, 200);
+
+    let missing = Pubkey::new_unique();
+    let loaded = dex.offers(&[offers[3], missing, offers[4]]).await.unwrap();
+    assert_eq!(loaded[0].unwrap().holding_amount, 400);
+    assert!(loaded[1].is_none());
+    assert_eq!(loaded[2].unwrap().offer.offer.offer_mint, other);
+    assert!(matches!(
+        dex.offer(&missing).await,
+        Err(ClientError::AccountNotFound(address)) if address == missing
+    ));
+    // not an offer
+    assert!(matches!(
+        dex.offer(&alice_base).await,
+        Err(ClientError::IncorrectOwner { .. })
+    ));
+
+    // closed offers drop out
+    let cancel_ix = cancel_offer(&dex.offer(&offers[0]).await.unwrap().offer.offer).unwrap();
+    process(
+        &mut client,
+        &recent_blockhash,
+        &payer,
+        &[cancel_ix],
+        &[&alice],
+    )
+    .await;
+    let asks = dex.offers_by_pair(&base, &quote).await.unwrap();
+    assert_eq!(addresses(&asks), [offers[1]]);
+    assert!(dex.offers(&[offers[0]]).await.unwrap()[0].is_none());
+}
@@ -0,0 +1,127 @@
+mod helpers;
+
+use helpers::{
+    create_funded_token_account, create_mint, create_offer, process, program_test, BanksSource,
+};
+use simpledex::instructions::CreateOfferAccounts;
+use simpledex_client::{route, OrderBook, Side, SimpleDexClient, SwapAmount};
+use solana_program_test::tokio;
+use solana_sdk::{clock::Clock, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+#[tokio::test]
+async fn success_execute_route() {
+    let mut context = program_test().start_with_context().await;
+    let payer = Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+    let recent_blockhash = context.last_blockhash;
+    let mut client = context.banks_client.clone();
+    let base = create_mint(&mut client, &recent_blockhash, &payer).await;
+    let quote = create_mint(&mut client, &recent_blockhash, &payer).await;
+
+    let owners: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
+    let (makers, taker) = (&owners[..2], &owners[2]);
+    // (base_account, quote_account) of the makers, the taker and the matcher
+    let mut accounts = Vec::new();
+    for (i, owner) in owners.iter().enumerate() {
+        let (base_amount, quote_amount) = match i {
+            0 | 1 => (1_000, 0),
+            2 => (0, 2_000),
+            _ => (0, 0),
+        };
+        let mut owner_accounts = Vec::new();
+        for (mint, amount) in [(&base, base_amount), (&quote, quote_amount)] {
+            owner_accounts.push(
+                create_funded_token_account(
+                    &mut client,
+                    &recent_blockhash,
+                    &payer,
+                    mint,
+                    &owner.pubkey(),
+                    amount,
+                )
+                .await,
+            );
+        }
+        accounts.push(owner_accounts);
+    }
+    let mut asks = Vec::new();
+    // 100 base at 10 quote each, then 100 at 12
+    for (i, accept_at_least) in [(0, 1_000), (1, 1_200)] {
+        asks.push(
+            create_offer(
+                &mut client,
+                &recent_blockhash,
+                &payer,
+                &makers[i],
+                &accounts[i][0],
+                &accounts[i][1],
+                &base,
+                &quote,
+                0,
+                100,
+                accept_at_least,
+            )
+            .await,
+        );
+    }
+
+    // the taker offer must come after the asks to pay the taker fee
+    let slot = client.get_sysvar::<Clock>().await.unwrap().slot;
+    context.warp_to_slot(slot + 10).unwrap();
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut client = context.banks_client.clone();
+    let slot = client.get_sysvar::<Clock>().await.unwrap().slot;
+
+    let dex = SimpleDexClient::new(BanksSource {
+        client: client.clone(),
+        known_accounts: asks.clone(),
+    });
+    let market = dex.market(&base, &quote).await.unwrap();
+    let book = OrderBook::from_market(base, quote, &market);
+    let route = route(&book, Side::Bid, SwapAmount::ExactIn(1_600), slot).unwrap();
+    assert_eq!(route.steps.len(), 2);
+
+    let (taker_base, taker_quote) = (accounts[2][0], accounts[2][1]);
+    let (matcher_base, matcher_quote) = (accounts[3][0], accounts[3][1]);
+    let offer_accounts = CreateOfferAccounts::new(&taker.pubkey(), &quote, &base, 0)
+        .unwrap()
+        .payer(&payer.pubkey())
+        .pay_from(&taker_quote)
+        .refund_to(&taker_quote)
+        .credit_to(&taker_base);
+    let ixs = route
+        .instructions(
+            &offer_accounts,
+            0,
+            &matcher_quote,
+            &matcher_base,
+            &simpledex::id(),
+        )
+        .unwrap();
+    process(&mut client, &recent_blockhash, &payer, &ixs, &[taker]).await;
+
+    let balance = |account: Pubkey| {
+        let mut client = client.clone();
+        async move {
+            client
+                .get_packed_account_data::<spl_token::state::Account>(account)
+                .await
+                .unwrap()
+                .amount
+        }
+    };
+    // the quote is exact
+    assert_eq!(balance(taker_quote).await, 2_000 - route.amount_in);
+    assert_eq!(balance(taker_base).await, route.amount_out);
+    assert_eq!(balance(matcher_quote).await, route.matcher_in);
+    assert_eq!(balance(matcher_base).await, route.matcher_out);
+    assert!(route.amount_out > 0 && route.fee > 0);
+    // the taker offer is filled and closed, the first ask too
+    assert!(client
+        .get_account(offer_accounts.offer)
+        .await
+        .unwrap()
+        .is_none());
+    let market = dex.market(&base, &quote).await.unwrap();
+    let remaining: Vec<_> = market.asks.iter().map(|o| o.address).collect();
+    assert_eq!(remaining, [asks[1]]);
+}
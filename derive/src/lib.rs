@@ -0,0 +1,306 @@
+//! Derive macros for `simpledex::packun`'s `SerializePacked`, `DeserializePacked` and `PackedLen`.
+//!
+//! The generated code refers to `crate::packun` and `crate::error::SimpleDexError`,
+//! so these are only meant to be used from within the simpledex program crate.
+//!
+//! Structs are packed field by field in declaration order.
+//! Enums are packed as a `u8` tag followed by the variant's fields in declaration order.
+//! The tag is the variant's explicit discriminant if it has one, else its index.
+//!
+//! `#[derive(DeserializePacked)]` also implements `PackedLen` and `PackedLayout`, and adds the inherent consts:
+//! - structs: `PACKED_LEN`
+//! - enums: `PACKED_LEN_<VARIANT>` for each variant, e.g. `PACKED_LEN_CREATE_OFFER`, and `PACKED_LEN_MAX`
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident, Variant};
+
+#[proc_macro_derive(SerializePacked)]
+pub fn derive_serialize_packed(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_serialize(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(DeserializePacked)]
+pub fn derive_deserialize_packed(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_deserialize(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn check_no_generics(input: &DeriveInput) -> Result<(), Error> {
+    match input.generics.params.is_empty() {
+        true => Ok(()),
+        false => Err(Error::new_spanned(
+            &input.generics,
+            "packed (de)serialization can't be derived for generic types",
+        )),
+    }
+}
+
+/// `(binding, type)` of each field, bindings are `_0, _1..` for tuple fields
+fn field_bindings(fields: &Fields) -> Vec<(Ident, &syn::Type)> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| match &f.ident {
+            Some(ident) => (ident.clone(), &f.ty),
+            None => (format_ident!("_{}", i), &f.ty),
+        })
+        .collect()
+}
+
+/// pattern/constructor body for `fields` using the bindings from [`field_bindings`]
+fn destructure(fields: &Fields, bindings: &[(Ident, &syn::Type)]) -> TokenStream2 {
+    let idents = bindings.iter().map(|(ident, _)| ident);
+    match fields {
+        Fields::Named(_) => quote! { { #(#idents),* } },
+        Fields::Unnamed(_) => quote! { ( #(#idents),* ) },
+        Fields::Unit => quote! {},
+    }
+}
+
+fn fields_packed_len(bindings: &[(Ident, &syn::Type)]) -> TokenStream2 {
+    let tys = bindings.iter().map(|(_, ty)| ty);
+    quote! { 0 #(+ <#tys as crate::packun::PackedLen>::PACKED_LEN)* }
+}
+
+/// `vec![FieldDef { .. }, ..]` describing `fields`
+fn field_defs(fields: &Fields, bindings: &[(Ident, &syn::Type)]) -> TokenStream2 {
+    let defs = bindings.iter().map(|(ident, ty)| {
+        let name = match fields {
+            Fields::Named(_) => {
+                let name = ident.to_string();
+                quote! { Some(#name) }
+            }
+            _ => quote! { None },
+        };
+        quote! {
+            crate::packun::FieldDef {
+                name: #name,
+                layout: <#ty as crate::packun::PackedLayout>::layout(),
+            }
+        }
+    });
+    quote! { vec![#(#defs),*] }
+}
+
+fn variant_tag(variant: &Variant, index: usize) -> Result<TokenStream2, Error> {
+    match &variant.discriminant {
+        Some((_, expr)) => Ok(quote! { (#expr) as u8 }),
+        None => {
+            if index > u8::MAX as usize {
+                return Err(Error::new_spanned(
+                    variant,
+                    "too many variants for a u8 tag",
+                ));
+            }
+            let tag = index as u8;
+            Ok(quote! { #tag })
+        }
+    }
+}
+
+/// `CreateOffer` -> `CREATE_OFFER`
+fn screaming_snake_case(ident: &Ident) -> String {
+    let mut res = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            res.push('_');
+        }
+        res.extend(c.to_uppercase());
+    }
+    res
+}
+
+fn expand_serialize(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    check_no_generics(input)?;
+    let name = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let bindings = field_bindings(&data.fields);
+            let pattern = destructure(&data.fields, &bindings);
+            let idents = bindings.iter().map(|(ident, _)| ident);
+            quote! {
+                let Self #pattern = self;
+                #(crate::packun::SerializePacked::<W, crate::error::SimpleDexError>::write_bytes(#idents, buf)?;)*
+                Ok(())
+            }
+        }
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for (i, variant) in data.variants.iter().enumerate() {
+                let variant_name = &variant.ident;
+                let tag = variant_tag(variant, i)?;
+                let bindings = field_bindings(&variant.fields);
+                let pattern = destructure(&variant.fields, &bindings);
+                let idents = bindings.iter().map(|(ident, _)| ident);
+                arms.push(quote! {
+                    Self::#variant_name #pattern => {
+                        crate::packun::SerializePacked::<W, crate::error::SimpleDexError>::write_bytes(&(#tag), buf)?;
+                        #(crate::packun::SerializePacked::<W, crate::error::SimpleDexError>::write_bytes(#idents, buf)?;)*
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+                Ok(())
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "packed (de)serialization can't be derived for unions",
+            ))
+        }
+    };
+    Ok(quote! {
+        impl<W: std::io::Write> crate::packun::SerializePacked<W, crate::error::SimpleDexError> for #name {
+            fn write_bytes(&self, buf: &mut W) -> Result<(), crate::error::SimpleDexError> {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_deserialize(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    check_no_generics(input)?;
+    let name = &input.ident;
+    let (body, len_impls) = match &input.data {
+        Data::Struct(data) => {
+            let bindings = field_bindings(&data.fields);
+            let construct = destructure(&data.fields, &bindings);
+            let reads = bindings.iter().map(|(ident, ty)| {
+                quote! {
+                    let #ident = <#ty as crate::packun::DeserializePacked<R, crate::error::SimpleDexError>>::read_bytes(buf)?;
+                }
+            });
+            let packed_len = fields_packed_len(&bindings);
+            let fields = field_defs(&data.fields, &bindings);
+            (
+                quote! {
+                    #(#reads)*
+                    Ok(Self #construct)
+                },
+                quote! {
+                    impl crate::packun::PackedLen for #name {
+                        const PACKED_LEN: usize = #packed_len;
+                    }
+
+                    impl crate::packun::PackedLayout for #name {
+                        fn layout() -> crate::packun::Layout {
+                            crate::packun::Layout::Defined(stringify!(#name))
+                        }
+
+                        fn type_def() -> Option<crate::packun::TypeDef> {
+                            Some(crate::packun::TypeDef::Struct {
+                                name: stringify!(#name),
+                                fields: #fields,
+                            })
+                        }
+                    }
+
+                    impl #name {
+                        pub const PACKED_LEN: usize = <Self as crate::packun::PackedLen>::PACKED_LEN;
+                    }
+                },
+            )
+        }
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            let mut len_consts = Vec::new();
+            let mut len_const_idents = Vec::new();
+            let mut variant_defs = Vec::new();
+            for (i, variant) in data.variants.iter().enumerate() {
+                let variant_name = &variant.ident;
+                let tag = variant_tag(variant, i)?;
+                let bindings = field_bindings(&variant.fields);
+                let construct = destructure(&variant.fields, &bindings);
+                let reads = bindings.iter().map(|(ident, ty)| {
+                    quote! {
+                        let #ident = <#ty as crate::packun::DeserializePacked<R, crate::error::SimpleDexError>>::read_bytes(buf)?;
+                    }
+                });
+                arms.push(quote! {
+                    t if t == #tag => {
+                        #(#reads)*
+                        Ok(Self::#variant_name #construct)
+                    }
+                });
+                let const_ident =
+                    format_ident!("PACKED_LEN_{}", screaming_snake_case(variant_name));
+                let packed_len = fields_packed_len(&bindings);
+                len_consts.push(quote! {
+                    pub const #const_ident: usize = 1 + #packed_len;
+                });
+                len_const_idents.push(const_ident);
+                let fields = field_defs(&variant.fields, &bindings);
+                variant_defs.push(quote! {
+                    crate::packun::VariantDef {
+                        name: stringify!(#variant_name),
+                        tag: #tag,
+                        fields: #fields,
+                    }
+                });
+            }
+            let max = len_const_idents.iter().fold(quote! { 0 }, |acc, ident| {
+                quote! { crate::packun::const_max(#acc, Self::#ident) }
+            });
+            (
+                quote! {
+                    let tag = <u8 as crate::packun::DeserializePacked<R, crate::error::SimpleDexError>>::read_bytes(buf)?;
+                    match tag {
+                        #(#arms)*
+                        _ => Err(crate::error::SimpleDexError::PackunError),
+                    }
+                },
+                quote! {
+                    impl crate::packun::PackedLen for #name {
+                        const PACKED_LEN: usize = Self::PACKED_LEN_MAX;
+                    }
+
+                    impl crate::packun::PackedLayout for #name {
+                        fn layout() -> crate::packun::Layout {
+                            crate::packun::Layout::Defined(stringify!(#name))
+                        }
+
+                        fn type_def() -> Option<crate::packun::TypeDef> {
+                            Some(crate::packun::TypeDef::Enum {
+                                name: stringify!(#name),
+                                variants: vec![#(#variant_defs),*],
+                            })
+                        }
+                    }
+
+                    impl #name {
+                        #(#len_consts)*
+
+                        /// Packed length of the largest variant
+                        pub const PACKED_LEN_MAX: usize = #max;
+                    }
+                },
+            )
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "packed (de)serialization can't be derived for unions",
+            ))
+        }
+    };
+    Ok(quote! {
+        impl<R: std::io::Read> crate::packun::DeserializePacked<R, crate::error::SimpleDexError> for #name {
+            fn read_bytes(buf: &mut R) -> Result<Self, crate::error::SimpleDexError> {
+                #body
+            }
+        }
+
+        #len_impls
+    })
+}
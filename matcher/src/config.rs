@@ -0,0 +1,146 @@
+//! Matcher configuration, read from a TOML file:
+//!
+//! ```toml
+//! rpc_url = "http://127.0.0.1:8899"
+//! keypair = "matcher.json"
+//! poll_interval_ms = 1000
+//! fee_lanes = 4
+//! compute_unit_price = 1000
+//! lookup_table = "8qW1ZVtRJ5rhTvZvZmzVKpWgmWPRrfEPRh5sJ8u8CqTZ"
+//!
+//! [[pairs]]
+//! base_mint = "So11111111111111111111111111111111111111112"
+//! quote_mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+//! # quote atomics
+//! min_profit = 1000
+//! ```
+
+use std::{fs, path::Path, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::MatcherError;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub rpc_url: String,
+    /// path to the matcher's keypair file, which pays for and signs the matches
+    pub keypair: String,
+    /// defaults to `simpledex::id()`
+    #[serde(default = "simpledex::id", deserialize_with = "deserialize_pubkey")]
+    pub program_id: Pubkey,
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// fee accounts per mint, see [`Matcher::with_fee_lanes`](crate::Matcher::with_fee_lanes)
+    #[serde(default = "default_fee_lanes")]
+    pub fee_lanes: usize,
+    /// priority fee in micro-lamports per compute unit
+    #[serde(default)]
+    pub compute_unit_price: u64,
+    /// table of the matcher's hot accounts,
+    /// see [`Matcher::hot_accounts`](crate::Matcher::hot_accounts)
+    #[serde(default, deserialize_with = "deserialize_optional_pubkey")]
+    pub lookup_table: Option<Pubkey>,
+    pub pairs: Vec<PairConfig>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PairConfig {
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub base_mint: Pubkey,
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub quote_mint: Pubkey,
+    /// matches earning less fees and bonus than this, valued in quote atomics, are skipped
+    #[serde(default)]
+    pub min_profit: u64,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MatcherError> {
+        let s = fs::read_to_string(path).map_err(|e| MatcherError::Config(e.to_string()))?;
+        s.parse()
+    }
+}
+
+impl FromStr for Config {
+    type Err = MatcherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|e| MatcherError::Config(e.to_string()))
+    }
+}
+
+fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_fee_lanes() -> usize {
+    1
+}
+
+/// Pubkeys are base58 strings in the config, not serde's byte arrays
+fn deserialize_pubkey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Pubkey::from_str(&s).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_pubkey<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Pubkey>, D::Error> {
+    deserialize_pubkey(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config: Config = r#"
+            rpc_url = "http://127.0.0.1:8899"
+            keypair = "matcher.json"
+            lookup_table = "8qW1ZVtRJ5rhTvZvZmzVKpWgmWPRrfEPRh5sJ8u8CqTZ"
+
+            [[pairs]]
+            base_mint = "So11111111111111111111111111111111111111112"
+            quote_mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+            min_profit = 1000
+
+            [[pairs]]
+            base_mint = "4Rf9mGD7FeYknun5JczX5nGLTfQuS1GRjNVfkEMKE92b"
+            quote_mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(config.program_id, simpledex::id());
+        assert_eq!(config.poll_interval_ms, 1000);
+        assert_eq!(config.fee_lanes, 1);
+        assert_eq!(config.compute_unit_price, 0);
+        assert_eq!(
+            config.lookup_table,
+            Some(Pubkey::from_str("8qW1ZVtRJ5rhTvZvZmzVKpWgmWPRrfEPRh5sJ8u8CqTZ").unwrap())
+        );
+        assert_eq!(config.pairs.len(), 2);
+        assert_eq!(config.pairs[0].min_profit, 1000);
+        assert_eq!(config.pairs[1].min_profit, 0);
+        assert_eq!(
+            config.pairs[0].base_mint,
+            Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap()
+        );
+
+        let bad_mint = r#"
+            rpc_url = "http://127.0.0.1:8899"
+            keypair = "matcher.json"
+            [[pairs]]
+            base_mint = "not a pubkey"
+            quote_mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        "#;
+        assert!(matches!(
+            bad_mint.parse::<Config>(),
+            Err(MatcherError::Config(_))
+        ));
+    }
+}
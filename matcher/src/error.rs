@@ -0,0 +1,35 @@
+//! Error types
+
+use simpledex_client::ClientError;
+use solana_sdk::{message::CompileError, program_error::ProgramError, pubkey::PubkeyError};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MatcherError {
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error(transparent)]
+    Client(#[from] ClientError),
+
+    #[error("Transaction sender error: {0}")]
+    Sender(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Could not build instruction: {0}")]
+    Instruction(#[from] ProgramError),
+
+    #[error("Could not derive offer address: {0}")]
+    Pubkey(#[from] PubkeyError),
+
+    #[error("Could not compile transaction: {0}")]
+    Compile(#[from] CompileError),
+
+    #[error("Instruction {0} doesn't fit in a transaction")]
+    InstructionTooLarge(usize),
+}
+
+impl MatcherError {
+    pub fn sender<E: std::error::Error + Send + Sync + 'static>(err: E) -> Self {
+        Self::Sender(Box::new(err))
+    }
+}
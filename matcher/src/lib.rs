@@ -0,0 +1,20 @@
+//! Reference matcher for simpledex.
+//!
+//! [`Matcher`] polls the configured pairs with [`simpledex_client`], finds crossing offers
+//! and sends the most profitable matches, see [`strategy`] for how they're picked,
+//! [`schedule`] for how they're sent concurrently and [`pack`] for how they're packed
+//! into transactions.
+//! The `simpledex-matcher` binary runs it with a [`Config`] read from a TOML file.
+
+pub mod config;
+pub mod error;
+pub mod matcher;
+pub mod pack;
+pub mod schedule;
+pub mod sender;
+pub mod strategy;
+
+pub use config::{Config, PairConfig};
+pub use error::MatcherError;
+pub use matcher::Matcher;
+pub use sender::TransactionSender;
@@ -0,0 +1,72 @@
+use std::{env, process, time::Duration};
+
+use simpledex_client::SimpleDexClient;
+use simpledex_matcher::{Config, Matcher, MatcherError};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::read_keypair_file};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (path, create_lookup_table) = match args.as_slice() {
+        [path] => (path, false),
+        [path, command] if command == "create-lookup-table" => (path, true),
+        _ => {
+            eprintln!("usage: simpledex-matcher <CONFIG.toml> [create-lookup-table]");
+            process::exit(1);
+        }
+    };
+    let config = Config::load(path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+    let payer = read_keypair_file(&config.keypair).unwrap_or_else(|err| {
+        eprintln!("Could not read keypair {}: {}", config.keypair, err);
+        process::exit(1);
+    });
+    let rpc_url = config.rpc_url.clone();
+    let rpc = || RpcClient::new_with_commitment(rpc_url.clone(), CommitmentConfig::confirmed());
+    let client = SimpleDexClient::new_with_program_id(rpc(), config.program_id);
+    let mut matcher = Matcher::new(client, rpc(), payer, config.pairs)
+        .with_fee_lanes(config.fee_lanes)
+        .with_compute_unit_price(config.compute_unit_price);
+
+    match matcher.create_missing_fee_accounts().await {
+        Ok(created) => {
+            for ata in created {
+                log::info!("created fee account {}", ata);
+            }
+        }
+        Err(err) => {
+            eprintln!("Could not create fee accounts: {}", err);
+            process::exit(1);
+        }
+    }
+    if create_lookup_table {
+        let table = match rpc().get_slot().await {
+            Ok(slot) => matcher.create_lookup_table(slot).await,
+            Err(err) => Err(MatcherError::sender(err)),
+        };
+        match table {
+            Ok(table) => println!("{}", table.key),
+            Err(err) => {
+                eprintln!("Could not create lookup table: {}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(address) = config.lookup_table {
+        match matcher.load_lookup_table(&address).await {
+            Ok(table) => matcher = matcher.with_lookup_tables(vec![table]),
+            Err(err) => {
+                eprintln!("Could not load lookup table {}: {}", address, err);
+                process::exit(1);
+            }
+        }
+    }
+    matcher
+        .run(Duration::from_millis(config.poll_interval_ms))
+        .await;
+}
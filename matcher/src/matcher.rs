@@ -0,0 +1,379 @@
+//! The matching loop
+
+use std::{collections::BTreeSet, time::Duration};
+
+use futures::future::join_all;
+use simpledex::instructions::MatchOffersAccounts;
+use simpledex_client::{AccountSource, ClientError, OrderBook, SimpleDexClient};
+use solana_address_lookup_table_program::{
+    instruction::{create_lookup_table, extend_lookup_table},
+    state::AddressLookupTable,
+};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    clock::Slot,
+    hash::Hash,
+    instruction::Instruction,
+    message::VersionedMessage,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+
+use crate::{
+    config::PairConfig,
+    error::MatcherError,
+    pack::{simulated_budget, Packed, TxPacker},
+    schedule::{schedule, write_set},
+    sender::TransactionSender,
+    strategy::find_matches,
+};
+
+/// Fee accounts created per transaction, keeps it well under the size limit
+const FEE_ACCOUNTS_PER_TX: usize = 4;
+
+/// Addresses added to a lookup table per transaction, keeps it well under the size limit
+const LOOKUP_TABLE_ADDRESSES_PER_TX: usize = 20;
+
+/// Keeps [`fee_account_seed`] within the 32 byte seed limit
+pub const MAX_FEE_LANES: usize = 64;
+
+/// Polls the configured pairs and matches their crossing offers,
+/// crediting the fees and bonus to `payer`'s token accounts
+pub struct Matcher<S, T> {
+    client: SimpleDexClient<S>,
+    sender: T,
+    payer: Keypair,
+    pairs: Vec<PairConfig>,
+    fee_lanes: usize,
+    compute_unit_price: u64,
+    lookup_tables: Vec<AddressLookupTableAccount>,
+}
+
+impl<S: AccountSource + Sync, T: TransactionSender + Sync> Matcher<S, T> {
+    pub fn new(
+        client: SimpleDexClient<S>,
+        sender: T,
+        payer: Keypair,
+        pairs: Vec<PairConfig>,
+    ) -> Self {
+        Self {
+            client,
+            sender,
+            payer,
+            pairs,
+            fee_lanes: 1,
+            compute_unit_price: 0,
+            lookup_tables: Vec::new(),
+        }
+    }
+
+    /// Number of fee accounts per mint to spread matches over, 1 by default,
+    /// clamped to `1..=MAX_FEE_LANES`.
+    /// Matches crediting the same fee account can't land concurrently,
+    /// so with a single lane a pair's matches land one after the other.
+    pub fn with_fee_lanes(mut self, fee_lanes: usize) -> Self {
+        self.fee_lanes = fee_lanes.clamp(1, MAX_FEE_LANES);
+        self
+    }
+
+    /// Priority fee of the matches in micro-lamports per compute unit, none by default
+    pub fn with_compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = micro_lamports;
+        self
+    }
+
+    /// Tables to look up the matches' accounts in, making room for more matches per
+    /// transaction, see [`Self::hot_accounts`] and [`Self::load_lookup_table`]
+    pub fn with_lookup_tables(mut self, lookup_tables: Vec<AddressLookupTableAccount>) -> Self {
+        self.lookup_tables = lookup_tables;
+        self
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    pub fn client(&self) -> &SimpleDexClient<S> {
+        &self.client
+    }
+
+    /// Token account `mint` fees of `lane` are credited to: the payer's ATA for lane 0,
+    /// an account derived from the payer with [`fee_account_seed`] for the others
+    pub fn fee_account(&self, mint: &Pubkey, lane: usize) -> Pubkey {
+        match lane {
+            0 => get_associated_token_address(&self.payer(), mint),
+            // the seed is at most 32 bytes, so this can't fail
+            _ => Pubkey::create_with_seed(
+                &self.payer(),
+                &fee_account_seed(mint, lane),
+                &spl_token::id(),
+            )
+            .unwrap(),
+        }
+    }
+
+    /// Accounts in most matches, worth putting in a lookup table:
+    /// the token program and the fee accounts of every lane for the configured pairs' mints
+    pub fn hot_accounts(&self) -> Vec<Pubkey> {
+        let fee_accounts = self.fee_accounts().into_iter();
+        std::iter::once(spl_token::id())
+            .chain(fee_accounts.map(|(_, _, address)| address))
+            .collect()
+    }
+
+    /// Creates a lookup table of [`Self::hot_accounts`] owned by the payer.
+    /// `recent_slot` must be a recent rooted slot, e.g. from `getSlot`.
+    ///
+    /// The table can be used from the slot after its addresses were added.
+    pub async fn create_lookup_table(
+        &self,
+        recent_slot: Slot,
+    ) -> Result<AddressLookupTableAccount, MatcherError> {
+        let payer = self.payer();
+        let (create_ix, key) = create_lookup_table(payer, payer, recent_slot);
+        let addresses = self.hot_accounts();
+        for (i, chunk) in addresses.chunks(LOOKUP_TABLE_ADDRESSES_PER_TX).enumerate() {
+            let extend_ix = extend_lookup_table(key, payer, Some(payer), chunk.to_vec());
+            match i {
+                0 => self.send(&[create_ix.clone(), extend_ix]).await?,
+                _ => self.send(&[extend_ix]).await?,
+            };
+        }
+        Ok(AddressLookupTableAccount { key, addresses })
+    }
+
+    /// Fetches the lookup table at `address`
+    pub async fn load_lookup_table(
+        &self,
+        address: &Pubkey,
+    ) -> Result<AddressLookupTableAccount, MatcherError> {
+        let account = self
+            .client
+            .source()
+            .get_multiple_accounts(&[*address])
+            .await?
+            .pop()
+            .flatten()
+            .ok_or(ClientError::AccountNotFound(*address))?;
+        let table = AddressLookupTable::deserialize(&account.data).map_err(|_| {
+            ClientError::InvalidAccountData {
+                address: *address,
+                err: ProgramError::InvalidAccountData,
+            }
+        })?;
+        Ok(AddressLookupTableAccount {
+            key: *address,
+            addresses: table.addresses.to_vec(),
+        })
+    }
+
+    /// Creates the fee accounts of every lane for the configured pairs' mints
+    /// that don't exist yet, returns the created accounts
+    pub async fn create_missing_fee_accounts(&self) -> Result<Vec<Pubkey>, MatcherError> {
+        let wanted = self.fee_accounts();
+        let addresses: Vec<Pubkey> = wanted.iter().map(|(_, _, address)| *address).collect();
+        let accounts = self
+            .client
+            .source()
+            .get_multiple_accounts(&addresses)
+            .await?;
+        let missing: Vec<_> = wanted
+            .into_iter()
+            .zip(accounts)
+            .filter(|(_, account)| account.is_none())
+            .map(|(wanted, _)| wanted)
+            .collect();
+        for chunk in missing.chunks(FEE_ACCOUNTS_PER_TX) {
+            let ixs: Vec<Instruction> = chunk
+                .iter()
+                .flat_map(|(mint, lane, address)| self.create_fee_account(mint, *lane, address))
+                .collect();
+            self.send(&ixs).await?;
+        }
+        Ok(missing.into_iter().map(|(_, _, address)| address).collect())
+    }
+
+    /// Loads each pair's book and sends its matches, concurrently where their
+    /// write sets allow, see [`crate::schedule`].
+    /// Matches that can land concurrently are packed into as few transactions as fit,
+    /// see [`crate::pack`], which land or fail together, so each match is simulated first
+    /// and those that would fail, e.g. because another matcher got there first, are skipped.
+    /// The simulations also measure the compute units each transaction requests.
+    /// Matches that still fail to land are logged and skipped. Returns the landed transactions.
+    pub async fn run_once(&self) -> Result<Vec<Signature>, MatcherError> {
+        let program_id = self.client.program_id();
+        let mut jobs = Vec::new();
+        for pair in &self.pairs {
+            let market = self
+                .client
+                .market(&pair.base_mint, &pair.quote_mint)
+                .await?;
+            let book = OrderBook::from_market(pair.base_mint, pair.quote_mint, &market);
+            for candidate in find_matches(&book, pair.min_profit) {
+                let lane = jobs.len() % self.fee_lanes;
+                let accounts = MatchOffersAccounts::new_with_program_id(
+                    &candidate.ask.offer,
+                    &candidate.bid.offer,
+                    &self.fee_account(&pair.base_mint, lane),
+                    &self.fee_account(&pair.quote_mint, lane),
+                    program_id,
+                )?;
+                let write_set = write_set(&accounts);
+                jobs.push(((candidate, accounts), write_set));
+            }
+        }
+
+        let packer = TxPacker::new(self.payer())
+            .with_compute_unit_price(self.compute_unit_price)
+            .with_lookup_tables(self.lookup_tables.clone());
+        let mut res = Vec::new();
+        for batch in schedule(jobs) {
+            let ixs = batch
+                .iter()
+                .map(|(_, accounts)| accounts.instruction_with_program_id(program_id))
+                .collect::<Result<Vec<_>, _>>()?;
+            let recent_blockhash = self.sender.get_latest_blockhash().await?;
+            // a batch's matches don't share writable accounts, so each can be simulated alone
+            let simulations = ixs
+                .iter()
+                .map(|ix| self.simulate(ix.clone(), recent_blockhash));
+            let results = join_all(simulations).await;
+            let mut live = Vec::new();
+            let mut live_ixs = Vec::new();
+            for ((job, ix), result) in batch.iter().zip(ixs).zip(results) {
+                match result {
+                    Ok(units_consumed) => {
+                        live.push(job);
+                        live_ixs.push((ix, simulated_budget(units_consumed)));
+                    }
+                    Err(err) => log::warn!(
+                        "skipped match of {} with {}: {}",
+                        job.0.ask.address,
+                        job.0.bid.address,
+                        err
+                    ),
+                }
+            }
+            let packed = packer.pack(&live_ixs, recent_blockhash)?;
+            let sends = packed
+                .iter()
+                .map(|Packed { message, .. }| self.send_message(message.clone()));
+            for (Packed { instructions, .. }, result) in packed.iter().zip(join_all(sends).await) {
+                for (candidate, _) in &live[instructions.clone()] {
+                    match &result {
+                        Ok(signature) => log::info!(
+                            "matched {} with {}, profit {}: {}",
+                            candidate.ask.address,
+                            candidate.bid.address,
+                            candidate.profit,
+                            signature
+                        ),
+                        Err(err) => log::warn!(
+                            "failed to match {} with {}: {}",
+                            candidate.ask.address,
+                            candidate.bid.address,
+                            err
+                        ),
+                    }
+                }
+                if let Ok(signature) = result {
+                    res.push(signature);
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// [`Self::run_once`] every `poll_interval` forever, logging errors
+    pub async fn run(&self, poll_interval: Duration) {
+        let mut interval = tokio::time::interval(poll_interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.run_once().await {
+                log::error!("{}", err);
+            }
+        }
+    }
+
+    fn create_fee_account(&self, mint: &Pubkey, lane: usize, address: &Pubkey) -> Vec<Instruction> {
+        let payer = self.payer();
+        if lane == 0 {
+            return vec![create_associated_token_account(&payer, &payer, mint)];
+        }
+        let len = spl_token::state::Account::LEN;
+        vec![
+            system_instruction::create_account_with_seed(
+                &payer,
+                address,
+                &payer,
+                &fee_account_seed(mint, lane),
+                // rent has never changed from the default
+                Rent::default().minimum_balance(len),
+                len as u64,
+                &spl_token::id(),
+            ),
+            // only fails for a wrong token program id
+            spl_token::instruction::initialize_account(&spl_token::id(), address, mint, &payer)
+                .unwrap(),
+        ]
+    }
+
+    /// (mint, lane, address) of the fee accounts of every lane for the configured pairs' mints
+    fn fee_accounts(&self) -> Vec<(Pubkey, usize, Pubkey)> {
+        let mints: BTreeSet<Pubkey> = self
+            .pairs
+            .iter()
+            .flat_map(|pair| vec![pair.base_mint, pair.quote_mint])
+            .collect();
+        mints
+            .iter()
+            .flat_map(|mint| (0..self.fee_lanes).map(move |lane| (*mint, lane)))
+            .map(|(mint, lane)| (mint, lane, self.fee_account(&mint, lane)))
+            .collect()
+    }
+
+    /// Simulates `ix` in a transaction of its own, returning the compute units it consumed
+    async fn simulate(
+        &self,
+        ix: Instruction,
+        recent_blockhash: Hash,
+    ) -> Result<Option<u64>, MatcherError> {
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.payer()),
+            &[&self.payer],
+            recent_blockhash,
+        );
+        self.sender.simulate(&tx.into()).await
+    }
+
+    async fn send_message(&self, message: VersionedMessage) -> Result<Signature, MatcherError> {
+        // only fails if the payer isn't the message's only signer
+        let tx = VersionedTransaction::try_new(message, &[&self.payer]).unwrap();
+        self.sender.send_and_confirm(&tx).await
+    }
+
+    async fn send(&self, ixs: &[Instruction]) -> Result<Signature, MatcherError> {
+        let recent_blockhash = self.sender.get_latest_blockhash().await?;
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&self.payer()),
+            &[&self.payer],
+            recent_blockhash,
+        );
+        self.sender.send_and_confirm(&tx.into()).await
+    }
+}
+
+/// Seed of the fee account of `mint` for lanes other than 0,
+/// short enough for `Pubkey::create_with_seed`
+pub fn fee_account_seed(mint: &Pubkey, lane: usize) -> String {
+    format!("fee{}{}", lane, &mint.to_string()[..16])
+}
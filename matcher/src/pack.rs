@@ -0,0 +1,327 @@
+//! Packing instructions into as few transactions as the packet size and compute limits allow.
+//!
+//! Matches sent as one transaction share its signature, fee and accounts:
+//! the token program, the matcher's fee accounts and often a trader's token accounts
+//! are only listed once. They also land or fail together,
+//! so only pack matches that don't depend on each other.
+//!
+//! Each transaction requests the compute units its instructions are budgeted,
+//! instead of the runtime's default of 200k per instruction,
+//! which would cap a transaction at 7 instructions and overpay any priority fee.
+//! Budgets come from simulating each instruction on its own, see [`simulated_budget`].
+
+use std::ops::Range;
+
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use crate::error::MatcherError;
+
+/// Most compute units a transaction can request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Compute units budgeted for an instruction whose simulation didn't report any,
+/// the runtime's default
+pub const DEFAULT_INSTRUCTION_CU: u32 = 200_000;
+
+/// Compute units of each compute budget instruction
+pub const COMPUTE_BUDGET_INSTRUCTION_CU: u32 = 150;
+
+/// Compute units budgeted on top of a simulation's, in percent of them.
+/// The accounts an instruction touches can change between its simulation and landing,
+/// e.g. a match that only partially filled an offer when simulated may close it once landed.
+pub const SIMULATED_CU_MARGIN_PERCENT: u64 = 20;
+
+/// Compute units to budget for an instruction that consumed `units_consumed`
+/// when simulated in a transaction of its own, plus [`SIMULATED_CU_MARGIN_PERCENT`].
+/// [`DEFAULT_INSTRUCTION_CU`] if the simulation didn't report them.
+pub fn simulated_budget(units_consumed: Option<u64>) -> u32 {
+    match units_consumed {
+        Some(units) => {
+            let margin = units.saturating_mul(SIMULATED_CU_MARGIN_PERCENT) / 100;
+            let budget = units.saturating_add(margin);
+            budget.min(u64::from(MAX_COMPUTE_UNIT_LIMIT)) as u32
+        }
+        None => DEFAULT_INSTRUCTION_CU,
+    }
+}
+
+/// A packed transaction's message, signed only by the payer
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packed {
+    /// The packed instructions' indices in the instructions passed to [`TxPacker::pack`]
+    pub instructions: Range<usize>,
+    pub message: VersionedMessage,
+}
+
+/// Packs instructions signed only by `payer` into v0 transactions
+#[derive(Clone, Debug)]
+pub struct TxPacker {
+    payer: Pubkey,
+    lookup_tables: Vec<AddressLookupTableAccount>,
+    compute_unit_price: u64,
+}
+
+impl TxPacker {
+    pub fn new(payer: Pubkey) -> Self {
+        Self {
+            payer,
+            lookup_tables: Vec::new(),
+            compute_unit_price: 0,
+        }
+    }
+
+    /// Tables to load accounts from, each looked up account takes 1 byte instead of 32.
+    /// Signers are never looked up.
+    pub fn with_lookup_tables(mut self, lookup_tables: Vec<AddressLookupTableAccount>) -> Self {
+        self.lookup_tables = lookup_tables;
+        self
+    }
+
+    /// Priority fee in micro-lamports per requested compute unit, none by default
+    pub fn with_compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = micro_lamports;
+        self
+    }
+
+    /// Splits `ixs`, each with the compute units budgeted for it, in order,
+    /// into as few transactions as fit the packet size and the compute limit,
+    /// each prefixed with its compute budget instructions.
+    ///
+    /// Fails if an instruction doesn't fit in a transaction on its own.
+    pub fn pack(
+        &self,
+        ixs: &[(Instruction, u32)],
+        recent_blockhash: Hash,
+    ) -> Result<Vec<Packed>, MatcherError> {
+        let mut res = Vec::new();
+        let mut start = 0;
+        // the last message that fit, of ixs[start..end]
+        let mut fitting: Option<VersionedMessage> = None;
+        let mut end = start;
+        while end < ixs.len() {
+            match self.compile(&ixs[start..=end], recent_blockhash)? {
+                Some(message) => {
+                    fitting = Some(message);
+                    end += 1;
+                }
+                None => match fitting.take() {
+                    Some(message) => {
+                        res.push(Packed {
+                            instructions: start..end,
+                            message,
+                        });
+                        start = end;
+                    }
+                    None => return Err(MatcherError::InstructionTooLarge(end)),
+                },
+            }
+        }
+        if let Some(message) = fitting {
+            res.push(Packed {
+                instructions: start..end,
+                message,
+            });
+        }
+        Ok(res)
+    }
+
+    /// `ixs`' message with its compute budget instructions, if it fits in a transaction
+    fn compile(
+        &self,
+        ixs: &[(Instruction, u32)],
+        recent_blockhash: Hash,
+    ) -> Result<Option<VersionedMessage>, MatcherError> {
+        let mut budget_ixs = Vec::with_capacity(2);
+        if self.compute_unit_price > 0 {
+            budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.compute_unit_price,
+            ));
+        }
+        // placeholder for the limit, which counts itself
+        budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(0));
+        let units = budget_ixs.len() as u64 * u64::from(COMPUTE_BUDGET_INSTRUCTION_CU)
+            + ixs.iter().map(|(_, units)| u64::from(*units)).sum::<u64>();
+        if units > u64::from(MAX_COMPUTE_UNIT_LIMIT) {
+            return Ok(None);
+        }
+        // just checked it's <= MAX_COMPUTE_UNIT_LIMIT
+        *budget_ixs.last_mut().unwrap() =
+            ComputeBudgetInstruction::set_compute_unit_limit(units as u32);
+        budget_ixs.extend(ixs.iter().map(|(ix, _)| ix.clone()));
+
+        let message = v0::Message::try_compile(
+            &self.payer,
+            &budget_ixs,
+            &self.lookup_tables,
+            recent_blockhash,
+        )?;
+        let message = VersionedMessage::V0(message);
+        match transaction_size(&message) <= PACKET_DATA_SIZE {
+            true => Ok(Some(message)),
+            false => Ok(None),
+        }
+    }
+}
+
+/// Serialized size of `message`'s signed transaction
+pub fn transaction_size(message: &VersionedMessage) -> usize {
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures.into()],
+        message: message.clone(),
+    };
+    // only fails for types serde can't serialize
+    bincode::serialized_size(&tx).unwrap() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use simpledex::instructions::MatchOffersAccounts;
+    use solana_sdk::instruction::AccountMeta;
+
+    use super::*;
+
+    /// Budget of each match, large enough that the compute limit
+    /// rather than the packet size caps `test_pack_compute_limit`
+    const MATCH_CU: u32 = 75_000;
+
+    /// MatchOffers between fresh offers and traders, crediting the shared fee accounts
+    fn match_ix(matcher_a: &Pubkey, matcher_b: &Pubkey) -> (Instruction, u32) {
+        let key = Pubkey::new_unique;
+        let ix = MatchOffersAccounts {
+            offering_a: key(),
+            holding_a: key(),
+            offering_b: key(),
+            holding_b: key(),
+            credit_to_a: key(),
+            refund_to_a: key(),
+            refund_rent_to_a: key(),
+            credit_to_b: key(),
+            refund_to_b: key(),
+            refund_rent_to_b: key(),
+            matcher_a: *matcher_a,
+            matcher_b: *matcher_b,
+            token_program: spl_token::id(),
+        }
+        .instruction_with_program_id(&simpledex::id())
+        .unwrap();
+        (ix, MATCH_CU)
+    }
+
+    fn assert_packed(packed: &[Packed], len: usize) {
+        let mut next = 0;
+        for Packed {
+            instructions,
+            message,
+        } in packed
+        {
+            assert_eq!(instructions.start, next);
+            assert!(!instructions.is_empty());
+            next = instructions.end;
+            assert!(transaction_size(message) <= PACKET_DATA_SIZE);
+            // the compute unit limit, then the packed instructions
+            assert_eq!(message.instructions().len(), instructions.len() + 1);
+        }
+        assert_eq!(next, len);
+    }
+
+    #[test]
+    fn test_simulated_budget() {
+        assert_eq!(simulated_budget(Some(50_000)), 60_000);
+        assert_eq!(simulated_budget(Some(0)), 0);
+        assert_eq!(simulated_budget(None), DEFAULT_INSTRUCTION_CU);
+        assert_eq!(simulated_budget(Some(u64::MAX)), MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn test_pack() {
+        let payer = Pubkey::new_unique();
+        let (matcher_a, matcher_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ixs: Vec<(Instruction, u32)> =
+            (0..10).map(|_| match_ix(&matcher_a, &matcher_b)).collect();
+        let packer = TxPacker::new(payer);
+        let packed = packer.pack(&ixs, Hash::default()).unwrap();
+        assert_packed(&packed, ixs.len());
+        // 10 unique accounts per match, the shared ones are only listed once
+        assert_eq!(packed[0].instructions, 0..2);
+        assert_eq!(packed.len(), 5);
+        let keys = packed[0].message.static_account_keys();
+        assert_eq!(keys[0], payer);
+        assert_eq!(keys.iter().filter(|k| **k == matcher_a).count(), 1);
+        assert_eq!(keys.iter().filter(|k| **k == spl_token::id()).count(), 1);
+
+        // the fee accounts and token program are looked up instead
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![spl_token::id(), matcher_a, matcher_b],
+        };
+        let with_table = packer.clone().with_lookup_tables(vec![table]);
+        let packed = with_table.pack(&ixs, Hash::default()).unwrap();
+        assert_packed(&packed, ixs.len());
+        let message = &packed[0].message;
+        assert_eq!(packed[0].instructions, 0..2);
+        assert!(!message.static_account_keys().contains(&matcher_a));
+        assert!(!message.static_account_keys().contains(&spl_token::id()));
+        assert_eq!(message.address_table_lookups().unwrap().len(), 1);
+        let without_table = &packer.pack(&ixs[..2], Hash::default()).unwrap()[0].message;
+        assert!(transaction_size(message) < transaction_size(without_table));
+
+        assert!(packer.pack(&[], Hash::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pack_compute_limit() {
+        let (ix, units) = match_ix(&Pubkey::new_unique(), &Pubkey::new_unique());
+        // small enough for the packet, too many for the compute limit
+        let ix = Instruction::new_with_bytes(simpledex::id(), &ix.data, vec![]);
+        let ixs = vec![(ix, units); 20];
+        let packer = TxPacker::new(Pubkey::new_unique()).with_compute_unit_price(10);
+        let packed = packer.pack(&ixs, Hash::default()).unwrap();
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0].instructions, 0..18);
+        assert_eq!(packed[1].instructions, 18..20);
+
+        let message = &packed[1].message;
+        // the price, then the limit
+        assert_eq!(message.instructions().len(), 4);
+        assert_eq!(
+            message.instructions()[0].data,
+            ComputeBudgetInstruction::set_compute_unit_price(10).data
+        );
+        let units = 2 * COMPUTE_BUDGET_INSTRUCTION_CU + 2 * MATCH_CU;
+        assert_eq!(
+            message.instructions()[1].data,
+            ComputeBudgetInstruction::set_compute_unit_limit(units).data
+        );
+    }
+
+    #[test]
+    fn test_pack_too_large() {
+        let payer = Pubkey::new_unique();
+        let (matcher_a, matcher_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let accounts = (0..40)
+            .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+            .collect();
+        let ixs = [
+            match_ix(&matcher_a, &matcher_b),
+            (
+                Instruction::new_with_bytes(simpledex::id(), &[], accounts),
+                MATCH_CU,
+            ),
+        ];
+        let packer = TxPacker::new(payer);
+        assert!(matches!(
+            packer.pack(&ixs, Hash::default()),
+            Err(MatcherError::InstructionTooLarge(1))
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//! Scheduling matches into batches of transactions that can land concurrently.
+//!
+//! MatchOffers only write-locks the accounts of the 2 offers and the matcher's fee accounts,
+//! so matches with disjoint write sets can land in the same block in any order.
+//! Matches that share a writable account, e.g. a partly filled offer matched again
+//! or 2 offers crediting the same token account, must land one after the other.
+
+use std::collections::HashMap;
+
+use simpledex::instructions::MatchOffersAccounts;
+use solana_sdk::pubkey::Pubkey;
+
+/// Accounts a match write-locks, including the matcher's fee accounts
+pub fn write_set(accounts: &MatchOffersAccounts) -> Vec<Pubkey> {
+    accounts
+        .to_account_metas()
+        .into_iter()
+        .filter(|meta| meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect()
+}
+
+/// Splits jobs, in the order they must land, into batches to send one after the other.
+///
+/// Jobs in the same batch write disjoint accounts, so they can be sent concurrently.
+/// A job goes in the batch right after the last one writing any of its accounts,
+/// so jobs sharing an account stay in order while the others are sent as early as possible.
+pub fn schedule<T>(jobs: impl IntoIterator<Item = (T, Vec<Pubkey>)>) -> Vec<Vec<T>> {
+    let mut batches: Vec<Vec<T>> = Vec::new();
+    // batch index of the last job writing each account
+    let mut last_write: HashMap<Pubkey, usize> = HashMap::new();
+    for (job, write_set) in jobs {
+        let batch = write_set
+            .iter()
+            .filter_map(|account| last_write.get(account))
+            .map(|i| i + 1)
+            .max()
+            .unwrap_or(0);
+        for account in write_set {
+            last_write.insert(account, batch);
+        }
+        if batch == batches.len() {
+            batches.push(Vec::new());
+        }
+        batches[batch].push(job);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_set() {
+        let key = Pubkey::new_unique;
+        let accounts = MatchOffersAccounts {
+            offering_a: key(),
+            holding_a: key(),
+            offering_b: key(),
+            holding_b: key(),
+            credit_to_a: key(),
+            refund_to_a: key(),
+            refund_rent_to_a: key(),
+            credit_to_b: key(),
+            refund_to_b: key(),
+            refund_rent_to_b: key(),
+            matcher_a: key(),
+            matcher_b: key(),
+            token_program: spl_token::id(),
+        };
+        let write_set = write_set(&accounts);
+        assert_eq!(write_set.len(), 12);
+        assert!(write_set.contains(&accounts.offering_a));
+        assert!(write_set.contains(&accounts.holding_b));
+        assert!(write_set.contains(&accounts.refund_rent_to_b));
+        assert!(write_set.contains(&accounts.matcher_a));
+        assert!(!write_set.contains(&accounts.token_program));
+    }
+
+    #[test]
+    fn test_schedule() {
+        let [a, b, c, d, e] = [(); 5].map(|_| Pubkey::new_unique());
+        let batches = schedule(vec![
+            (0, vec![a, b]),
+            (1, vec![c]),
+            // after 0
+            (2, vec![b, d]),
+            // after 2
+            (3, vec![d]),
+            (4, vec![e]),
+            // after 0 and 1, before 3
+            (5, vec![a, c]),
+            // after 3 and 4
+            (6, vec![d, e]),
+        ]);
+        assert_eq!(batches, [vec![0, 1, 4], vec![2, 5], vec![3], vec![6]]);
+
+        assert!(schedule::<u8>(None).is_empty());
+    }
+}
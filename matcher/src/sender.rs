@@ -0,0 +1,46 @@
+//! Where transactions are sent to
+
+use async_trait::async_trait;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{hash::Hash, signature::Signature, transaction::VersionedTransaction};
+
+use crate::error::MatcherError;
+
+/// The RPC methods the matcher needs to land transactions
+#[async_trait]
+pub trait TransactionSender {
+    async fn get_latest_blockhash(&self) -> Result<Hash, MatcherError>;
+
+    /// Simulates `tx` against the latest state, erroring if it would fail.
+    /// Returns the compute units it consumed, if the node reports them.
+    async fn simulate(&self, tx: &VersionedTransaction) -> Result<Option<u64>, MatcherError>;
+
+    /// Sends `tx`, legacy or v0, and waits for it to be confirmed
+    async fn send_and_confirm(&self, tx: &VersionedTransaction) -> Result<Signature, MatcherError>;
+}
+
+#[async_trait]
+impl TransactionSender for RpcClient {
+    async fn get_latest_blockhash(&self) -> Result<Hash, MatcherError> {
+        RpcClient::get_latest_blockhash(self)
+            .await
+            .map_err(MatcherError::sender)
+    }
+
+    async fn simulate(&self, tx: &VersionedTransaction) -> Result<Option<u64>, MatcherError> {
+        let res = self
+            .simulate_transaction(tx)
+            .await
+            .map_err(MatcherError::sender)?;
+        match res.value.err {
+            Some(err) => Err(MatcherError::sender(err)),
+            None => Ok(res.value.units_consumed),
+        }
+    }
+
+    async fn send_and_confirm(&self, tx: &VersionedTransaction) -> Result<Signature, MatcherError> {
+        self.send_and_confirm_transaction(tx)
+            .await
+            .map_err(MatcherError::sender)
+    }
+}
@@ -0,0 +1,191 @@
+//! Finding and ranking matches in a book.
+//!
+//! Matches are quoted with [`MatchQuote::calc`], the same code the program runs,
+//! with the ask as offering_a and the bid as offering_b.
+
+use simpledex::{quote::MatchQuote, state::Offer};
+use simpledex_client::{orderbook::L3Entry, OrderBook};
+
+/// A match the matcher could send
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// offering_a, offers base tokens for quote tokens
+    pub ask: L3Entry,
+    /// offering_b, offers quote tokens for base tokens
+    pub bid: L3Entry,
+    pub quote: MatchQuote,
+    /// fees and bonus paid to the matcher, in quote atomics
+    pub profit: u128,
+}
+
+/// Value of a match's payout to the matcher in quote atomics,
+/// with the base tokens valued at the match's own price.
+/// Token A is base, token B is quote.
+pub fn profit_in_quote(quote: &MatchQuote) -> u128 {
+    let base = u128::from(quote.receipt.a_to_matcher);
+    let base_value = (base * u128::from(quote.amt_b_gives))
+        .checked_div(quote.amt_a_gives.into())
+        .unwrap_or(0);
+    u128::from(quote.receipt.b_to_matcher) + base_value
+}
+
+/// Matches of crossing offers in `book` earning at least `min_profit` quote atomics,
+/// picked greedily, most profitable first, ties in book priority order.
+///
+/// Each match is applied to a copy of the book before picking the next,
+/// so an offer can be in several matches, each quoted against the state the
+/// previous ones leave it in. Such matches must land in order, see [`crate::schedule`].
+pub fn find_matches(book: &OrderBook, min_profit: u64) -> Vec<Candidate> {
+    let mut book = book.clone();
+    let mut res = Vec::new();
+    // every match closes at least one of its offers, so this ends
+    while let Some(candidate) = best_match(&book, min_profit) {
+        let quote = &candidate.quote;
+        book.insert(
+            candidate.ask.address,
+            Offer {
+                offering: quote.offering_a_new_offering,
+                accept_at_least: quote.offering_a_new_accept_at_least,
+                ..candidate.ask.offer
+            },
+        );
+        book.insert(
+            candidate.bid.address,
+            Offer {
+                offering: quote.offering_b_new_offering,
+                accept_at_least: quote.offering_b_new_accept_at_least,
+                ..candidate.bid.offer
+            },
+        );
+        res.push(candidate);
+    }
+    res
+}
+
+fn best_match(book: &OrderBook, min_profit: u64) -> Option<Candidate> {
+    let (best_bid, best_ask) = (book.best_bid()?.price, book.best_ask()?.price);
+    let mut res: Option<Candidate> = None;
+    let bids = book.bids().iter().take_while(|bid| bid.price >= best_ask);
+    for bid in bids {
+        let asks = book.asks().iter().take_while(|ask| ask.price <= best_bid);
+        for ask in asks.filter(|ask| ask.price <= bid.price) {
+            // offers that can't fill each other don't match
+            let quote = match MatchQuote::calc(&ask.offer, &bid.offer) {
+                Ok(quote) => quote,
+                Err(_) => continue,
+            };
+            let profit = profit_in_quote(&quote);
+            if profit < min_profit.into() || matches!(&res, Some(best) if best.profit >= profit) {
+                continue;
+            }
+            res = Some(Candidate {
+                ask: *ask,
+                bid: *bid,
+                quote,
+                profit,
+            });
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use simpledex::quote::Receipt;
+    use simpledex_test_utils::Market;
+
+    use super::*;
+
+    #[test]
+    fn test_profit_in_quote() {
+        let m = Market::new();
+        // ask is the older maker, the bid taker pays 10 bps of 2_000_000 quote atomics.
+        // The bid pays 200 per base atomic for an ask at 100: half the excess goes to the matcher.
+        let (_, ask) = m.ask(10_000, 1_000_000, 1);
+        let (_, bid) = m.bid(2_000_000, 10_000, 2);
+        let quote = MatchQuote::calc(&ask, &bid).unwrap();
+        assert_eq!(quote.receipt.a_to_matcher, 0);
+        assert_eq!(quote.receipt.b_fee, 2_000);
+        assert_eq!(quote.receipt.b_bonus, 500_000);
+        assert_eq!(profit_in_quote(&quote), 502_000);
+
+        // base payouts are valued at the match price, 20 quote atomics per base atomic
+        let base_payout = MatchQuote {
+            receipt: Receipt {
+                a_to_matcher: 10,
+                b_to_matcher: 5,
+                ..quote.receipt
+            },
+            amt_a_gives: 100,
+            amt_b_gives: 2_000,
+            ..quote
+        };
+        assert_eq!(profit_in_quote(&base_payout), 205);
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let m = Market::new();
+        let best_bid = m.bid(3_000_000, 10_000, 1);
+        let low_bid = m.bid(1_050_000, 10_000, 1);
+        let no_cross_bid = m.bid(900_000, 10_000, 1);
+        let best_ask = m.ask(10_000, 1_000_000, 2);
+        let high_ask = m.ask(10_000, 1_020_000, 1);
+        let book = OrderBook::new(
+            m.base,
+            m.quote,
+            vec![best_bid, low_bid, no_cross_bid, best_ask, high_ask],
+        );
+
+        let matches = find_matches(&book, 0);
+        let pairs: Vec<_> = matches
+            .iter()
+            .map(|c| (c.bid.address, c.ask.address))
+            .collect();
+        // best_bid with best_ask is worth the most, which leaves low_bid with high_ask
+        assert_eq!(pairs, [(best_bid.0, best_ask.0), (low_bid.0, high_ask.0)]);
+        assert!(matches[0].profit > matches[1].profit);
+        assert_eq!(
+            matches[0].quote,
+            MatchQuote::calc(&best_ask.1, &best_bid.1).unwrap()
+        );
+
+        let matches = find_matches(&book, u64::try_from(matches[1].profit).unwrap() + 1);
+        assert_eq!(matches.len(), 1);
+        assert!(find_matches(&book, u64::MAX).is_empty());
+
+        let uncrossed = OrderBook::new(m.base, m.quote, vec![no_cross_bid, high_ask]);
+        assert!(find_matches(&uncrossed, 0).is_empty());
+    }
+
+    #[test]
+    fn test_find_matches_chained() {
+        let m = Market::new();
+        let big_bid = m.bid(3_000_000, 20_000, 1);
+        let ask = m.ask(10_000, 1_000_000, 1);
+        let other_ask = m.ask(10_000, 1_200_000, 1);
+        let book = OrderBook::new(m.base, m.quote, vec![big_bid, ask, other_ask]);
+
+        let matches = find_matches(&book, 0);
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|c| c.bid.address == big_bid.0));
+        assert_eq!(matches[0].ask.address, ask.0);
+        assert_eq!(matches[1].ask.address, other_ask.0);
+        // the 2nd match is quoted against what the 1st leaves of the bid
+        let partly_filled = matches[1].bid.offer;
+        assert_eq!(
+            partly_filled.offering,
+            matches[0].quote.offering_b_new_offering
+        );
+        assert_eq!(
+            partly_filled.accept_at_least,
+            matches[0].quote.offering_b_new_accept_at_least
+        );
+        assert_eq!(
+            matches[1].quote,
+            MatchQuote::calc(&other_ask.1, &partly_filled).unwrap()
+        );
+    }
+}
@@ -0,0 +1,100 @@
+#![allow(dead_code, unused_imports)]
+
+use async_trait::async_trait;
+use simpledex_client::{AccountSource, ClientError};
+use simpledex_matcher::{MatcherError, TransactionSender};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_program_test::BanksClient;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+pub use simpledex_test_utils::{
+    create_funded_token_account, create_mint, create_offer, process, program_test,
+};
+
+/// BanksClient can't scan accounts, so getProgramAccounts is emulated
+/// by applying the filters to a known list of addresses
+pub struct BanksSource {
+    pub client: BanksClient,
+    pub known_accounts: Vec<Pubkey>,
+}
+
+#[async_trait]
+impl AccountSource for BanksSource {
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError> {
+        let mut client = self.client.clone();
+        let mut res = Vec::with_capacity(pubkeys.len());
+        for pubkey in pubkeys {
+            res.push(
+                client
+                    .get_account(*pubkey)
+                    .await
+                    .map_err(ClientError::source)?,
+            );
+        }
+        Ok(res)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+        let accounts = self.get_multiple_accounts(&self.known_accounts).await?;
+        Ok(self
+            .known_accounts
+            .iter()
+            .zip(accounts)
+            .filter_map(|(pubkey, account)| Some((*pubkey, account?)))
+            .filter(|(_, account)| {
+                let shared = AccountSharedData::from(account.clone());
+                &account.owner == program_id && filters.iter().all(|f| f.allows(&shared))
+            })
+            .collect())
+    }
+}
+
+pub struct BanksSender {
+    pub client: BanksClient,
+}
+
+#[async_trait]
+impl TransactionSender for BanksSender {
+    async fn get_latest_blockhash(&self) -> Result<Hash, MatcherError> {
+        self.client
+            .clone()
+            .get_latest_blockhash()
+            .await
+            .map_err(MatcherError::sender)
+    }
+
+    async fn simulate(&self, tx: &VersionedTransaction) -> Result<Option<u64>, MatcherError> {
+        let res = self
+            .client
+            .clone()
+            .simulate_transaction(tx.clone())
+            .await
+            .map_err(MatcherError::sender)?;
+        match res.result {
+            Some(Err(err)) => Err(MatcherError::sender(err)),
+            _ => Ok(res.simulation_details.map(|details| details.units_consumed)),
+        }
+    }
+
+    async fn send_and_confirm(&self, tx: &VersionedTransaction) -> Result<Signature, MatcherError> {
+        self.client
+            .clone()
+            .process_transaction(tx.clone())
+            .await
+            .map_err(MatcherError::sender)?;
+        Ok(tx.signatures[0])
+    }
+}
@@ -0,0 +1,294 @@
+mod helpers;
+
+use helpers::{
+    create_funded_token_account, create_mint, create_offer, process, program_test, BanksSender,
+    BanksSource,
+};
+use simpledex_client::SimpleDexClient;
+use simpledex_matcher::{Matcher, PairConfig};
+use solana_program_test::tokio;
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL, signature::Keypair, signer::Signer, system_instruction,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+#[tokio::test]
+async fn success_match_crossing_offers() {
+    let (mut client, payer, recent_blockhash) = program_test().start().await;
+    let base = create_mint(&mut client, &recent_blockhash, &payer).await;
+    let quote = create_mint(&mut client, &recent_blockhash, &payer).await;
+
+    let other_base = create_mint(&mut client, &recent_blockhash, &payer).await;
+    let other_quote = create_mint(&mut client, &recent_blockhash, &payer).await;
+
+    let traders: Vec<Keypair> = (0..6).map(|_| Keypair::new()).collect();
+    // (base_account, quote_account) of each trader
+    let mut accounts = Vec::new();
+    for (i, owner) in traders.iter().enumerate() {
+        let (base_mint, quote_mint) = match i {
+            0..=3 => (&base, &quote),
+            _ => (&other_base, &other_quote),
+        };
+        let mut owner_accounts = Vec::new();
+        for mint in [base_mint, quote_mint] {
+            owner_accounts.push(
+                create_funded_token_account(
+                    &mut client,
+                    &recent_blockhash,
+                    &payer,
+                    mint,
+                    &owner.pubkey(),
+                    1_000_000,
+                )
+                .await,
+            );
+        }
+        accounts.push(owner_accounts);
+    }
+    // (trader, is_bid, offering, accept_at_least)
+    let mut offers = Vec::new();
+    for (i, is_bid, offering, accept_at_least) in [
+        // ask at 100 quote per base
+        (0, false, 1_000, 100_000),
+        // bid at 333, big enough to be matched with both asks one after the other
+        (1, true, 600_000, 1_800),
+        // bid at 50, doesn't cross
+        (2, true, 50_000, 1_000),
+        // ask at 120
+        (3, false, 500, 60_000),
+        // crossing offers of another pair, can land alongside the others
+        (4, false, 1_000, 100_000),
+        (5, true, 300_000, 900),
+    ] {
+        let (base_mint, quote_mint) = match i {
+            0..=3 => (&base, &quote),
+            _ => (&other_base, &other_quote),
+        };
+        let (base_account, quote_account) = (accounts[i][0], accounts[i][1]);
+        let (pay_from, credit_to, offer_mint, accept_mint) = match is_bid {
+            true => (quote_account, base_account, quote_mint, base_mint),
+            false => (base_account, quote_account, base_mint, quote_mint),
+        };
+        offers.push(
+            create_offer(
+                &mut client,
+                &recent_blockhash,
+                &payer,
+                &traders[i],
+                &pay_from,
+                &credit_to,
+                offer_mint,
+                accept_mint,
+                0,
+                offering,
+                accept_at_least,
+            )
+            .await,
+        );
+    }
+
+    let matcher_keypair = Keypair::new();
+    let matcher_pubkey = matcher_keypair.pubkey();
+    let fund_ix = system_instruction::transfer(&payer.pubkey(), &matcher_pubkey, LAMPORTS_PER_SOL);
+    process(&mut client, &recent_blockhash, &payer, &[fund_ix], &[]).await;
+
+    let pairs = [(base, quote), (other_base, other_quote)];
+    let new_matcher = |keypair: Keypair, min_profit: u64| {
+        let source = BanksSource {
+            client: client.clone(),
+            known_accounts: offers.clone(),
+        };
+        let sender = BanksSender {
+            client: client.clone(),
+        };
+        let pairs = pairs
+            .iter()
+            .map(|(base_mint, quote_mint)| PairConfig {
+                base_mint: *base_mint,
+                quote_mint: *quote_mint,
+                min_profit,
+            })
+            .collect();
+        Matcher::new(SimpleDexClient::new(source), sender, keypair, pairs).with_fee_lanes(2)
+    };
+
+    // too expensive to be worth it
+    let picky = new_matcher(
+        Keypair::from_bytes(&matcher_keypair.to_bytes()).unwrap(),
+        u64::MAX,
+    );
+    // 4 mints, 2 lanes each
+    let created = picky.create_missing_fee_accounts().await.unwrap();
+    assert_eq!(created.len(), 8);
+    assert!(created.contains(&get_associated_token_address(&matcher_pubkey, &base)));
+    assert!(created.contains(&picky.fee_account(&other_quote, 1)));
+    assert!(picky
+        .create_missing_fee_accounts()
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(picky.run_once().await.unwrap().is_empty());
+
+    let matcher = new_matcher(matcher_keypair, 0);
+    // the 2 independent matches are packed together, then the chained one
+    assert_eq!(matcher.run_once().await.unwrap().len(), 2);
+    let market = matcher.client().market(&base, &quote).await.unwrap();
+    assert!(market.asks.is_empty());
+    let bids: Vec<_> = market.bids.iter().map(|o| o.address).collect();
+    assert_eq!(bids, [offers[1], offers[2]]);
+    let market = matcher
+        .client()
+        .market(&other_base, &other_quote)
+        .await
+        .unwrap();
+    assert!(market.asks.is_empty() && market.bids.is_empty());
+
+    for mint in [&base, &quote] {
+        let mut earned = 0;
+        for lane in 0..2 {
+            let account = client
+                .get_packed_account_data::<spl_token::state::Account>(
+                    matcher.fee_account(mint, lane),
+                )
+                .await
+                .unwrap();
+            assert_eq!(account.owner, matcher_pubkey);
+            earned += account.amount;
+        }
+        assert!(earned > 0);
+    }
+
+    // nothing left to match
+    assert!(matcher.run_once().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn success_skip_failing_match() {
+    let (mut client, payer, recent_blockhash) = program_test().start().await;
+    let pairs = [
+        (
+            create_mint(&mut client, &recent_blockhash, &payer).await,
+            create_mint(&mut client, &recent_blockhash, &payer).await,
+        ),
+        (
+            create_mint(&mut client, &recent_blockhash, &payer).await,
+            create_mint(&mut client, &recent_blockhash, &payer).await,
+        ),
+    ];
+
+    // a crossing ask and bid per pair
+    let mut offers = Vec::new();
+    let mut bid_credit_to = Vec::new();
+    for (base, quote) in &pairs {
+        let (asker, bidder) = (Keypair::new(), Keypair::new());
+        // ask_from, ask_to, bid_from, bid_to
+        let mut accounts = Vec::new();
+        for (mint, owner, amount) in [
+            (base, &asker, 1_000_000),
+            (quote, &asker, 0),
+            (quote, &bidder, 1_000_000),
+            (base, &bidder, 0),
+        ] {
+            accounts.push(
+                create_funded_token_account(
+                    &mut client,
+                    &recent_blockhash,
+                    &payer,
+                    mint,
+                    &owner.pubkey(),
+                    amount,
+                )
+                .await,
+            );
+        }
+        let (ask_from, ask_to, bid_from, bid_to) =
+            (accounts[0], accounts[1], accounts[2], accounts[3]);
+        offers.push(
+            create_offer(
+                &mut client,
+                &recent_blockhash,
+                &payer,
+                &asker,
+                &ask_from,
+                &ask_to,
+                base,
+                quote,
+                0,
+                1_000,
+                100_000,
+            )
+            .await,
+        );
+        offers.push(
+            create_offer(
+                &mut client,
+                &recent_blockhash,
+                &payer,
+                &bidder,
+                &bid_from,
+                &bid_to,
+                quote,
+                base,
+                0,
+                100_000,
+                1_000,
+            )
+            .await,
+        );
+        bid_credit_to.push((bidder, bid_to));
+    }
+    // the first pair's bid can't be credited anymore, so its match fails
+    let (bidder, bid_to) = &bid_credit_to[0];
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        bid_to,
+        &payer.pubkey(),
+        &bidder.pubkey(),
+        &[],
+    )
+    .unwrap();
+    process(
+        &mut client,
+        &recent_blockhash,
+        &payer,
+        &[close_ix],
+        &[bidder],
+    )
+    .await;
+
+    let matcher_keypair = Keypair::new();
+    let fund_ix =
+        system_instruction::transfer(&payer.pubkey(), &matcher_keypair.pubkey(), LAMPORTS_PER_SOL);
+    process(&mut client, &recent_blockhash, &payer, &[fund_ix], &[]).await;
+    let source = BanksSource {
+        client: client.clone(),
+        known_accounts: offers.clone(),
+    };
+    let sender = BanksSender {
+        client: client.clone(),
+    };
+    let pair_configs = pairs
+        .iter()
+        .map(|(base_mint, quote_mint)| PairConfig {
+            base_mint: *base_mint,
+            quote_mint: *quote_mint,
+            min_profit: 0,
+        })
+        .collect();
+    let matcher = Matcher::new(
+        SimpleDexClient::new(source),
+        sender,
+        matcher_keypair,
+        pair_configs,
+    );
+    matcher.create_missing_fee_accounts().await.unwrap();
+
+    // both matches are independent, only the second one is sent
+    assert_eq!(matcher.run_once().await.unwrap().len(), 1);
+    let (base, quote) = pairs[0];
+    let market = matcher.client().market(&base, &quote).await.unwrap();
+    assert_eq!((market.asks.len(), market.bids.len()), (1, 1));
+    let (base, quote) = pairs[1];
+    let market = matcher.client().market(&base, &quote).await.unwrap();
+    assert!(market.asks.is_empty() && market.bids.is_empty());
+}
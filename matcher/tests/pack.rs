@@ -0,0 +1,100 @@
+mod helpers;
+
+use helpers::{create_funded_token_account, create_mint, create_offer, program_test};
+use simpledex::{instructions::match_offers, state::Offer};
+use simpledex_matcher::pack::{simulated_budget, TxPacker};
+use solana_program_test::tokio;
+use solana_sdk::{
+    program_pack::Pack,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, VersionedTransaction},
+};
+
+/// A match lands within the compute units its own simulation measured.
+/// Natively only the CPIs are metered, `cargo test-sbf` checks it against a BPF build.
+#[tokio::test]
+async fn match_offers_within_simulated_budget() {
+    let (mut client, payer, recent_blockhash) = program_test().start().await;
+    let base = create_mint(&mut client, &recent_blockhash, &payer).await;
+    let quote = create_mint(&mut client, &recent_blockhash, &payer).await;
+
+    let (asker, bidder) = (Keypair::new(), Keypair::new());
+    let mut token_accounts = Vec::new();
+    for (owner, mint, amount) in [
+        (&asker, &base, 1_000_000),
+        (&asker, &quote, 0),
+        (&bidder, &base, 0),
+        (&bidder, &quote, 1_000_000),
+        (&payer, &base, 0),
+        (&payer, &quote, 0),
+    ] {
+        token_accounts.push(
+            create_funded_token_account(
+                &mut client,
+                &recent_blockhash,
+                &payer,
+                mint,
+                &owner.pubkey(),
+                amount,
+            )
+            .await,
+        );
+    }
+    // crossing at different prices, both offers are filled and closed and the matcher earns the excess
+    let ask = create_offer(
+        &mut client,
+        &recent_blockhash,
+        &payer,
+        &asker,
+        &token_accounts[0],
+        &token_accounts[1],
+        &base,
+        &quote,
+        0,
+        1_000,
+        100_000,
+    )
+    .await;
+    let bid = create_offer(
+        &mut client,
+        &recent_blockhash,
+        &payer,
+        &bidder,
+        &token_accounts[3],
+        &token_accounts[2],
+        &quote,
+        &base,
+        0,
+        110_000,
+        1_000,
+    )
+    .await;
+    let mut offers = Vec::new();
+    for offer in [ask, bid] {
+        let account = client.get_account(offer).await.unwrap().unwrap();
+        offers.push(Offer::unpack(&account.data).unwrap());
+    }
+
+    let ix = match_offers(
+        &offers[0],
+        &offers[1],
+        &token_accounts[4],
+        &token_accounts[5],
+    )
+    .unwrap();
+    let mut tx = Transaction::new_with_payer(std::slice::from_ref(&ix), Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    let res = client.simulate_transaction(tx).await.unwrap();
+    res.result.unwrap().unwrap();
+    let units = res.simulation_details.unwrap().units_consumed;
+    assert!(units > 0);
+
+    let budget = simulated_budget(Some(units));
+    let packed = TxPacker::new(payer.pubkey())
+        .pack(&[(ix, budget)], recent_blockhash)
+        .unwrap();
+    assert_eq!(packed.len(), 1);
+    let tx = VersionedTransaction::try_new(packed[0].message.clone(), &[&payer]).unwrap();
+    client.process_transaction(tx).await.unwrap();
+}
@@ -0,0 +1,8 @@
+//! Prints the program's interface description, see `simpledex::idl`
+
+fn main() {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&simpledex::idl::generate()).unwrap()
+    );
+}
@@ -1,11 +1,11 @@
 use solana_program::pubkey::Pubkey;
 
-use crate::{error::SimpleDexError, state::Offer};
+use crate::{error::SimpleDexError, state::OfferFields};
 
 macro_rules! is_pubkey_field {
     ($fn_name: ident, $field: ident, $err: expr) => {
-        pub fn $fn_name(actual: &Pubkey, offer: &Offer) -> Result<(), SimpleDexError> {
-            match *actual == offer.$field {
+        pub fn $fn_name<O: OfferFields>(actual: &Pubkey, offer: &O) -> Result<(), SimpleDexError> {
+            match *actual == offer.$field() {
                 true => Ok(()),
                 false => Err($err),
             }
@@ -1,6 +1,12 @@
-use solana_program::{account_info::AccountInfo, program_error::ProgramError};
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
 
-use crate::{error::SimpleDexError, pda::try_find_offer_pda};
+use crate::{
+    error::SimpleDexError,
+    pda::{
+        try_find_fee_account_with_program_id, try_find_fee_authority_pda_with_program_id,
+        try_find_offer_pda_with_program_id,
+    },
+};
 
 use super::is_pubkey_matching;
 
@@ -11,12 +17,35 @@ pub fn is_offer_pda(
     accept_mint: &AccountInfo,
     seed: u16,
     bump: u8,
+    program_id: &Pubkey,
 ) -> Result<(), ProgramError> {
-    let (found_pubkey, found_bump) =
-        try_find_offer_pda(owner.key, offer_mint.key, accept_mint.key, seed)?;
+    let (found_pubkey, found_bump) = try_find_offer_pda_with_program_id(
+        owner.key,
+        offer_mint.key,
+        accept_mint.key,
+        seed,
+        program_id,
+    )?;
     is_pubkey_matching(actual, &found_pubkey, SimpleDexError::InvalidOfferAccount)?;
     match bump == found_bump {
         true => Ok(()),
         false => Err(SimpleDexError::InvalidOfferBump.into()),
     }
 }
+
+pub fn is_fee_account(
+    actual: &AccountInfo,
+    accept_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<(), ProgramError> {
+    let expected = try_find_fee_account_with_program_id(accept_mint, program_id)?;
+    is_pubkey_matching(actual, &expected, SimpleDexError::IncorrectFeeAccount)?;
+    Ok(())
+}
+
+/// Returns the fee authority PDA's bump to sign with
+pub fn is_fee_authority(actual: &AccountInfo, program_id: &Pubkey) -> Result<u8, ProgramError> {
+    let (expected, bump) = try_find_fee_authority_pda_with_program_id(program_id)?;
+    is_pubkey_matching(actual, &expected, SimpleDexError::IncorrectFeeAuthority)?;
+    Ok(bump)
+}
@@ -1,7 +1,16 @@
-use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use solana_program::{
+    account_info::AccountInfo, bpf_loader_upgradeable, program_error::ProgramError, pubkey::Pubkey,
+};
+
+use crate::error::SimpleDexError;
 
 use super::is_pubkey_matching;
 
+/// bincode tag of `UpgradeableLoaderState::ProgramData`
+const PROGRAM_DATA_TAG: [u8; 4] = 3u32.to_le_bytes();
+/// after the tag, `slot: u64` and `upgrade_authority_address: Option<Pubkey>`'s tag
+const UPGRADE_AUTHORITY_OFFSET: usize = 4 + 8 + 1;
+
 fn is_program(actual: &AccountInfo, expected: &Pubkey) -> Result<(), ProgramError> {
     is_pubkey_matching(actual, expected, ProgramError::IncorrectProgramId)
 }
@@ -17,3 +26,34 @@ pub fn is_token_program(actual: &AccountInfo) -> Result<(), ProgramError> {
 pub fn is_ata_program(actual: &AccountInfo) -> Result<(), ProgramError> {
     is_program(actual, &spl_associated_token_account::id())
 }
+
+/// Checks `actual` is the upgrade authority recorded in `program_data`,
+/// the ProgramData account of the program deployed at `program_id`.
+/// Fails if the program is immutable.
+pub fn is_upgrade_authority(
+    actual: &AccountInfo,
+    program_data: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<(), ProgramError> {
+    let (expected_program_data, _bump) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    is_pubkey_matching(
+        program_data,
+        &expected_program_data,
+        SimpleDexError::IncorrectUpgradeAuthority,
+    )?;
+    if program_data.owner != &bpf_loader_upgradeable::id() {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let data = program_data.data.borrow();
+    let upgrade_authority = match data.get(..UPGRADE_AUTHORITY_OFFSET + 32) {
+        Some(meta) if meta[..4] == PROGRAM_DATA_TAG && meta[UPGRADE_AUTHORITY_OFFSET - 1] == 1 => {
+            &meta[UPGRADE_AUTHORITY_OFFSET..]
+        }
+        _ => return Err(SimpleDexError::IncorrectUpgradeAuthority.into()),
+    };
+    match actual.key.as_ref() == upgrade_authority {
+        true => Ok(()),
+        false => Err(SimpleDexError::IncorrectUpgradeAuthority.into()),
+    }
+}
@@ -30,6 +30,17 @@ pub enum SimpleDexError {
     OffersDontMatch,
     InvalidOfferAccount,
     InvalidOfferBump,
+    InvalidReturnData,
+    // 15
+    LegacyOfferAccount,
+    IncorrectTraderAccount,
+    SlippageExceeded,
+    IncorrectFeeAccount,
+    // 20
+    TraderSeedsExhausted,
+    TraderPairsFull,
+    IncorrectUpgradeAuthority,
+    IncorrectFeeAuthority,
 }
 
 impl From<SimpleDexError> for ProgramError {
@@ -38,6 +49,41 @@ impl From<SimpleDexError> for ProgramError {
     }
 }
 
+impl SimpleDexError {
+    /// Message logged by [`PrintProgramError::print`]
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::NumericalError => "numerical error",
+            Self::PackunError => "(de)serialization error",
+            Self::InvalidHoldingAccount => "provided account is not a valid holding account",
+            Self::IncorrectMint => "given mint does not match token account's",
+            Self::TokenAccountFrozen => "provided token account is frozen",
+            Self::RefundingToOfferAccounts => {
+                "attempting to refund tokens/lamports to offer accounts"
+            }
+            Self::IncorrectOfferAccount => "passed offer account is not the right one",
+            Self::IncorrectOwner => "not owner of this offer",
+            Self::IncorrectRefundTo => "incorrect refund_to for this offer",
+            Self::IncorredRefundRentTo => "incorrect refund_rent_to for this offer",
+            Self::IncorrectCreditTo => "incorrect credit_to for this offer",
+            Self::OffersDontMatch => "offers do not meet each other's limit price",
+            Self::InvalidOfferAccount => "provided account is not a valid offer account",
+            Self::InvalidOfferBump => "provided bump seed for offer account is not correct",
+            Self::InvalidReturnData => "no valid simpledex return data was set",
+            Self::LegacyOfferAccount => {
+                "offer account uses an outdated layout, migrate it with MigrateOffer first"
+            }
+            Self::IncorrectTraderAccount => "passed trader account is not the owner's",
+            Self::SlippageExceeded => "offer gives less than the taker's minimum amount out",
+            Self::IncorrectFeeAccount => "fee_account is not the fee authority's accept_mint ATA",
+            Self::TraderSeedsExhausted => "trader account has no seeds left after this one",
+            Self::TraderPairsFull => "trader account has open offers in too many other pairs",
+            Self::IncorrectUpgradeAuthority => "signer is not the program's upgrade authority",
+            Self::IncorrectFeeAuthority => "fee_authority is not the fee authority PDA",
+        }
+    }
+}
+
 impl<T> DecodeError<T> for SimpleDexError {
     fn type_of() -> &'static str {
         "SimpleDexError"
@@ -53,24 +99,7 @@ impl PrintProgramError for SimpleDexError {
             + PrintProgramError
             + num_traits::FromPrimitive,
     {
-        match self {
-            Self::NumericalError => msg!("numerical error"),
-            Self::PackunError => msg!("(de)serialization error"),
-            Self::InvalidHoldingAccount => msg!("provided account is not a valid holding account"),
-            Self::IncorrectMint => msg!("given mint does not match token account's"),
-            Self::TokenAccountFrozen => msg!("provided token account is frozen"),
-            Self::RefundingToOfferAccounts => {
-                msg!("attempting to refund tokens/lamports to offer accounts")
-            }
-            Self::IncorrectOfferAccount => msg!("passed offer account is not the right one"),
-            Self::IncorrectOwner => msg!("not owner of this offer"),
-            Self::IncorrectRefundTo => msg!("incorrect refund_to for this offer"),
-            Self::IncorredRefundRentTo => msg!("incorrect refund_rent_to for this offer"),
-            Self::IncorrectCreditTo => msg!("incorrect credit_to for this offer"),
-            Self::OffersDontMatch => msg!("offers do not meet each other's limit price"),
-            Self::InvalidOfferAccount => msg!("provided account is not a valid offer account"),
-            Self::InvalidOfferBump => msg!("provided bump seed for offer account is not correct"),
-        }
+        msg!(self.message());
     }
 }
 
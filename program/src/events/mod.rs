@@ -0,0 +1,277 @@
+//! Structured events emitted by the program through `sol_log_data`.
+//!
+//! Each successful CreateOffer, CancelOffer, MatchOffers and TakeOffer emits exactly one packed [`SimpleDexEvent`].
+//! Off-chain consumers can decode the base64 payload of a `Program data: ` log line with
+//! [`DeserializePacked`](crate::packun::DeserializePacked):
+//!
+//! ```ignore
+//! let event = SimpleDexEvent::read_bytes(&mut decoded.as_slice())?;
+//! ```
+
+use std::{fmt, io::Cursor};
+
+use solana_program::{clock::Slot, log::sol_log_data, pubkey::Pubkey};
+
+use crate::{
+    error::SimpleDexError,
+    packun::{DeserializePacked, SerializePacked},
+    quote::Receipt,
+    state::MEMO_LEN,
+};
+
+/// Offer state reported by CREATE and CANCEL events
+#[derive(Clone, Copy, Debug, Default, PartialEq, DeserializePacked, SerializePacked)]
+pub struct OfferEvent {
+    pub offer: Pubkey,
+    pub offer_mint: Pubkey,
+    pub offering: u64,
+    pub accept_mint: Pubkey,
+    pub accept_at_least: u64,
+    pub client_order_id: u64,
+    pub memo: [u8; MEMO_LEN],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub struct MatchOffersEvent {
+    pub token_a: Pubkey,
+    pub token_b: Pubkey,
+    pub receipt: Receipt,
+    pub offering_a: Pubkey,
+    pub offering_a_new_offering: u64,
+    pub offering_a_new_accept_at_least: u64,
+    pub offering_a_client_order_id: u64,
+    pub offering_a_memo: [u8; MEMO_LEN],
+    pub offering_b: Pubkey,
+    pub offering_b_new_offering: u64,
+    pub offering_b_new_accept_at_least: u64,
+    pub offering_b_client_order_id: u64,
+    pub offering_b_memo: [u8; MEMO_LEN],
+    pub matcher_a: Pubkey,
+    pub matcher_b: Pubkey,
+    /// slot at which the match happened
+    pub slot: Slot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub struct TakeOfferEvent {
+    pub offer: Pubkey,
+    pub offer_mint: Pubkey,
+    pub accept_mint: Pubkey,
+    pub taker: Pubkey,
+    /// accept_mint credited to the offer
+    pub amount_in: u64,
+    /// offer_mint credited to the taker
+    pub amount_out: u64,
+    /// taker fee in accept_mint, credited to `fee_account`
+    pub fee: u64,
+    pub fee_account: Pubkey,
+    pub new_offering: u64,
+    pub new_accept_at_least: u64,
+    pub client_order_id: u64,
+    pub memo: [u8; MEMO_LEN],
+    /// slot at which the take happened
+    pub slot: Slot,
+}
+
+// events are short-lived, not worth boxing on-chain
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub enum SimpleDexEvent {
+    CreateOffer(OfferEvent),
+    CancelOffer(OfferEvent),
+    MatchOffers(MatchOffersEvent),
+    TakeOffer(TakeOfferEvent),
+}
+
+impl SimpleDexEvent {
+    /// Writes this event to the program logs as a single `sol_log_data` field
+    pub fn emit(&self) -> Result<(), SimpleDexError> {
+        let mut data = [0; Self::PACKED_LEN_MAX];
+        let mut writer = Cursor::new(data.as_mut());
+        self.write_bytes(&mut writer)?;
+        let len = writer.position() as usize;
+        sol_log_data(&[&data[..len]]);
+        Ok(())
+    }
+}
+
+/// Formats an event as the legacy human readable csv log, see the README for the formats.
+/// Memos and the `to_matcher` amounts are left out.
+pub struct CsvLog<'a>(pub &'a SimpleDexEvent);
+
+impl fmt::Display for CsvLog<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            SimpleDexEvent::CreateOffer(e) => write!(
+                f,
+                "CREATE:{},{},{},{},{},{}",
+                e.offer,
+                e.offer_mint,
+                e.offering,
+                e.accept_mint,
+                e.accept_at_least,
+                e.client_order_id
+            ),
+            SimpleDexEvent::CancelOffer(e) => write!(
+                f,
+                "CANCEL:{},{},{},{},{},{}",
+                e.offer,
+                e.offer_mint,
+                e.offering,
+                e.accept_mint,
+                e.accept_at_least,
+                e.client_order_id
+            ),
+            SimpleDexEvent::MatchOffers(e) => write!(
+                f,
+                "MATCH:{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                e.token_a,
+                e.receipt.a_to_b,
+                e.token_b,
+                e.receipt.b_to_a,
+                e.offering_a,
+                e.offering_a_new_offering,
+                e.offering_a_new_accept_at_least,
+                e.offering_b,
+                e.offering_b_new_offering,
+                e.offering_b_new_accept_at_least,
+                e.receipt.a_fee,
+                e.receipt.a_bonus,
+                e.receipt.b_fee,
+                e.receipt.b_bonus,
+                e.receipt.a_seq as u8,
+                e.matcher_a,
+                e.matcher_b,
+                e.slot,
+                e.offering_a_client_order_id,
+                e.offering_b_client_order_id,
+            ),
+            SimpleDexEvent::TakeOffer(e) => write!(
+                f,
+                "TAKE:{},{},{},{},{},{},{},{},{},{},{},{}",
+                e.offer,
+                e.offer_mint,
+                e.amount_out,
+                e.accept_mint,
+                e.amount_in,
+                e.fee,
+                e.new_offering,
+                e.new_accept_at_least,
+                e.taker,
+                e.fee_account,
+                e.slot,
+                e.client_order_id,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        instructions::SimpleDexInstruction,
+        return_data::{CreateOfferReturnData, MatchOffersReturnData, TakeOfferReturnData},
+        types::OfferSeq,
+    };
+
+    use super::*;
+
+    fn roundtrip(event: SimpleDexEvent, packed_len: usize) {
+        let mut data = vec![0; packed_len];
+        let mut writer = Cursor::new(data.as_mut_slice());
+        event.write_bytes(&mut writer).unwrap();
+        assert_eq!(writer.position() as usize, packed_len);
+        let mut reader = data.as_slice();
+        assert_eq!(SimpleDexEvent::read_bytes(&mut reader).unwrap(), event);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_packed_lens() {
+        // wire format consumed by off-chain clients, must not change with the derive
+        assert_eq!(SimpleDexEvent::PACKED_LEN_CREATE_OFFER, 137);
+        assert_eq!(SimpleDexEvent::PACKED_LEN_CANCEL_OFFER, 137);
+        assert_eq!(SimpleDexEvent::PACKED_LEN_MATCH_OFFERS, 346);
+        assert_eq!(SimpleDexEvent::PACKED_LEN_TAKE_OFFER, 233);
+        assert_eq!(SimpleDexEvent::PACKED_LEN_MAX, 346);
+        assert_eq!(SimpleDexInstruction::PACKED_LEN_CREATE_OFFER, 44);
+        assert_eq!(SimpleDexInstruction::PACKED_LEN_CANCEL_OFFER, 1);
+        assert_eq!(SimpleDexInstruction::PACKED_LEN_TAKE_OFFER, 17);
+        assert_eq!(Receipt::PACKED_LEN, 65);
+        assert_eq!(MatchOffersReturnData::PACKED_LEN, 97);
+        assert_eq!(CreateOfferReturnData::PACKED_LEN, 33);
+        assert_eq!(TakeOfferReturnData::PACKED_LEN, 40);
+    }
+
+    #[test]
+    fn test_event_packing_roundtrip() {
+        let offer_event = OfferEvent {
+            offer: Pubkey::new_unique(),
+            offer_mint: Pubkey::new_unique(),
+            offering: 1_000_000_000,
+            accept_mint: Pubkey::new_unique(),
+            accept_at_least: 100_000_000,
+            client_order_id: 42,
+            memo: *b"my-strategy-tag!",
+        };
+        roundtrip(
+            SimpleDexEvent::CreateOffer(offer_event),
+            SimpleDexEvent::PACKED_LEN_CREATE_OFFER,
+        );
+        roundtrip(
+            SimpleDexEvent::CancelOffer(offer_event),
+            SimpleDexEvent::PACKED_LEN_CANCEL_OFFER,
+        );
+        roundtrip(
+            SimpleDexEvent::MatchOffers(MatchOffersEvent {
+                token_a: Pubkey::new_unique(),
+                token_b: Pubkey::new_unique(),
+                receipt: Receipt {
+                    a_to_b: 1_000_000_000,
+                    b_to_a: 99_950_000,
+                    a_to_matcher: 0,
+                    b_to_matcher: 50_000,
+                    a_fee: 0,
+                    b_fee: 0,
+                    a_bonus: 0,
+                    b_bonus: 50_000,
+                    a_seq: OfferSeq::Maker,
+                },
+                offering_a: Pubkey::new_unique(),
+                offering_a_new_offering: 0,
+                offering_a_new_accept_at_least: 0,
+                offering_a_client_order_id: 1,
+                offering_a_memo: [0; MEMO_LEN],
+                offering_b: Pubkey::new_unique(),
+                offering_b_new_offering: 10_000_000,
+                offering_b_new_accept_at_least: 100_000_000,
+                offering_b_client_order_id: 2,
+                offering_b_memo: [0xff; MEMO_LEN],
+                matcher_a: Pubkey::new_unique(),
+                matcher_b: Pubkey::new_unique(),
+                slot: 123_456_789,
+            }),
+            SimpleDexEvent::PACKED_LEN_MATCH_OFFERS,
+        );
+        roundtrip(
+            SimpleDexEvent::TakeOffer(TakeOfferEvent {
+                offer: Pubkey::new_unique(),
+                offer_mint: Pubkey::new_unique(),
+                accept_mint: Pubkey::new_unique(),
+                taker: Pubkey::new_unique(),
+                amount_in: 50_000_000,
+                amount_out: 500_000_000,
+                fee: 50_000,
+                fee_account: Pubkey::new_unique(),
+                new_offering: 500_000_000,
+                new_accept_at_least: 50_000_000,
+                client_order_id: 42,
+                memo: *b"my-strategy-tag!",
+                slot: 123_456_789,
+            }),
+            SimpleDexEvent::PACKED_LEN_TAKE_OFFER,
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Machine-readable description of the program's interface, generated from the Rust types.
+//!
+//! Clients in other languages can generate their instruction builders, account decoders
+//! and error tables from `idl/simpledex.json` instead of duplicating them by hand.
+//! Regenerate it with `cargo run --example idl --features idl > ../idl/simpledex.json`.
+
+use num_traits::FromPrimitive;
+use serde_json::{json, Value};
+
+use crate::{
+    error::SimpleDexError,
+    events::{MatchOffersEvent, OfferEvent, SimpleDexEvent, TakeOfferEvent},
+    instructions::{
+        create_offer::CreateOfferArgs, sync_trader_pair::SyncTraderPairArgs,
+        take_offer::TakeOfferArgs, CancelOfferAccounts, CreateOfferAccounts, CreateTraderAccounts,
+        InstructionAccount, MatchOffersAccounts, MigrateOfferAccounts, QuoteMatchAccounts,
+        SimpleDexInstruction, SyncTraderPairAccounts, TakeOfferAccounts, WithdrawFeesAccounts,
+    },
+    packun::{FieldDef, Layout, PackedLayout, TypeDef},
+    quote::Receipt,
+    return_data::{CreateOfferReturnData, MatchOffersReturnData, TakeOfferReturnData},
+    state::{Offer, OfferVersion, Trader, TraderPair, OFFER_DISCRIMINATOR, TRADER_DISCRIMINATOR},
+    types::OfferSeq,
+};
+
+/// Accounts of each [`SimpleDexInstruction`] variant, by variant name
+const INSTRUCTION_ACCOUNTS: &[(&str, &[InstructionAccount])] = &[
+    ("CreateOffer", CreateOfferAccounts::ACCOUNTS),
+    ("CancelOffer", CancelOfferAccounts::ACCOUNTS),
+    ("MatchOffers", MatchOffersAccounts::ACCOUNTS),
+    ("QuoteMatch", QuoteMatchAccounts::ACCOUNTS),
+    ("MigrateOffer", MigrateOfferAccounts::ACCOUNTS),
+    ("CreateTrader", CreateTraderAccounts::ACCOUNTS),
+    ("TakeOffer", TakeOfferAccounts::ACCOUNTS),
+    ("SyncTraderPair", SyncTraderPairAccounts::ACCOUNTS),
+    ("WithdrawFees", WithdrawFeesAccounts::ACCOUNTS),
+];
+
+pub fn generate() -> Value {
+    json!({
+        "name": "simpledex",
+        "version": env!("CARGO_PKG_VERSION"),
+        "programId": crate::id().to_string(),
+        "instructions": instructions(),
+        "accounts": [
+            {
+                "name": "Offer",
+                "prefix": [
+                    { "name": "discriminator", "type": "u8", "value": OFFER_DISCRIMINATOR },
+                    { "name": "version", "type": "u8", "value": OfferVersion::CURRENT as u8 },
+                ],
+                "size": <Offer as solana_program::program_pack::Pack>::LEN,
+                "type": layout(Offer::layout()),
+            },
+            {
+                "name": "Trader",
+                "prefix": [
+                    { "name": "discriminator", "type": "u8", "value": TRADER_DISCRIMINATOR },
+                ],
+                "size": <Trader as solana_program::program_pack::Pack>::LEN,
+                "type": layout(Trader::layout()),
+            },
+        ],
+        "events": layout(SimpleDexEvent::layout()),
+        "returnData": {
+            "CreateOffer": layout(CreateOfferReturnData::layout()),
+            "MatchOffers": layout(MatchOffersReturnData::layout()),
+            "QuoteMatch": layout(MatchOffersReturnData::layout()),
+            "TakeOffer": layout(TakeOfferReturnData::layout()),
+        },
+        "types": [
+            type_def::<CreateOfferArgs>(),
+            type_def::<TakeOfferArgs>(),
+            type_def::<SyncTraderPairArgs>(),
+            type_def::<Offer>(),
+            type_def::<Trader>(),
+            type_def::<TraderPair>(),
+            type_def::<OfferSeq>(),
+            type_def::<Receipt>(),
+            type_def::<OfferEvent>(),
+            type_def::<MatchOffersEvent>(),
+            type_def::<TakeOfferEvent>(),
+            type_def::<SimpleDexEvent>(),
+            type_def::<CreateOfferReturnData>(),
+            type_def::<MatchOffersReturnData>(),
+            type_def::<TakeOfferReturnData>(),
+        ],
+        "errors": errors(),
+    })
+}
+
+fn instructions() -> Value {
+    let variants = match SimpleDexInstruction::type_def() {
+        Some(TypeDef::Enum { variants, .. }) => variants,
+        _ => unreachable!("SimpleDexInstruction is a derived enum"),
+    };
+    variants
+        .iter()
+        .map(|variant| {
+            let accounts = INSTRUCTION_ACCOUNTS
+                .iter()
+                .find(|(name, _)| *name == variant.name)
+                .map(|(_, accounts)| *accounts)
+                .unwrap_or_else(|| panic!("no accounts listed for {}", variant.name));
+            json!({
+                "name": variant.name,
+                "tag": variant.tag,
+                "args": fields(&variant.fields),
+                "accounts": accounts.iter().map(|a| json!({
+                    "name": a.name,
+                    "signer": a.signer,
+                    "writable": a.writable,
+                    "optional": a.optional,
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+fn errors() -> Value {
+    (0..)
+        .map_while(SimpleDexError::from_u32)
+        .map(|e| {
+            json!({
+                "code": e.clone() as u32,
+                "name": format!("{:?}", e),
+                "msg": e.message(),
+            })
+        })
+        .collect()
+}
+
+fn type_def<T: PackedLayout>() -> Value {
+    match T::type_def() {
+        Some(TypeDef::Struct { name, fields: f }) => json!({
+            "name": name,
+            "kind": "struct",
+            "fields": fields(&f),
+        }),
+        Some(TypeDef::Enum { name, variants }) => json!({
+            "name": name,
+            "kind": "enum",
+            "variants": variants.iter().map(|v| json!({
+                "name": v.name,
+                "tag": v.tag,
+                "fields": fields(&v.fields),
+            })).collect::<Vec<_>>(),
+        }),
+        None => unreachable!("only called with derived types"),
+    }
+}
+
+fn fields(fields: &[FieldDef]) -> Value {
+    fields
+        .iter()
+        .map(|f| match f.name {
+            Some(name) => json!({ "name": name, "type": layout(f.layout) }),
+            None => json!({ "type": layout(f.layout) }),
+        })
+        .collect()
+}
+
+fn layout(layout: Layout) -> Value {
+    match layout {
+        Layout::U8 => json!("u8"),
+        Layout::U16 => json!("u16"),
+        Layout::U32 => json!("u32"),
+        Layout::U64 => json!("u64"),
+        Layout::Pubkey => json!("publicKey"),
+        Layout::Bytes(len) => json!({ "array": ["u8", len] }),
+        Layout::Defined(name) => json!({ "defined": name }),
+        Layout::DefinedArray(name, len) => json!({ "array": [{ "defined": name }, len] }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+    use crate::{
+        instructions::{
+            create_trader, match_offers, migrate_offer, quote_match, sync_trader_pair, take_offer,
+            withdraw_fees, CancelOfferAccounts,
+        },
+        pda::try_find_offer_pda,
+        state::MEMO_LEN,
+    };
+
+    use super::*;
+
+    fn offer() -> Offer {
+        let owner = Pubkey::new_unique();
+        let offer_mint = Pubkey::new_unique();
+        let accept_mint = Pubkey::new_unique();
+        let (_, bump) = try_find_offer_pda(&owner, &offer_mint, &accept_mint, 0).unwrap();
+        Offer {
+            owner,
+            offer_mint,
+            accept_mint,
+            bump,
+            refund_to: Pubkey::new_unique(),
+            credit_to: Pubkey::new_unique(),
+            refund_rent_to: Pubkey::new_unique(),
+            ..Offer::default()
+        }
+    }
+
+    fn assert_accounts_match(ix: &Instruction, accounts: &[InstructionAccount]) {
+        assert_eq!(ix.accounts.len(), accounts.len());
+        for (meta, account) in ix.accounts.iter().zip(accounts) {
+            assert_eq!(meta.is_signer, account.signer, "{}", account.name);
+            assert_eq!(meta.is_writable, account.writable, "{}", account.name);
+        }
+    }
+
+    /// Account lists must agree with what the instruction builders produce
+    #[test]
+    fn test_accounts_match_builders() {
+        let k = Pubkey::new_unique;
+        let (a, b) = (offer(), offer());
+        let ixs = [
+            (
+                "CreateOffer",
+                CreateOfferAccounts::new(&k(), &k(), &k(), 0)
+                    .unwrap()
+                    .with_trader()
+                    .unwrap()
+                    .instruction(0, 1, 1, 0, [0; MEMO_LEN])
+                    .unwrap(),
+            ),
+            (
+                "CancelOffer",
+                CancelOfferAccounts::new(&a)
+                    .unwrap()
+                    .with_trader()
+                    .unwrap()
+                    .instruction()
+                    .unwrap(),
+            ),
+            ("MatchOffers", match_offers(&a, &b, &k(), &k()).unwrap()),
+            ("QuoteMatch", quote_match(&a, &b).unwrap()),
+            ("MigrateOffer", migrate_offer(&k(), &a).unwrap()),
+            ("CreateTrader", create_trader(&k(), &k()).unwrap()),
+            ("TakeOffer", take_offer(&a, &k(), 1, 1).unwrap()),
+            (
+                "SyncTraderPair",
+                sync_trader_pair(&k(), &k(), &k(), 1).unwrap(),
+            ),
+            ("WithdrawFees", withdraw_fees(&k(), &k(), &k()).unwrap()),
+        ];
+        assert_eq!(ixs.len(), INSTRUCTION_ACCOUNTS.len());
+        for ((name, ix), (expected_name, accounts)) in ixs.iter().zip(INSTRUCTION_ACCOUNTS) {
+            assert_eq!(name, expected_name);
+            assert_accounts_match(ix, accounts);
+            // tag
+            let variant = &instructions()[ix.data[0] as usize];
+            assert_eq!(variant["name"], *name);
+        }
+    }
+
+    #[test]
+    fn test_idl_up_to_date() {
+        let committed: Value =
+            serde_json::from_str(include_str!("../../idl/simpledex.json")).unwrap();
+        assert!(
+            committed == generate(),
+            "idl/simpledex.json is outdated, regenerate it with `cargo run --example idl --features idl > ../idl/simpledex.json`"
+        );
+    }
+}
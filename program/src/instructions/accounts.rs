@@ -0,0 +1,119 @@
+#![macro_use]
+//! Account structs shared by the instruction builders and the processor,
+//! so each instruction's account order is only defined once.
+
+/// Defines an instruction's accounts struct, in the order the instruction expects them.
+/// Each account is declared with the [`InstructionAccount`](super::InstructionAccount)
+/// constructor matching its flags, optional accounts come last.
+///
+/// Generates:
+/// - `$name<T = Pubkey>`, with a `T` field per account and an `Option<T>` per optional account
+/// - `$name::ACCOUNTS`, the [`InstructionAccount`](super::InstructionAccount) of each field
+/// - `$name::to_account_metas()` for instruction builders
+/// - `TryFrom<&[AccountInfo]>` for `$name<&AccountInfo>` for the processor
+macro_rules! instruction_accounts {
+    (
+        $(#[$meta:meta])*
+        $name: ident {
+            $($field: ident: $ctor: ident($signer: expr)),* $(,)?
+        }
+        $(optional {
+            $($opt_field: ident: $opt_ctor: ident($opt_signer: expr)),* $(,)?
+        })?
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub struct $name<T = solana_program::pubkey::Pubkey> {
+            $(pub $field: T,)*
+            $($(pub $opt_field: Option<T>,)*)?
+        }
+
+        impl $name {
+            pub const ACCOUNTS: &'static [$crate::instructions::InstructionAccount] = &[
+                $($crate::instructions::InstructionAccount::$ctor(stringify!($field), $signer),)*
+                $($(
+                    $crate::instructions::InstructionAccount::$opt_ctor(stringify!($opt_field), $opt_signer)
+                        .optional(),
+                )*)?
+            ];
+
+            /// Omitted optional accounts are left out, along with any optional accounts after them
+            pub fn to_account_metas(&self) -> Vec<solana_program::instruction::AccountMeta> {
+                let pubkeys = [$(Some(self.$field),)* $($(self.$opt_field,)*)?];
+                pubkeys
+                    .iter()
+                    .zip(Self::ACCOUNTS)
+                    .map_while(|(pubkey, account)| pubkey.map(|pubkey| account.to_account_meta(pubkey)))
+                    .collect()
+            }
+        }
+
+        impl<'a, 'me> core::convert::TryFrom<&'me [solana_program::account_info::AccountInfo<'a>]>
+            for $name<&'me solana_program::account_info::AccountInfo<'a>>
+        {
+            type Error = solana_program::program_error::ProgramError;
+
+            fn try_from(
+                accounts: &'me [solana_program::account_info::AccountInfo<'a>],
+            ) -> Result<Self, Self::Error> {
+                let account_info_iter = &mut accounts.iter();
+                $(let $field = solana_program::account_info::next_account_info(account_info_iter)?;)*
+                $($(let $opt_field = solana_program::account_info::next_account_info(account_info_iter).ok();)*)?
+                Ok(Self {
+                    $($field,)*
+                    $($($opt_field,)*)?
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+
+    use crate::instructions::CreateOfferAccounts;
+
+    #[test]
+    fn test_optional_accounts_roundtrip() {
+        let k = Pubkey::new_unique;
+        let accounts = CreateOfferAccounts::new(&k(), &k(), &k(), 0).unwrap();
+        let with_trader = CreateOfferAccounts {
+            trader: Some(k()),
+            ..accounts
+        };
+        assert_eq!(accounts.to_account_metas().len(), 14);
+        let metas = with_trader.to_account_metas();
+        assert_eq!(metas.len(), 15);
+
+        let mut lamports: Vec<u64> = vec![0; metas.len()];
+        let mut data: Vec<Vec<u8>> = vec![vec![]; metas.len()];
+        let owner = Pubkey::default();
+        let infos: Vec<AccountInfo> = metas
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((meta, lamports), data)| {
+                AccountInfo::new(
+                    &meta.pubkey,
+                    meta.is_signer,
+                    meta.is_writable,
+                    lamports,
+                    data,
+                    &owner,
+                    false,
+                    0,
+                )
+            })
+            .collect();
+
+        let parsed = CreateOfferAccounts::try_from(infos.as_slice()).unwrap();
+        assert_eq!(parsed.rent.key, &with_trader.rent);
+        assert_eq!(parsed.trader.map(|t| *t.key), with_trader.trader);
+        let parsed = CreateOfferAccounts::try_from(&infos[..14]).unwrap();
+        assert!(parsed.trader.is_none());
+        assert!(CreateOfferAccounts::try_from(&infos[..13]).is_err());
+    }
+}
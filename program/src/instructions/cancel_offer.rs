@@ -1,36 +1,81 @@
-use std::io::Cursor;
+use std::{convert::TryFrom, io::Cursor};
 
+#[cfg(feature = "csv-logs")]
+use solana_program::msg;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
-    instruction::{AccountMeta, Instruction},
-    msg,
+    account_info::AccountInfo,
+    instruction::Instruction,
     program_error::ProgramError,
-    pubkey::Pubkey,
+    pubkey::{Pubkey, PubkeyError},
 };
 use spl_associated_token_account::get_associated_token_address;
 
+#[cfg(feature = "csv-logs")]
+use crate::events::CsvLog;
 use crate::{
+    account::Account,
     checks::{is_owner, is_refund_rent_to, is_refund_to, is_signer, is_token_program},
+    error::SimpleDexError,
+    events::{OfferEvent, SimpleDexEvent},
     packun::SerializePacked,
-    pda::try_create_offer_pda,
-    state::{HoldingAccount, Offer, OfferAccount},
+    pda::{try_create_offer_pda_with_program_id, try_find_trader_pda_with_program_id},
+    state::{
+        HoldingAccount, Offer, OfferAccount, OfferFields, OfferViewAccount, TraderAccount, MEMO_LEN,
+    },
 };
 
 use super::SimpleDexInstruction;
 
-pub fn process_cancel(accounts: &[AccountInfo]) -> Result<(), ProgramError> {
-    let account_info_iter = &mut accounts.iter();
+instruction_accounts! {
+    /// Accounts expected by CancelOffer, in order
+    CancelOfferAccounts {
+        owner: new_readonly(true),
+        offer: new(false),
+        holding: new(false),
+        refund_to: new(false),
+        refund_rent_to: new(false),
+        token_program: new_readonly(false),
+    }
+    optional {
+        trader: new(false),
+    }
+}
 
-    let owner = next_account_info(account_info_iter)?;
-    let offer = next_account_info(account_info_iter)?;
-    let holding = next_account_info(account_info_iter)?;
-    let refund_to = next_account_info(account_info_iter)?;
-    let refund_rent_to = next_account_info(account_info_iter)?;
-    let token_prog = next_account_info(account_info_iter)?;
+pub fn process_cancel(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    let accounts = CancelOfferAccounts::try_from(accounts)?;
 
     // Deser
-    let offer_acc = OfferAccount::load_checked(offer)?;
+    // offer is closed, not saved, so outdated offers can be canceled without migrating
+    match OfferViewAccount::load_checked(accounts.offer, program_id) {
+        Ok(offer_acc) => cancel(program_id, accounts, offer_acc),
+        Err(err) if err == SimpleDexError::LegacyOfferAccount.into() => {
+            let (offer_acc, _version) =
+                OfferAccount::load_checked_any_version(accounts.offer, program_id)?;
+            cancel(program_id, accounts, offer_acc)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn cancel<'a, 'me, O: OfferFields>(
+    program_id: &Pubkey,
+    accounts: CancelOfferAccounts<&'me AccountInfo<'a>>,
+    offer_acc: Account<'a, 'me, O>,
+) -> Result<(), ProgramError> {
+    let CancelOfferAccounts {
+        owner,
+        offer: _,
+        holding,
+        refund_to,
+        refund_rent_to,
+        token_program: token_prog,
+        trader,
+    } = accounts;
+
     let holding_acc = HoldingAccount::load_checked(holding, &offer_acc)?;
+    let trader_acc = trader
+        .map(|t| TraderAccount::load_checked(t, owner.key, program_id))
+        .transpose()?;
 
     // Checks
     is_signer(owner)?;
@@ -41,13 +86,19 @@ pub fn process_cancel(accounts: &[AccountInfo]) -> Result<(), ProgramError> {
 
     // Process
     let canceled_offer = offer_acc.account_info.key;
-    let offer_mint = offer_acc.data.offer_mint;
-    let offering = offer_acc.data.offering;
-    let accept_mint = offer_acc.data.accept_mint;
-    let accept_at_least = offer_acc.data.accept_at_least;
+    let offer_mint = offer_acc.data.offer_mint();
+    let offering = offer_acc.data.offering();
+    let accept_mint = offer_acc.data.accept_mint();
+    let accept_at_least = offer_acc.data.accept_at_least();
+    let client_order_id = offer_acc.data.client_order_id();
+    let memo = offer_acc.data.memo();
 
     holding_acc.close(&offer_acc, refund_to, refund_rent_to)?;
     offer_acc.close(refund_rent_to)?;
+    if let Some(mut trader_acc) = trader_acc {
+        trader_acc.data.on_offer_canceled(&offer_mint, &accept_mint);
+        trader_acc.save()?;
+    }
 
     log_success(
         canceled_offer,
@@ -55,7 +106,9 @@ pub fn process_cancel(accounts: &[AccountInfo]) -> Result<(), ProgramError> {
         offering,
         &accept_mint,
         accept_at_least,
-    );
+        client_order_id,
+        memo,
+    )?;
     Ok(())
 }
 
@@ -65,37 +118,87 @@ fn log_success(
     offering: u64,
     accept_mint: &Pubkey,
     accept_at_least: u64,
-) {
-    msg!(
-        "CANCEL:{},{},{},{},{}",
-        canceled_offer.to_string(),
-        offer_mint.to_string(),
+    client_order_id: u64,
+    memo: [u8; MEMO_LEN],
+) -> Result<(), SimpleDexError> {
+    let event = SimpleDexEvent::CancelOffer(OfferEvent {
+        offer: *canceled_offer,
+        offer_mint: *offer_mint,
         offering,
-        accept_mint.to_string(),
-        accept_at_least
-    );
+        accept_mint: *accept_mint,
+        accept_at_least,
+        client_order_id,
+        memo,
+    });
+    #[cfg(feature = "csv-logs")]
+    msg!("{}", CsvLog(&event));
+    event.emit()
+}
+
+impl CancelOfferAccounts {
+    /// Derives all accounts from `offer`
+    pub fn new(offer: &Offer) -> Result<Self, PubkeyError> {
+        Self::new_with_program_id(offer, &crate::id())
+    }
+
+    /// [`Self::new`] for a deployment at `program_id`
+    pub fn new_with_program_id(offer: &Offer, program_id: &Pubkey) -> Result<Self, PubkeyError> {
+        let offer_pubkey = try_create_offer_pda_with_program_id(offer, program_id)?;
+        Ok(Self {
+            owner: offer.owner,
+            offer: offer_pubkey,
+            holding: get_associated_token_address(&offer_pubkey, &offer.offer_mint),
+            refund_to: offer.refund_to,
+            refund_rent_to: offer.refund_rent_to,
+            token_program: spl_token::id(),
+            trader: None,
+        })
+    }
+
+    /// Also update the owner's `Trader` account's open offers
+    pub fn with_trader(self) -> Result<Self, PubkeyError> {
+        self.with_trader_with_program_id(&crate::id())
+    }
+
+    /// [`Self::with_trader`] for a deployment at `program_id`
+    pub fn with_trader_with_program_id(self, program_id: &Pubkey) -> Result<Self, PubkeyError> {
+        let (trader, _bump) = try_find_trader_pda_with_program_id(&self.owner, program_id)?;
+        Ok(Self {
+            trader: Some(trader),
+            ..self
+        })
+    }
+
+    pub fn instruction(&self) -> Result<Instruction, ProgramError> {
+        self.instruction_with_program_id(&crate::id())
+    }
+
+    /// [`Self::instruction`] for a deployment at `program_id`
+    pub fn instruction_with_program_id(
+        &self,
+        program_id: &Pubkey,
+    ) -> Result<Instruction, ProgramError> {
+        let mut data = [0; SimpleDexInstruction::PACKED_LEN_CANCEL_OFFER];
+        let mut writer = Cursor::new(data.as_mut());
+        SimpleDexInstruction::CancelOffer.write_bytes(&mut writer)?;
+
+        Ok(Instruction {
+            program_id: *program_id,
+            accounts: self.to_account_metas(),
+            data: data.to_vec(),
+        })
+    }
 }
 
 pub fn cancel_offer(offer: &Offer) -> Result<Instruction, ProgramError> {
-    let offer_pubkey = try_create_offer_pda(offer)?;
-    let holding = get_associated_token_address(&offer_pubkey, &offer.offer_mint);
-
-    let accounts = vec![
-        AccountMeta::new_readonly(offer.owner, true),
-        AccountMeta::new(offer_pubkey, false),
-        AccountMeta::new(holding, false),
-        AccountMeta::new(offer.refund_to, false),
-        AccountMeta::new(offer.refund_rent_to, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-
-    let mut data = [0; SimpleDexInstruction::PACKED_LEN_CANCEL_OFFER];
-    let mut writer = Cursor::new(data.as_mut());
-    SimpleDexInstruction::CancelOffer.write_bytes(&mut writer)?;
-
-    Ok(Instruction {
-        program_id: crate::id(),
-        accounts,
-        data: data.to_vec(),
-    })
+    cancel_offer_with_program_id(offer, &crate::id())
+}
+
+/// [`cancel_offer`] for a deployment at `program_id`
+pub fn cancel_offer_with_program_id(
+    offer: &Offer,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    CancelOfferAccounts::new_with_program_id(offer, program_id)?
+        .instruction_with_program_id(program_id)
 }
@@ -1,59 +1,91 @@
-use std::io::Cursor;
+use std::{convert::TryFrom, io::Cursor};
 
+#[cfg(feature = "csv-logs")]
+use solana_program::msg;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
-    instruction::{AccountMeta, Instruction},
-    msg,
+    account_info::AccountInfo,
+    instruction::Instruction,
     program_error::ProgramError,
-    pubkey::Pubkey,
+    pubkey::{Pubkey, PubkeyError},
     rent::Rent,
     system_program,
     sysvar::SysvarId,
 };
 use spl_associated_token_account::get_associated_token_address;
 
+#[cfg(feature = "csv-logs")]
+use crate::events::CsvLog;
 use crate::{
     checks::{
         is_ata_program, is_not_frozen, is_not_pubkey, is_of_mint, is_offer_pda, is_signer,
         is_system_program, is_token_program, mint_account_checked, token_account_checked,
     },
     error::SimpleDexError,
-    packun::SerializePacked,
-    pda::try_find_offer_pda,
-    state::{HoldingAccount, OfferAccount},
+    events::{OfferEvent, SimpleDexEvent},
+    packun::{DeserializePacked, SerializePacked},
+    pda::{try_find_offer_pda_with_program_id, try_find_trader_pda_with_program_id},
+    return_data::CreateOfferReturnData,
+    state::{HoldingAccount, OfferAccount, TraderAccount, MEMO_LEN},
 };
 
 use super::SimpleDexInstruction;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
 pub struct CreateOfferArgs {
     pub bump: u8,
     pub seed: u16,
     pub offering: u64,
     pub accept_at_least: u64,
+    pub client_order_id: u64,
+    pub memo: [u8; MEMO_LEN],
+}
+
+instruction_accounts! {
+    /// Accounts expected by CreateOffer, in order
+    CreateOfferAccounts {
+        payer: new(true),
+        owner: new_readonly(true),
+        pay_from: new(false),
+        offer: new(false),
+        holding: new(false),
+        refund_to: new_readonly(false),
+        credit_to: new_readonly(false),
+        refund_rent_to: new_readonly(false),
+        offer_mint: new_readonly(false),
+        accept_mint: new_readonly(false),
+        token_program: new_readonly(false),
+        associated_token_program: new_readonly(false),
+        system_program: new_readonly(false),
+        // TODO: remove once spl-ATA 1.0.5 drops
+        rent: new_readonly(false),
+    }
+    optional {
+        trader: new(false),
+    }
 }
 
 pub fn process_create_offer(
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     args: CreateOfferArgs,
 ) -> Result<(), ProgramError> {
-    let account_info_iter = &mut accounts.iter();
-
-    let payer = next_account_info(account_info_iter)?;
-    let owner = next_account_info(account_info_iter)?;
-    let pay_from = next_account_info(account_info_iter)?;
-    let offer = next_account_info(account_info_iter)?;
-    let holding = next_account_info(account_info_iter)?;
-    let refund_to = next_account_info(account_info_iter)?;
-    let credit_to = next_account_info(account_info_iter)?;
-    let refund_rent_to = next_account_info(account_info_iter)?;
-    let offer_mint = next_account_info(account_info_iter)?;
-    let accept_mint = next_account_info(account_info_iter)?;
-    let token_prog = next_account_info(account_info_iter)?;
-    let ata_prog = next_account_info(account_info_iter)?;
-    let sys_prog = next_account_info(account_info_iter)?;
-    // TODO: remove once spl-ATA 1.0.5 drops
-    let rent = next_account_info(account_info_iter)?;
+    let CreateOfferAccounts {
+        payer,
+        owner,
+        pay_from,
+        offer,
+        holding,
+        refund_to,
+        credit_to,
+        refund_rent_to,
+        offer_mint,
+        accept_mint,
+        token_program: token_prog,
+        associated_token_program: ata_prog,
+        system_program: sys_prog,
+        rent,
+        trader,
+    } = CreateOfferAccounts::try_from(accounts)?;
 
     // Deser
     let refund_to_token_acc = token_account_checked(refund_to)?;
@@ -61,6 +93,9 @@ pub fn process_create_offer(
     // This checks that the mints are initialized
     mint_account_checked(accept_mint)?;
     mint_account_checked(offer_mint)?;
+    let trader_acc = trader
+        .map(|t| TraderAccount::load_checked(t, owner.key, program_id))
+        .transpose()?;
 
     // Checks
     is_signer(payer)?;
@@ -69,7 +104,15 @@ pub fn process_create_offer(
 
     // rely on token program transfer to make sure pay_from is of the correct mint type
 
-    is_offer_pda(offer, owner, offer_mint, accept_mint, args.seed, args.bump)?;
+    is_offer_pda(
+        offer,
+        owner,
+        offer_mint,
+        accept_mint,
+        args.seed,
+        args.bump,
+        program_id,
+    )?;
 
     // rely on ATA CPI safety check to make sure holding is offer's ATA
 
@@ -118,15 +161,31 @@ pub fn process_create_offer(
         refund_to.key,
         credit_to.key,
         refund_rent_to.key,
+        args.client_order_id,
+        args.memo,
+        program_id,
     )?;
     created_holding.receive_holding_tokens(owner, pay_from, &created_offer.data)?;
+    if let Some(mut trader_acc) = trader_acc {
+        trader_acc
+            .data
+            .on_offer_created(offer_mint.key, accept_mint.key, args.seed)?;
+        trader_acc.save()?;
+    }
     log_success(
         offer.key,
         offer_mint.key,
         args.offering,
         accept_mint.key,
         args.accept_at_least,
-    );
+        args.client_order_id,
+        args.memo,
+    )?;
+    CreateOfferReturnData {
+        offer: *offer.key,
+        bump: args.bump,
+    }
+    .set()?;
     Ok(())
 }
 
@@ -136,24 +195,180 @@ fn log_success(
     offering: u64,
     accept_mint: &Pubkey,
     accept_at_least: u64,
-) {
-    // Comparison:
-    // concat_string! prog size 212120 bytes
-    // format str prog size 209208 bytes
-    //
-    // concat_string! BPF instructions executed 100638. compute units 139454
-    // format str BPF instructions exec 65614. compute units 96931
-    // with no logs at all, BPF instructions exec 29140. compute units 63314
-    msg!(
-        "CREATE:{},{},{},{},{}",
-        created_offer.to_string(),
-        offer_mint.to_string(),
+    client_order_id: u64,
+    memo: [u8; MEMO_LEN],
+) -> Result<(), SimpleDexError> {
+    let event = SimpleDexEvent::CreateOffer(OfferEvent {
+        offer: *created_offer,
+        offer_mint: *offer_mint,
         offering,
-        accept_mint.to_string(),
-        accept_at_least
-    );
+        accept_mint: *accept_mint,
+        accept_at_least,
+        client_order_id,
+        memo,
+    });
+    #[cfg(feature = "csv-logs")]
+    msg!("{}", CsvLog(&event));
+    event.emit()
+}
+
+impl CreateOfferAccounts {
+    /// Accounts for the owner's offer `seed` of `offer_mint` for `accept_mint`.
+    /// Derives the offer PDA and its holding ATA. Defaults to:
+    /// - `owner` as `payer` and `refund_rent_to`
+    /// - the owner's `offer_mint` ATA as `pay_from` and `refund_to`
+    /// - the owner's `accept_mint` ATA as `credit_to`
+    pub fn new(
+        owner: &Pubkey,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+        seed: u16,
+    ) -> Result<Self, PubkeyError> {
+        Self::new_with_program_id(owner, offer_mint, accept_mint, seed, &crate::id())
+    }
+
+    /// [`Self::new`] for a deployment at `program_id`
+    pub fn new_with_program_id(
+        owner: &Pubkey,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+        seed: u16,
+        program_id: &Pubkey,
+    ) -> Result<Self, PubkeyError> {
+        let (offer, _bump) =
+            try_find_offer_pda_with_program_id(owner, offer_mint, accept_mint, seed, program_id)?;
+        let owner_offer_ata = get_associated_token_address(owner, offer_mint);
+        Ok(Self {
+            payer: *owner,
+            owner: *owner,
+            pay_from: owner_offer_ata,
+            offer,
+            holding: get_associated_token_address(&offer, offer_mint),
+            refund_to: owner_offer_ata,
+            credit_to: get_associated_token_address(owner, accept_mint),
+            refund_rent_to: *owner,
+            offer_mint: *offer_mint,
+            accept_mint: *accept_mint,
+            token_program: spl_token::id(),
+            associated_token_program: spl_associated_token_account::id(),
+            system_program: system_program::id(),
+            rent: Rent::id(),
+            trader: None,
+        })
+    }
+
+    pub fn payer(self, payer: &Pubkey) -> Self {
+        Self {
+            payer: *payer,
+            ..self
+        }
+    }
+
+    pub fn pay_from(self, pay_from: &Pubkey) -> Self {
+        Self {
+            pay_from: *pay_from,
+            ..self
+        }
+    }
+
+    pub fn refund_to(self, refund_to: &Pubkey) -> Self {
+        Self {
+            refund_to: *refund_to,
+            ..self
+        }
+    }
+
+    pub fn credit_to(self, credit_to: &Pubkey) -> Self {
+        Self {
+            credit_to: *credit_to,
+            ..self
+        }
+    }
+
+    pub fn refund_rent_to(self, refund_rent_to: &Pubkey) -> Self {
+        Self {
+            refund_rent_to: *refund_rent_to,
+            ..self
+        }
+    }
+
+    /// Also update the owner's `Trader` account.
+    /// Use the trader account's `next_seed` as `seed`.
+    pub fn with_trader(self) -> Result<Self, PubkeyError> {
+        self.with_trader_with_program_id(&crate::id())
+    }
+
+    /// [`Self::with_trader`] for a deployment at `program_id`
+    pub fn with_trader_with_program_id(self, program_id: &Pubkey) -> Result<Self, PubkeyError> {
+        let (trader, _bump) = try_find_trader_pda_with_program_id(&self.owner, program_id)?;
+        Ok(Self {
+            trader: Some(trader),
+            ..self
+        })
+    }
+
+    /// `seed` must be the one these accounts were created with
+    pub fn instruction(
+        &self,
+        seed: u16,
+        offering: u64,
+        accept_at_least: u64,
+        client_order_id: u64,
+        memo: [u8; MEMO_LEN],
+    ) -> Result<Instruction, ProgramError> {
+        self.instruction_with_program_id(
+            &crate::id(),
+            seed,
+            offering,
+            accept_at_least,
+            client_order_id,
+            memo,
+        )
+    }
+
+    /// [`Self::instruction`] for a deployment at `program_id`
+    pub fn instruction_with_program_id(
+        &self,
+        program_id: &Pubkey,
+        seed: u16,
+        offering: u64,
+        accept_at_least: u64,
+        client_order_id: u64,
+        memo: [u8; MEMO_LEN],
+    ) -> Result<Instruction, ProgramError> {
+        let (offer, bump) = try_find_offer_pda_with_program_id(
+            &self.owner,
+            &self.offer_mint,
+            &self.accept_mint,
+            seed,
+            program_id,
+        )?;
+        if offer != self.offer {
+            return Err(SimpleDexError::IncorrectOfferAccount.into());
+        }
+
+        let mut data = [0; SimpleDexInstruction::PACKED_LEN_CREATE_OFFER];
+        let mut writer = Cursor::new(data.as_mut());
+        SimpleDexInstruction::CreateOffer(CreateOfferArgs {
+            bump,
+            seed,
+            offering,
+            accept_at_least,
+            client_order_id,
+            memo,
+        })
+        .write_bytes(&mut writer)?;
+
+        Ok(Instruction {
+            program_id: *program_id,
+            accounts: self.to_account_metas(),
+            data: data.to_vec(),
+        })
+    }
 }
 
+/// CreateOffer with every account given explicitly
+#[deprecated(note = "use `CreateOfferAccounts`, whose defaults cover most of these")]
 #[allow(clippy::too_many_arguments)]
 pub fn create_offer(
     payer: &Pubkey,
@@ -167,41 +382,14 @@ pub fn create_offer(
     seed: u16,
     offering: u64,
     accept_at_least: u64,
+    client_order_id: u64,
+    memo: [u8; MEMO_LEN],
 ) -> Result<Instruction, ProgramError> {
-    let (offer, bump) = try_find_offer_pda(owner, offer_mint, accept_mint, seed)?;
-    let holding = get_associated_token_address(&offer, offer_mint);
-
-    let accounts = vec![
-        AccountMeta::new(*payer, true),
-        AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new(*pay_from, false),
-        AccountMeta::new(offer, false),
-        AccountMeta::new(holding, false),
-        AccountMeta::new_readonly(*refund_to, false),
-        AccountMeta::new_readonly(*credit_to, false),
-        AccountMeta::new_readonly(*refund_rent_to, false),
-        AccountMeta::new_readonly(*offer_mint, false),
-        AccountMeta::new_readonly(*accept_mint, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
-        AccountMeta::new_readonly(system_program::id(), false),
-        // TODO: remove once spl-ATA 1.0.5 drops
-        AccountMeta::new_readonly(Rent::id(), false),
-    ];
-
-    let mut data = [0; SimpleDexInstruction::PACKED_LEN_CREATE_OFFER];
-    let mut writer = Cursor::new(data.as_mut());
-    SimpleDexInstruction::CreateOffer(CreateOfferArgs {
-        bump,
-        seed,
-        offering,
-        accept_at_least,
-    })
-    .write_bytes(&mut writer)?;
-
-    Ok(Instruction {
-        program_id: crate::id(),
-        accounts,
-        data: data.to_vec(),
-    })
+    CreateOfferAccounts::new(owner, offer_mint, accept_mint, seed)?
+        .payer(payer)
+        .pay_from(pay_from)
+        .refund_to(refund_to)
+        .credit_to(credit_to)
+        .refund_rent_to(refund_rent_to)
+        .instruction(seed, offering, accept_at_least, client_order_id, memo)
 }
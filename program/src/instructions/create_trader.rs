@@ -0,0 +1,81 @@
+use std::{convert::TryFrom, io::Cursor};
+
+use solana_program::{
+    account_info::AccountInfo, instruction::Instruction, program_error::ProgramError,
+    pubkey::Pubkey, system_program,
+};
+
+use crate::{
+    checks::{is_signer, is_system_program},
+    error::SimpleDexError,
+    packun::SerializePacked,
+    pda::try_find_trader_pda_with_program_id,
+    state::TraderAccount,
+};
+
+use super::SimpleDexInstruction;
+
+instruction_accounts! {
+    /// Accounts expected by CreateTrader, in order
+    CreateTraderAccounts {
+        payer: new(true),
+        owner: new_readonly(true),
+        trader: new(false),
+        system_program: new_readonly(false),
+    }
+}
+
+pub fn process_create_trader(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let CreateTraderAccounts {
+        payer,
+        owner,
+        trader,
+        system_program: sys_prog,
+    } = CreateTraderAccounts::try_from(accounts)?;
+
+    // Checks
+    is_signer(payer)?;
+    is_signer(owner)?;
+    let (expected_trader, bump) = try_find_trader_pda_with_program_id(owner.key, program_id)?;
+    if &expected_trader != trader.key {
+        return Err(SimpleDexError::IncorrectTraderAccount.into());
+    }
+    is_system_program(sys_prog)?;
+
+    // Process
+    TraderAccount::create_to(trader, payer, sys_prog, owner.key, bump, program_id)?;
+    Ok(())
+}
+
+pub fn create_trader(payer: &Pubkey, owner: &Pubkey) -> Result<Instruction, ProgramError> {
+    create_trader_with_program_id(payer, owner, &crate::id())
+}
+
+/// [`create_trader`] for a deployment at `program_id`
+pub fn create_trader_with_program_id(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (trader, _bump) = try_find_trader_pda_with_program_id(owner, program_id)?;
+
+    let accounts = CreateTraderAccounts {
+        payer: *payer,
+        owner: *owner,
+        trader,
+        system_program: system_program::id(),
+    };
+
+    let mut data = [0; SimpleDexInstruction::PACKED_LEN_CREATE_TRADER];
+    let mut writer = Cursor::new(data.as_mut());
+    SimpleDexInstruction::CreateTrader.write_bytes(&mut writer)?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(),
+        data: data.to_vec(),
+    })
+}
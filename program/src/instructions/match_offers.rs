@@ -1,47 +1,75 @@
-use std::io::Cursor;
+use std::{convert::TryFrom, io::Cursor};
 
+#[cfg(feature = "csv-logs")]
+use solana_program::msg;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
-    instruction::{AccountMeta, Instruction},
-    msg,
+    account_info::AccountInfo,
+    clock::Clock,
+    instruction::Instruction,
     program_error::ProgramError,
-    pubkey::Pubkey,
+    pubkey::{Pubkey, PubkeyError},
+    sysvar::Sysvar,
 };
 use spl_associated_token_account::get_associated_token_address;
 
+#[cfg(feature = "csv-logs")]
+use crate::events::CsvLog;
 use crate::{
     checks::{is_credit_to, is_refund_rent_to, is_refund_to, is_token_program},
     error::SimpleDexError,
-    fee::{calc_fee, MATCHER_EXCESS_BONUS_DIVISOR},
+    events::{MatchOffersEvent, SimpleDexEvent},
     packun::SerializePacked,
-    pda::try_create_offer_pda,
-    state::{HoldingAccount, Offer, OfferAccount},
-    types::OfferSeq,
+    pda::try_create_offer_pda_with_program_id,
+    quote::MatchQuote,
+    return_data::MatchOffersReturnData,
+    state::{HoldingAccount, Offer, OfferFields, OfferViewAccount},
 };
 
 use super::SimpleDexInstruction;
 
-pub fn process_match_offers(accounts: &[AccountInfo]) -> Result<(), ProgramError> {
-    let account_info_iter = &mut accounts.iter();
+instruction_accounts! {
+    /// Accounts expected by MatchOffers, in order
+    MatchOffersAccounts {
+        offering_a: new(false),
+        holding_a: new(false),
+        offering_b: new(false),
+        holding_b: new(false),
+        credit_to_a: new(false),
+        refund_to_a: new(false),
+        refund_rent_to_a: new(false),
+        credit_to_b: new(false),
+        refund_to_b: new(false),
+        refund_rent_to_b: new(false),
+        matcher_a: new(false),
+        matcher_b: new(false),
+        token_program: new_readonly(false),
+    }
+}
 
-    let offering_a = next_account_info(account_info_iter)?;
-    let holding_a = next_account_info(account_info_iter)?;
-    let offering_b = next_account_info(account_info_iter)?;
-    let holding_b = next_account_info(account_info_iter)?;
-    let credit_to_a = next_account_info(account_info_iter)?;
-    let refund_to_a = next_account_info(account_info_iter)?;
-    let refund_rent_to_a = next_account_info(account_info_iter)?;
-    let credit_to_b = next_account_info(account_info_iter)?;
-    let refund_to_b = next_account_info(account_info_iter)?;
-    let refund_rent_to_b = next_account_info(account_info_iter)?;
-    let matcher_a = next_account_info(account_info_iter)?;
-    let matcher_b = next_account_info(account_info_iter)?;
-    let token_prog = next_account_info(account_info_iter)?;
+pub fn process_match_offers(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let MatchOffersAccounts {
+        offering_a,
+        holding_a,
+        offering_b,
+        holding_b,
+        credit_to_a,
+        refund_to_a,
+        refund_rent_to_a,
+        credit_to_b,
+        refund_to_b,
+        refund_rent_to_b,
+        matcher_a,
+        matcher_b,
+        token_program: token_prog,
+    } = MatchOffersAccounts::try_from(accounts)?;
 
     // Deser
-    let offering_a_acc = OfferAccount::load_checked(offering_a)?;
+    let offering_a_acc = OfferViewAccount::load_checked(offering_a, program_id)?;
     let holding_a_acc = HoldingAccount::load_checked(holding_a, &offering_a_acc)?;
-    let offering_b_acc = OfferAccount::load_checked(offering_b)?;
+    let offering_b_acc = OfferViewAccount::load_checked(offering_b, program_id)?;
     let holding_b_acc = HoldingAccount::load_checked(holding_b, &offering_b_acc)?;
 
     // Checks
@@ -59,13 +87,8 @@ pub fn process_match_offers(accounts: &[AccountInfo]) -> Result<(), ProgramError
     is_token_program(token_prog)?;
 
     // Process
-    let (amt_a_gives, amt_b_gives) = Offer::try_match(&offering_a_acc.data, &offering_b_acc.data)?;
-    let receipt = Receipt::calc(
-        amt_a_gives,
-        amt_b_gives,
-        &offering_a_acc.data,
-        &offering_b_acc.data,
-    )?;
+    let quote = MatchQuote::calc(&offering_a_acc.data, &offering_b_acc.data)?;
+    let receipt = quote.receipt;
 
     holding_a_acc.transfer(&offering_a_acc, credit_to_b, receipt.a_to_b)?;
     holding_a_acc.transfer(&offering_a_acc, matcher_a, receipt.a_to_matcher)?;
@@ -74,136 +97,172 @@ pub fn process_match_offers(accounts: &[AccountInfo]) -> Result<(), ProgramError
 
     let token_a = holding_a_acc.data.mint;
     let token_b = holding_b_acc.data.mint;
+    let offering_a_client_order_id = offering_a_acc.data.client_order_id();
+    let offering_a_memo = offering_a_acc.data.memo();
+    let offering_b_client_order_id = offering_b_acc.data.client_order_id();
+    let offering_b_memo = offering_b_acc.data.memo();
 
-    let (offering_a_new_offering, offering_a_new_accept_at_least) = update_offer_accounts(
+    update_offer_accounts(
         offering_a_acc,
         holding_a_acc,
-        amt_a_gives,
+        quote.offering_a_new_offering,
+        quote.offering_a_new_accept_at_least,
+        quote.amt_a_gives,
+        receipt.b_to_a,
         refund_to_a,
         refund_rent_to_a,
     )?;
 
-    let (offering_b_new_offering, offering_b_new_accept_at_least) = update_offer_accounts(
+    update_offer_accounts(
         offering_b_acc,
         holding_b_acc,
-        amt_b_gives,
+        quote.offering_b_new_offering,
+        quote.offering_b_new_accept_at_least,
+        quote.amt_b_gives,
+        receipt.a_to_b,
         refund_to_b,
         refund_rent_to_b,
     )?;
 
-    log_success(
-        &token_a,
-        receipt.a_to_b,
-        &token_b,
-        receipt.b_to_a,
-        offering_a.key,
-        offering_a_new_offering,
-        offering_a_new_accept_at_least,
-        offering_b.key,
-        offering_b_new_offering,
-        offering_b_new_accept_at_least,
-    );
+    let event = MatchOffersEvent {
+        token_a,
+        token_b,
+        receipt,
+        offering_a: *offering_a.key,
+        offering_a_new_offering: quote.offering_a_new_offering,
+        offering_a_new_accept_at_least: quote.offering_a_new_accept_at_least,
+        offering_a_client_order_id,
+        offering_a_memo,
+        offering_b: *offering_b.key,
+        offering_b_new_offering: quote.offering_b_new_offering,
+        offering_b_new_accept_at_least: quote.offering_b_new_accept_at_least,
+        offering_b_client_order_id,
+        offering_b_memo,
+        matcher_a: *matcher_a.key,
+        matcher_b: *matcher_b.key,
+        slot: Clock::get()?.slot,
+    };
+    log_success(&event)?;
+
+    MatchOffersReturnData::from(&quote).set()?;
 
     Ok(())
 }
 
-struct Receipt {
-    a_to_b: u64,
-    b_to_a: u64,
-    a_to_matcher: u64,
-    b_to_matcher: u64,
+/// Records a fill of the offer, closing it and its holding account if completely filled
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_offer_accounts<'a, 'me>(
+    mut offer_acc: OfferViewAccount<'a, 'me>,
+    mut holding_acc: HoldingAccount<'a, 'me>,
+    new_offering: u64,
+    new_accept_at_least: u64,
+    amount_given: u64,
+    amount_received: u64,
+    refund_to: &AccountInfo<'a>,
+    refund_rent_to: &AccountInfo<'a>,
+) -> Result<(), ProgramError> {
+    offer_acc.data.set_offering(new_offering);
+    offer_acc.data.set_accept_at_least(new_accept_at_least);
+    offer_acc.data.record_fill(amount_given, amount_received)?;
+    // the view writes in place, so an offer left open is already saved
+    if offer_acc.data.is_closed() {
+        holding_acc = holding_acc.reload()?;
+        holding_acc.close(&offer_acc, refund_to, refund_rent_to)?;
+        offer_acc.close(refund_rent_to)?;
+    }
+    Ok(())
+}
+
+fn log_success(event: &MatchOffersEvent) -> Result<(), SimpleDexError> {
+    let event = SimpleDexEvent::MatchOffers(*event);
+    #[cfg(feature = "csv-logs")]
+    msg!("{}", CsvLog(&event));
+    event.emit()
 }
 
-impl Receipt {
-    fn calc(
-        amt_a_gives: u64,
-        amt_b_gives: u64,
+impl MatchOffersAccounts {
+    /// Derives all accounts but the matcher's token accounts from the 2 offers.
+    /// `matcher_a` and `matcher_b` are the token accounts to credit token A and B fees to.
+    pub fn new(
         offering_a: &Offer,
         offering_b: &Offer,
-    ) -> Result<Self, SimpleDexError> {
-        let (mut a_to_matcher, mut b_to_matcher) = match offering_a.relationship_with(offering_b) {
-            OfferSeq::Maker => (0, calc_fee(amt_b_gives)?),
-            OfferSeq::Taker => (calc_fee(amt_a_gives)?, 0),
-            OfferSeq::Neither => (calc_fee(amt_a_gives)? / 2, calc_fee(amt_b_gives)? / 2),
-        };
-
-        let excess_a =
-            amt_a_gives.saturating_sub(offering_b.min_willing_to_receive_for(amt_b_gives)?);
-        let excess_b =
-            amt_b_gives.saturating_sub(offering_a.min_willing_to_receive_for(amt_a_gives)?);
-        let bonus_a = excess_a / MATCHER_EXCESS_BONUS_DIVISOR;
-        let bonus_b = excess_b / MATCHER_EXCESS_BONUS_DIVISOR;
-
-        // overflow safety:
-        // bonus_a in [0, amt_a_gives / MATCHER_EXCESS_BONUS_DIVISOR]
-        let a_to_b = amt_a_gives - bonus_a;
-        let b_to_a = amt_b_gives - bonus_b;
-
-        a_to_matcher = a_to_matcher
-            .checked_add(bonus_a)
-            .ok_or(SimpleDexError::NumericalError)?;
-        b_to_matcher = b_to_matcher
-            .checked_add(bonus_b)
-            .ok_or(SimpleDexError::NumericalError)?;
+        matcher_a: &Pubkey,
+        matcher_b: &Pubkey,
+    ) -> Result<Self, PubkeyError> {
+        Self::new_with_program_id(offering_a, offering_b, matcher_a, matcher_b, &crate::id())
+    }
 
+    /// [`Self::new`] for a deployment at `program_id`
+    pub fn new_with_program_id(
+        offering_a: &Offer,
+        offering_b: &Offer,
+        matcher_a: &Pubkey,
+        matcher_b: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Self, PubkeyError> {
+        let offering_a_pubkey = try_create_offer_pda_with_program_id(offering_a, program_id)?;
+        let offering_b_pubkey = try_create_offer_pda_with_program_id(offering_b, program_id)?;
         Ok(Self {
-            a_to_b,
-            b_to_a,
-            a_to_matcher,
-            b_to_matcher,
+            offering_a: offering_a_pubkey,
+            holding_a: get_associated_token_address(&offering_a_pubkey, &offering_a.offer_mint),
+            offering_b: offering_b_pubkey,
+            holding_b: get_associated_token_address(&offering_b_pubkey, &offering_b.offer_mint),
+            credit_to_a: offering_a.credit_to,
+            refund_to_a: offering_a.refund_to,
+            refund_rent_to_a: offering_a.refund_rent_to,
+            credit_to_b: offering_b.credit_to,
+            refund_to_b: offering_b.refund_to,
+            refund_rent_to_b: offering_b.refund_rent_to,
+            matcher_a: *matcher_a,
+            matcher_b: *matcher_b,
+            token_program: spl_token::id(),
         })
     }
-}
 
-/// Returns (new offering, new accept_at_least)
-fn update_offer_accounts<'a, 'me>(
-    mut offer_acc: OfferAccount<'a, 'me>,
-    mut holding_acc: HoldingAccount<'a, 'me>,
-    amount_given: u64,
-    refund_to: &AccountInfo<'a>,
-    refund_rent_to: &AccountInfo<'a>,
-) -> Result<(u64, u64), ProgramError> {
-    offer_acc.data = offer_acc.data.update_offer_matched(amount_given)?;
-    let ret = (offer_acc.data.offering, offer_acc.data.accept_at_least);
-    match offer_acc.data.is_closed() {
-        true => {
-            holding_acc = holding_acc.reload()?;
-            holding_acc.close(&offer_acc, refund_to, refund_rent_to)?;
-            offer_acc.close(refund_rent_to)?;
-        }
-        false => {
-            offer_acc.save()?;
-        }
+    /// [`Self::new`] crediting fees to `matcher`'s ATAs
+    pub fn for_matcher(
+        offering_a: &Offer,
+        offering_b: &Offer,
+        matcher: &Pubkey,
+    ) -> Result<Self, PubkeyError> {
+        Self::for_matcher_with_program_id(offering_a, offering_b, matcher, &crate::id())
     }
-    Ok(ret)
-}
 
-#[allow(clippy::too_many_arguments)]
-fn log_success(
-    token_a: &Pubkey,
-    a_to_b: u64,
-    token_b: &Pubkey,
-    b_to_a: u64,
-    offering_a: &Pubkey,
-    offering_a_new_offering: u64,
-    offering_a_new_accept_at_least: u64,
-    offering_b: &Pubkey,
-    offering_b_new_offering: u64,
-    offering_b_new_accept_at_least: u64,
-) {
-    msg!(
-        "MATCH:{},{},{},{},{},{},{},{},{},{}",
-        token_a,
-        a_to_b,
-        token_b,
-        b_to_a,
-        offering_a,
-        offering_a_new_offering,
-        offering_a_new_accept_at_least,
-        offering_b,
-        offering_b_new_offering,
-        offering_b_new_accept_at_least,
-    );
+    /// [`Self::for_matcher`] for a deployment at `program_id`
+    pub fn for_matcher_with_program_id(
+        offering_a: &Offer,
+        offering_b: &Offer,
+        matcher: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Self, PubkeyError> {
+        Self::new_with_program_id(
+            offering_a,
+            offering_b,
+            &get_associated_token_address(matcher, &offering_a.offer_mint),
+            &get_associated_token_address(matcher, &offering_b.offer_mint),
+            program_id,
+        )
+    }
+
+    pub fn instruction(&self) -> Result<Instruction, ProgramError> {
+        self.instruction_with_program_id(&crate::id())
+    }
+
+    /// [`Self::instruction`] for a deployment at `program_id`
+    pub fn instruction_with_program_id(
+        &self,
+        program_id: &Pubkey,
+    ) -> Result<Instruction, ProgramError> {
+        let mut data = [0; SimpleDexInstruction::PACKED_LEN_MATCH_OFFERS];
+        let mut writer = Cursor::new(data.as_mut());
+        SimpleDexInstruction::MatchOffers.write_bytes(&mut writer)?;
+
+        Ok(Instruction {
+            program_id: *program_id,
+            accounts: self.to_account_metas(),
+            data: data.to_vec(),
+        })
+    }
 }
 
 pub fn match_offers(
@@ -212,76 +271,19 @@ pub fn match_offers(
     matcher_a: &Pubkey,
     matcher_b: &Pubkey,
 ) -> Result<Instruction, ProgramError> {
-    let offering_a_pubkey = try_create_offer_pda(offering_a)?;
-    let holding_a = get_associated_token_address(&offering_a_pubkey, &offering_a.offer_mint);
-    let offering_b_pubkey = try_create_offer_pda(offering_b)?;
-    let holding_b = get_associated_token_address(&offering_b_pubkey, &offering_b.offer_mint);
-
-    let accounts = vec![
-        AccountMeta::new(offering_a_pubkey, false),
-        AccountMeta::new(holding_a, false),
-        AccountMeta::new(offering_b_pubkey, false),
-        AccountMeta::new(holding_b, false),
-        AccountMeta::new(offering_a.credit_to, false),
-        AccountMeta::new(offering_a.refund_to, false),
-        AccountMeta::new(offering_a.refund_rent_to, false),
-        AccountMeta::new(offering_b.credit_to, false),
-        AccountMeta::new(offering_b.refund_to, false),
-        AccountMeta::new(offering_b.refund_rent_to, false),
-        AccountMeta::new(*matcher_a, false),
-        AccountMeta::new(*matcher_b, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-    ];
-
-    let mut data = [0; SimpleDexInstruction::PACKED_LEN_MATCH_OFFERS];
-    let mut writer = Cursor::new(data.as_mut());
-    SimpleDexInstruction::MatchOffers.write_bytes(&mut writer)?;
-
-    Ok(Instruction {
-        program_id: crate::id(),
-        accounts,
-        data: data.to_vec(),
-    })
+    match_offers_with_program_id(offering_a, offering_b, matcher_a, matcher_b, &crate::id())
 }
 
-#[cfg(test)]
-mod tests {
-    use proptest::prelude::*;
-
-    use super::*;
-
-    prop_compose! {
-        fn valid_matches()
-            (a_offering in 0..=u64::MAX, b_offering in 0..=u64::MAX)
-            (
-                a_offering in Just(a_offering), b_offering in Just(b_offering), a_accept_at_least in 0..=b_offering, b_accept_at_least in 0..=a_offering,)
-            -> (u64, u64, u64, u64) {
-                (a_offering, a_accept_at_least, b_offering, b_accept_at_least)
-            }
-    }
-
-    proptest! {
-        #[test]
-        fn test_receipt_can_be_calculated_for_valid_matches(
-            a_slot in 1..=u64::MAX,
-            b_slot in 1..=u64::MAX,
-            (a_offering, a_accept_at_least, b_offering, b_accept_at_least) in valid_matches()
-        ) {
-            let mut offering_a = Offer::default();
-            offering_a.slot = a_slot;
-            offering_a.offering = a_offering;
-            offering_a.accept_at_least = a_accept_at_least;
-            let mut offering_b = Offer::default();
-            offering_b.slot = b_slot;
-            offering_b.offering = b_offering;
-            offering_b.accept_at_least = b_accept_at_least;
-
-            let (amt_a_gives, amt_b_gives) = Offer::try_match(&offering_a, &offering_b)?;
-            let receipt = Receipt::calc(amt_a_gives, amt_b_gives, &offering_a, &offering_b)?;
-            prop_assert!(receipt.a_to_b <= amt_a_gives);
-            prop_assert!(receipt.b_to_a <= amt_b_gives);
-            prop_assert!(receipt.a_to_b >= offering_b.min_willing_to_receive_for(amt_b_gives)?);
-            prop_assert!(receipt.b_to_a >= offering_a.min_willing_to_receive_for(amt_a_gives)?);
-        }
-    }
+/// [`match_offers`] for a deployment at `program_id`
+pub fn match_offers_with_program_id(
+    offering_a: &Offer,
+    offering_b: &Offer,
+    matcher_a: &Pubkey,
+    matcher_b: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    MatchOffersAccounts::new_with_program_id(
+        offering_a, offering_b, matcher_a, matcher_b, program_id,
+    )?
+    .instruction_with_program_id(program_id)
 }
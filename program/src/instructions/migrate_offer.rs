@@ -0,0 +1,79 @@
+use std::{convert::TryFrom, io::Cursor};
+
+use solana_program::{
+    account_info::AccountInfo, instruction::Instruction, msg, program_error::ProgramError,
+    pubkey::Pubkey, system_program,
+};
+
+use crate::{
+    checks::{is_signer, is_system_program},
+    packun::SerializePacked,
+    pda::try_create_offer_pda_with_program_id,
+    state::{Offer, OfferAccount, OfferVersion},
+};
+
+use super::SimpleDexInstruction;
+
+instruction_accounts! {
+    /// Accounts expected by MigrateOffer, in order
+    MigrateOfferAccounts {
+        payer: new(true),
+        offer: new(false),
+        system_program: new_readonly(false),
+    }
+}
+
+pub fn process_migrate_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let MigrateOfferAccounts {
+        payer,
+        offer,
+        system_program: sys_prog,
+    } = MigrateOfferAccounts::try_from(accounts)?;
+
+    // Deser
+    let (offer_acc, version) = OfferAccount::load_checked_any_version(offer, program_id)?;
+
+    // Checks
+    is_signer(payer)?;
+    is_system_program(sys_prog)?;
+
+    // Process
+    // permissionless: the migrated offer's fields are unchanged
+    match version {
+        OfferVersion::CURRENT => {
+            msg!("offer already up to date");
+            Ok(())
+        }
+        _ => offer_acc.migrate(payer, sys_prog),
+    }
+}
+
+pub fn migrate_offer(payer: &Pubkey, offer: &Offer) -> Result<Instruction, ProgramError> {
+    migrate_offer_with_program_id(payer, offer, &crate::id())
+}
+
+/// [`migrate_offer`] for a deployment at `program_id`
+pub fn migrate_offer_with_program_id(
+    payer: &Pubkey,
+    offer: &Offer,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let accounts = MigrateOfferAccounts {
+        payer: *payer,
+        offer: try_create_offer_pda_with_program_id(offer, program_id)?,
+        system_program: system_program::id(),
+    };
+
+    let mut data = [0; SimpleDexInstruction::PACKED_LEN_MIGRATE_OFFER];
+    let mut writer = Cursor::new(data.as_mut());
+    SimpleDexInstruction::MigrateOffer.write_bytes(&mut writer)?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(),
+        data: data.to_vec(),
+    })
+}
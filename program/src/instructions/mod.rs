@@ -1,34 +1,112 @@
-use self::create_offer::CreateOfferArgs;
+use self::{
+    create_offer::CreateOfferArgs, sync_trader_pair::SyncTraderPairArgs, take_offer::TakeOfferArgs,
+};
+
+// needs to come first for macro defns
+mod accounts;
 
 pub mod cancel_offer;
 pub mod create_offer;
+pub mod create_trader;
 pub mod match_offers;
-mod packun;
+pub mod migrate_offer;
+pub mod quote_match;
+pub mod sync_trader_pair;
+pub mod take_offer;
+pub mod withdraw_fees;
+
+pub use cancel_offer::{cancel_offer, cancel_offer_with_program_id, CancelOfferAccounts};
+#[allow(deprecated)]
+pub use create_offer::{create_offer, CreateOfferAccounts};
+pub use create_trader::{create_trader, create_trader_with_program_id, CreateTraderAccounts};
+pub use match_offers::{match_offers, match_offers_with_program_id, MatchOffersAccounts};
+pub use migrate_offer::{migrate_offer, migrate_offer_with_program_id, MigrateOfferAccounts};
+pub use quote_match::{quote_match, quote_match_with_program_id, QuoteMatchAccounts};
+use solana_program::{instruction::AccountMeta, msg, pubkey::Pubkey};
+pub use sync_trader_pair::{
+    sync_trader_pair, sync_trader_pair_with_program_id, SyncTraderPairAccounts,
+};
+pub use take_offer::{take_offer, take_offer_with_program_id, TakeOfferAccounts};
+pub use withdraw_fees::{
+    create_fee_account, create_fee_account_with_program_id, withdraw_fees,
+    withdraw_fees_with_program_id, WithdrawFeesAccounts,
+};
+
+use crate::packun::{DeserializePacked, SerializePacked};
+
+/// An account an instruction expects, listed in the order it expects them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InstructionAccount {
+    pub name: &'static str,
+    pub signer: bool,
+    pub writable: bool,
+    /// Optional accounts come last and may be omitted
+    pub optional: bool,
+}
+
+// mirrors AccountMeta's constructors
+impl InstructionAccount {
+    pub const fn new(name: &'static str, signer: bool) -> Self {
+        Self {
+            name,
+            signer,
+            writable: true,
+            optional: false,
+        }
+    }
+
+    pub const fn new_readonly(name: &'static str, signer: bool) -> Self {
+        Self {
+            name,
+            signer,
+            writable: false,
+            optional: false,
+        }
+    }
 
-pub use cancel_offer::cancel_offer;
-pub use create_offer::create_offer;
-pub use match_offers::match_offers;
-use solana_program::msg;
+    pub const fn optional(self) -> Self {
+        Self {
+            optional: true,
+            ..self
+        }
+    }
+
+    pub fn to_account_meta(&self, pubkey: Pubkey) -> AccountMeta {
+        AccountMeta {
+            pubkey,
+            is_signer: self.signer,
+            is_writable: self.writable,
+        }
+    }
+}
 
 #[repr(C)]
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, DeserializePacked, SerializePacked)]
 pub enum SimpleDexInstruction {
     CreateOffer(CreateOfferArgs),
     CancelOffer,
     MatchOffers,
+    QuoteMatch,
+    MigrateOffer,
+    CreateTrader,
+    TakeOffer(TakeOfferArgs),
+    SyncTraderPair(SyncTraderPairArgs),
+    WithdrawFees,
 }
 
-// unfortunate, can't impl Pack for variable sized enums
+// PACKED_LEN_* consts are derived, see simpledex_derive
 impl SimpleDexInstruction {
-    pub const PACKED_LEN_CREATE_OFFER: usize = 20; // 1 + 19
-    pub const PACKED_LEN_CANCEL_OFFER: usize = 1;
-    pub const PACKED_LEN_MATCH_OFFERS: usize = 1;
-
     pub fn log_invocation(&self) {
         match self {
             Self::CreateOffer(_) => msg!("CreateOffer"),
             Self::CancelOffer => msg!("CancelOffer"),
             Self::MatchOffers => msg!("MatchOffers"),
+            Self::QuoteMatch => msg!("QuoteMatch"),
+            Self::MigrateOffer => msg!("MigrateOffer"),
+            Self::CreateTrader => msg!("CreateTrader"),
+            Self::TakeOffer(_) => msg!("TakeOffer"),
+            Self::SyncTraderPair(_) => msg!("SyncTraderPair"),
+            Self::WithdrawFees => msg!("WithdrawFees"),
         }
     }
 }
@@ -0,0 +1,80 @@
+use std::{convert::TryFrom, io::Cursor};
+
+use solana_program::{
+    account_info::AccountInfo, instruction::Instruction, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::SimpleDexError,
+    packun::SerializePacked,
+    pda::try_create_offer_pda_with_program_id,
+    quote::MatchQuote,
+    return_data::MatchOffersReturnData,
+    state::{Offer, OfferFields, OfferViewAccount},
+};
+
+use super::SimpleDexInstruction;
+
+instruction_accounts! {
+    /// Accounts expected by QuoteMatch, in order
+    QuoteMatchAccounts {
+        offering_a: new_readonly(false),
+        offering_b: new_readonly(false),
+    }
+}
+
+pub fn process_quote_match(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let QuoteMatchAccounts {
+        offering_a,
+        offering_b,
+    } = QuoteMatchAccounts::try_from(accounts)?;
+
+    // Deser
+    let offering_a_acc = OfferViewAccount::load_checked(offering_a, program_id)?;
+    let offering_b_acc = OfferViewAccount::load_checked(offering_b, program_id)?;
+
+    // Checks
+    // MatchOffers relies on the token program to ensure the mints match,
+    // there are no transfers here so check them explicitly
+    if offering_a_acc.data.offer_mint() != offering_b_acc.data.accept_mint()
+        || offering_a_acc.data.accept_mint() != offering_b_acc.data.offer_mint()
+    {
+        return Err(SimpleDexError::IncorrectMint.into());
+    }
+
+    // Process
+    // same calculations as MatchOffers, but nothing is transferred or saved
+    let quote = MatchQuote::calc(&offering_a_acc.data, &offering_b_acc.data)?;
+    MatchOffersReturnData::from(&quote).set()?;
+    Ok(())
+}
+
+pub fn quote_match(offering_a: &Offer, offering_b: &Offer) -> Result<Instruction, ProgramError> {
+    quote_match_with_program_id(offering_a, offering_b, &crate::id())
+}
+
+/// [`quote_match`] for a deployment at `program_id`
+pub fn quote_match_with_program_id(
+    offering_a: &Offer,
+    offering_b: &Offer,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let accounts = QuoteMatchAccounts {
+        offering_a: try_create_offer_pda_with_program_id(offering_a, program_id)?,
+        offering_b: try_create_offer_pda_with_program_id(offering_b, program_id)?,
+    };
+
+    let mut data = [0; SimpleDexInstruction::PACKED_LEN_QUOTE_MATCH];
+    let mut writer = Cursor::new(data.as_mut());
+    SimpleDexInstruction::QuoteMatch.write_bytes(&mut writer)?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(),
+        data: data.to_vec(),
+    })
+}
@@ -0,0 +1,94 @@
+use std::{convert::TryFrom, io::Cursor};
+
+use solana_program::{
+    account_info::AccountInfo, instruction::Instruction, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    checks::is_signer,
+    packun::{DeserializePacked, SerializePacked},
+    pda::try_find_trader_pda_with_program_id,
+    state::TraderAccount,
+};
+
+use super::SimpleDexInstruction;
+
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub struct SyncTraderPairArgs {
+    pub offer_mint: Pubkey,
+    pub accept_mint: Pubkey,
+    /// the owner's actual open offers of `offer_mint` for `accept_mint`
+    pub open_offers: u32,
+}
+
+instruction_accounts! {
+    /// Accounts expected by SyncTraderPair, in order
+    SyncTraderPairAccounts {
+        owner: new_readonly(true),
+        trader: new(false),
+    }
+}
+
+pub fn process_sync_trader_pair(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    args: SyncTraderPairArgs,
+) -> Result<(), ProgramError> {
+    let SyncTraderPairAccounts { owner, trader } = SyncTraderPairAccounts::try_from(accounts)?;
+
+    // Deser
+    let mut trader_acc = TraderAccount::load_checked(trader, owner.key, program_id)?;
+
+    // Checks
+    // the count is only the owner's bookkeeping, so the owner can set it to anything
+    is_signer(owner)?;
+
+    // Process
+    trader_acc
+        .data
+        .sync_pair(&args.offer_mint, &args.accept_mint, args.open_offers)?;
+    trader_acc.save()
+}
+
+/// Sets `owner`'s open offers of `offer_mint` for `accept_mint` in their `Trader` account,
+/// e.g. to subtract offers that were closed by being completely filled
+pub fn sync_trader_pair(
+    owner: &Pubkey,
+    offer_mint: &Pubkey,
+    accept_mint: &Pubkey,
+    open_offers: u32,
+) -> Result<Instruction, ProgramError> {
+    sync_trader_pair_with_program_id(owner, offer_mint, accept_mint, open_offers, &crate::id())
+}
+
+/// [`sync_trader_pair`] for a deployment at `program_id`
+pub fn sync_trader_pair_with_program_id(
+    owner: &Pubkey,
+    offer_mint: &Pubkey,
+    accept_mint: &Pubkey,
+    open_offers: u32,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (trader, _bump) = try_find_trader_pda_with_program_id(owner, program_id)?;
+
+    let accounts = SyncTraderPairAccounts {
+        owner: *owner,
+        trader,
+    };
+
+    let mut data = [0; SimpleDexInstruction::PACKED_LEN_SYNC_TRADER_PAIR];
+    let mut writer = Cursor::new(data.as_mut());
+    SimpleDexInstruction::SyncTraderPair(SyncTraderPairArgs {
+        offer_mint: *offer_mint,
+        accept_mint: *accept_mint,
+        open_offers,
+    })
+    .write_bytes(&mut writer)?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(),
+        data: data.to_vec(),
+    })
+}
@@ -0,0 +1,250 @@
+use std::{convert::TryFrom, io::Cursor};
+
+#[cfg(feature = "csv-logs")]
+use solana_program::msg;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    instruction::Instruction,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::{Pubkey, PubkeyError},
+    sysvar::Sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::instruction::transfer;
+
+#[cfg(feature = "csv-logs")]
+use crate::events::CsvLog;
+use crate::{
+    checks::{
+        is_credit_to, is_fee_account, is_refund_rent_to, is_refund_to, is_signer, is_token_program,
+    },
+    error::SimpleDexError,
+    events::{SimpleDexEvent, TakeOfferEvent},
+    packun::{DeserializePacked, SerializePacked},
+    pda::{try_create_offer_pda_with_program_id, try_find_fee_account_with_program_id},
+    quote::TakeQuote,
+    return_data::TakeOfferReturnData,
+    state::{HoldingAccount, Offer, OfferFields, OfferViewAccount},
+};
+
+use super::{match_offers::update_offer_accounts, SimpleDexInstruction};
+
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub struct TakeOfferArgs {
+    /// max `accept_mint` tokens to pay the offer, not including fees
+    pub amount_in: u64,
+    /// min `offer_mint` tokens to receive, fails with `SlippageExceeded` otherwise
+    pub min_amount_out: u64,
+}
+
+instruction_accounts! {
+    /// Accounts expected by TakeOffer, in order
+    TakeOfferAccounts {
+        taker: new_readonly(true),
+        pay_from: new(false),
+        receive_to: new(false),
+        offer: new(false),
+        holding: new(false),
+        credit_to: new(false),
+        refund_to: new(false),
+        refund_rent_to: new(false),
+        fee_account: new(false),
+        token_program: new_readonly(false),
+    }
+}
+
+pub fn process_take_offer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    args: TakeOfferArgs,
+) -> Result<(), ProgramError> {
+    let TakeOfferAccounts {
+        taker,
+        pay_from,
+        receive_to,
+        offer,
+        holding,
+        credit_to,
+        refund_to,
+        refund_rent_to,
+        fee_account,
+        token_program: token_prog,
+    } = TakeOfferAccounts::try_from(accounts)?;
+
+    // Deser
+    let offer_acc = OfferViewAccount::load_checked(offer, program_id)?;
+    let holding_acc = HoldingAccount::load_checked(holding, &offer_acc)?;
+
+    // Checks
+    is_signer(taker)?;
+
+    // rely on token program transfer checks to ensure
+    // pay_from is of accept_mint and receive_to is of offer_mint
+
+    is_fee_account(fee_account, &offer_acc.data.accept_mint(), program_id)?;
+
+    is_credit_to(credit_to.key, &offer_acc.data)?;
+    is_refund_to(refund_to.key, &offer_acc.data)?;
+    is_refund_rent_to(refund_rent_to.key, &offer_acc.data)?;
+
+    is_token_program(token_prog)?;
+
+    // Process
+    let quote = TakeQuote::calc(&offer_acc.data, args.amount_in)?;
+    if quote.amount_out == 0 || quote.amount_out < args.min_amount_out {
+        return Err(SimpleDexError::SlippageExceeded.into());
+    }
+
+    transfer_from_taker(taker, pay_from, credit_to, quote.amount_in)?;
+    transfer_from_taker(taker, pay_from, fee_account, quote.fee)?;
+    holding_acc.transfer(&offer_acc, receive_to, quote.amount_out)?;
+
+    let event = TakeOfferEvent {
+        offer: *offer.key,
+        offer_mint: offer_acc.data.offer_mint(),
+        accept_mint: offer_acc.data.accept_mint(),
+        taker: *taker.key,
+        amount_in: quote.amount_in,
+        amount_out: quote.amount_out,
+        fee: quote.fee,
+        fee_account: *fee_account.key,
+        new_offering: quote.new_offering,
+        new_accept_at_least: quote.new_accept_at_least,
+        client_order_id: offer_acc.data.client_order_id(),
+        memo: offer_acc.data.memo(),
+        slot: Clock::get()?.slot,
+    };
+
+    update_offer_accounts(
+        offer_acc,
+        holding_acc,
+        quote.new_offering,
+        quote.new_accept_at_least,
+        quote.amount_out,
+        quote.amount_in,
+        refund_to,
+        refund_rent_to,
+    )?;
+
+    log_success(&event)?;
+
+    TakeOfferReturnData::from(&quote).set()?;
+
+    Ok(())
+}
+
+fn transfer_from_taker<'a>(
+    taker: &AccountInfo<'a>,
+    pay_from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    amt: u64,
+) -> Result<(), ProgramError> {
+    let ix = transfer(&spl_token::id(), pay_from.key, to.key, taker.key, &[], amt)?;
+    invoke(&ix, &[pay_from.to_owned(), to.to_owned(), taker.to_owned()])
+}
+
+fn log_success(event: &TakeOfferEvent) -> Result<(), SimpleDexError> {
+    let event = SimpleDexEvent::TakeOffer(*event);
+    #[cfg(feature = "csv-logs")]
+    msg!("{}", CsvLog(&event));
+    event.emit()
+}
+
+impl TakeOfferAccounts {
+    /// Derives the offer's accounts from `offer`.
+    /// Defaults to the taker's ATAs as `pay_from` and `receive_to`.
+    pub fn new(offer: &Offer, taker: &Pubkey) -> Result<Self, PubkeyError> {
+        Self::new_with_program_id(offer, taker, &crate::id())
+    }
+
+    /// [`Self::new`] for a deployment at `program_id`
+    pub fn new_with_program_id(
+        offer: &Offer,
+        taker: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Self, PubkeyError> {
+        let offer_pubkey = try_create_offer_pda_with_program_id(offer, program_id)?;
+        Ok(Self {
+            taker: *taker,
+            pay_from: get_associated_token_address(taker, &offer.accept_mint),
+            receive_to: get_associated_token_address(taker, &offer.offer_mint),
+            offer: offer_pubkey,
+            holding: get_associated_token_address(&offer_pubkey, &offer.offer_mint),
+            credit_to: offer.credit_to,
+            refund_to: offer.refund_to,
+            refund_rent_to: offer.refund_rent_to,
+            fee_account: try_find_fee_account_with_program_id(&offer.accept_mint, program_id)?,
+            token_program: spl_token::id(),
+        })
+    }
+
+    pub fn pay_from(self, pay_from: &Pubkey) -> Self {
+        Self {
+            pay_from: *pay_from,
+            ..self
+        }
+    }
+
+    pub fn receive_to(self, receive_to: &Pubkey) -> Self {
+        Self {
+            receive_to: *receive_to,
+            ..self
+        }
+    }
+
+    pub fn instruction(
+        &self,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<Instruction, ProgramError> {
+        self.instruction_with_program_id(&crate::id(), amount_in, min_amount_out)
+    }
+
+    /// [`Self::instruction`] for a deployment at `program_id`
+    pub fn instruction_with_program_id(
+        &self,
+        program_id: &Pubkey,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<Instruction, ProgramError> {
+        let mut data = [0; SimpleDexInstruction::PACKED_LEN_TAKE_OFFER];
+        let mut writer = Cursor::new(data.as_mut());
+        SimpleDexInstruction::TakeOffer(TakeOfferArgs {
+            amount_in,
+            min_amount_out,
+        })
+        .write_bytes(&mut writer)?;
+
+        Ok(Instruction {
+            program_id: *program_id,
+            accounts: self.to_account_metas(),
+            data: data.to_vec(),
+        })
+    }
+}
+
+pub fn take_offer(
+    offer: &Offer,
+    taker: &Pubkey,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Result<Instruction, ProgramError> {
+    take_offer_with_program_id(offer, taker, amount_in, min_amount_out, &crate::id())
+}
+
+/// [`take_offer`] for a deployment at `program_id`
+pub fn take_offer_with_program_id(
+    offer: &Offer,
+    taker: &Pubkey,
+    amount_in: u64,
+    min_amount_out: u64,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    TakeOfferAccounts::new_with_program_id(offer, taker, program_id)?.instruction_with_program_id(
+        program_id,
+        amount_in,
+        min_amount_out,
+    )
+}
@@ -0,0 +1,143 @@
+use std::{convert::TryFrom, io::Cursor};
+
+use solana_program::{
+    account_info::AccountInfo,
+    bpf_loader_upgradeable,
+    instruction::Instruction,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::{Pubkey, PubkeyError},
+};
+use spl_associated_token_account::create_associated_token_account;
+use spl_token::instruction::transfer;
+
+use crate::{
+    checks::{
+        is_fee_account, is_fee_authority, is_signer, is_token_program, is_upgrade_authority,
+        token_account_checked,
+    },
+    packun::SerializePacked,
+    pda::{
+        try_find_fee_account_with_program_id, try_find_fee_authority_pda_with_program_id,
+        FEE_AUTHORITY_PDA_PREFIX,
+    },
+};
+
+use super::SimpleDexInstruction;
+
+instruction_accounts! {
+    /// Accounts expected by WithdrawFees, in order
+    WithdrawFeesAccounts {
+        upgrade_authority: new_readonly(true),
+        program_data: new_readonly(false),
+        fee_authority: new_readonly(false),
+        fee_account: new(false),
+        withdraw_to: new(false),
+        token_program: new_readonly(false),
+    }
+}
+
+pub fn process_withdraw_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let WithdrawFeesAccounts {
+        upgrade_authority,
+        program_data,
+        fee_authority,
+        fee_account,
+        withdraw_to,
+        token_program: token_prog,
+    } = WithdrawFeesAccounts::try_from(accounts)?;
+
+    // Deser
+    let fee_acc = token_account_checked(fee_account)?;
+
+    // Checks
+    // the program's upgrade authority is the configured fee authority
+    is_signer(upgrade_authority)?;
+    is_upgrade_authority(upgrade_authority, program_data, program_id)?;
+
+    let bump = is_fee_authority(fee_authority, program_id)?;
+    is_fee_account(fee_account, &fee_acc.mint, program_id)?;
+
+    // rely on token program transfer checks to ensure withdraw_to is of the fee mint
+
+    is_token_program(token_prog)?;
+
+    // Process
+    let ix = transfer(
+        &spl_token::id(),
+        fee_account.key,
+        withdraw_to.key,
+        fee_authority.key,
+        &[],
+        fee_acc.amount,
+    )?;
+    invoke_signed(
+        &ix,
+        &[
+            fee_account.to_owned(),
+            withdraw_to.to_owned(),
+            fee_authority.to_owned(),
+        ],
+        &[&[FEE_AUTHORITY_PDA_PREFIX, &[bump]]],
+    )
+}
+
+/// Withdraws all fees collected in `mint` to `withdraw_to`, signed by the program's upgrade authority
+pub fn withdraw_fees(
+    upgrade_authority: &Pubkey,
+    mint: &Pubkey,
+    withdraw_to: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    withdraw_fees_with_program_id(upgrade_authority, mint, withdraw_to, &crate::id())
+}
+
+/// [`withdraw_fees`] for a deployment at `program_id`
+pub fn withdraw_fees_with_program_id(
+    upgrade_authority: &Pubkey,
+    mint: &Pubkey,
+    withdraw_to: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let (program_data, _bump) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    let (fee_authority, _bump) = try_find_fee_authority_pda_with_program_id(program_id)?;
+
+    let accounts = WithdrawFeesAccounts {
+        upgrade_authority: *upgrade_authority,
+        program_data,
+        fee_authority,
+        fee_account: try_find_fee_account_with_program_id(mint, program_id)?,
+        withdraw_to: *withdraw_to,
+        token_program: spl_token::id(),
+    };
+
+    let mut data = [0; SimpleDexInstruction::PACKED_LEN_WITHDRAW_FEES];
+    let mut writer = Cursor::new(data.as_mut());
+    SimpleDexInstruction::WithdrawFees.write_bytes(&mut writer)?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(),
+        data: data.to_vec(),
+    })
+}
+
+/// Creates the fee account TakeOffer credits its fees in `mint` to.
+/// Not an instruction of this program: run it once per accept mint when deploying,
+/// TakeOffer fails for offers accepting `mint` until it exists.
+pub fn create_fee_account(payer: &Pubkey, mint: &Pubkey) -> Result<Instruction, PubkeyError> {
+    create_fee_account_with_program_id(payer, mint, &crate::id())
+}
+
+/// [`create_fee_account`] for a deployment at `program_id`
+pub fn create_fee_account_with_program_id(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Instruction, PubkeyError> {
+    let (fee_authority, _bump) = try_find_fee_authority_pda_with_program_id(program_id)?;
+    Ok(create_associated_token_account(payer, &fee_authority, mint))
+}
@@ -7,10 +7,15 @@ pub mod checks;
 
 pub mod account;
 pub mod error;
+pub mod events;
 pub mod fee;
+#[cfg(feature = "idl")]
+pub mod idl;
 pub mod instructions;
 pub mod packun;
 pub mod processor;
+pub mod quote;
+pub mod return_data;
 pub mod state;
 pub mod types;
 
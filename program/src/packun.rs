@@ -9,6 +9,107 @@ use solana_program::pubkey::Pubkey;
 
 use crate::error::SimpleDexError;
 
+pub use simpledex_derive::{DeserializePacked, SerializePacked};
+
+/// Compile-time length of a type's packed representation.
+/// For enums, this is the length of the largest variant.
+pub trait PackedLen {
+    const PACKED_LEN: usize;
+}
+
+/// Describes a type's packed representation,
+/// used to generate the program's interface description.
+pub trait PackedLayout {
+    fn layout() -> Layout;
+
+    /// Definition of the type for [`Layout::Defined`] types
+    fn type_def() -> Option<TypeDef> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    U8,
+    U16,
+    U32,
+    U64,
+    Pubkey,
+    /// `[u8; N]`
+    Bytes(usize),
+    /// A struct or enum with a [`TypeDef`] of this name
+    Defined(&'static str),
+    /// `[T; N]` of a [`Layout::Defined`] type `T` of this name
+    DefinedArray(&'static str, usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeDef {
+    /// Fields packed in order
+    Struct {
+        name: &'static str,
+        fields: Vec<FieldDef>,
+    },
+    /// A `u8` tag followed by the variant's fields packed in order
+    Enum {
+        name: &'static str,
+        variants: Vec<VariantDef>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldDef {
+    /// None for tuple fields
+    pub name: Option<&'static str>,
+    pub layout: Layout,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariantDef {
+    pub name: &'static str,
+    pub tag: u8,
+    pub fields: Vec<FieldDef>,
+}
+
+macro_rules! impl_packed_primitive {
+    ($t: ty, $len: expr, $layout: expr) => {
+        impl PackedLen for $t {
+            const PACKED_LEN: usize = $len;
+        }
+
+        impl PackedLayout for $t {
+            fn layout() -> Layout {
+                $layout
+            }
+        }
+    };
+}
+
+impl_packed_primitive!(u8, 1, Layout::U8);
+impl_packed_primitive!(u16, 2, Layout::U16);
+impl_packed_primitive!(u32, 4, Layout::U32);
+impl_packed_primitive!(u64, 8, Layout::U64);
+impl_packed_primitive!(Pubkey, 32, Layout::Pubkey);
+
+impl<const N: usize> PackedLen for [u8; N] {
+    const PACKED_LEN: usize = N;
+}
+
+impl<const N: usize> PackedLayout for [u8; N] {
+    fn layout() -> Layout {
+        Layout::Bytes(N)
+    }
+}
+
+/// `core::cmp::max` isn't const
+pub const fn const_max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
 pub trait DeserializePacked<R: Read, E: Error> {
     fn read_bytes(buf: &mut R) -> Result<Self, E>
     where
@@ -42,8 +143,15 @@ macro_rules! impl_deserialize_packed_le_primitive {
 
 impl_deserialize_packed_le_primitive!(u8);
 impl_deserialize_packed_le_primitive!(u16);
+impl_deserialize_packed_le_primitive!(u32);
 impl_deserialize_packed_le_primitive!(u64);
 
+impl<R: Read, const N: usize> DeserializePacked<R, SimpleDexError> for [u8; N] {
+    fn read_bytes(buf: &mut R) -> Result<Self, SimpleDexError> {
+        try_read(buf)
+    }
+}
+
 impl<R: Read> DeserializePacked<R, SimpleDexError> for Pubkey {
     fn read_bytes(buf: &mut R) -> Result<Self, SimpleDexError>
     where
@@ -83,8 +191,15 @@ macro_rules! impl_serialize_packed_le_primitive {
 
 impl_serialize_packed_le_primitive!(u8);
 impl_serialize_packed_le_primitive!(u16);
+impl_serialize_packed_le_primitive!(u32);
 impl_serialize_packed_le_primitive!(u64);
 
+impl<W: Write, const N: usize> SerializePacked<W, SimpleDexError> for [u8; N] {
+    fn write_bytes(&self, buf: &mut W) -> Result<(), SimpleDexError> {
+        try_write(self, buf)
+    }
+}
+
 impl<W: Write> SerializePacked<W, SimpleDexError> for Pubkey {
     fn write_bytes(&self, buf: &mut W) -> Result<(), SimpleDexError> {
         try_write(self.as_ref(), buf)
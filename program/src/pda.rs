@@ -1,8 +1,9 @@
 #![macro_use]
 
 use solana_program::pubkey::{Pubkey, PubkeyError};
+use spl_associated_token_account::get_associated_token_address;
 
-use crate::state::Offer;
+use crate::state::{Offer, Trader};
 
 macro_rules! pda_seed {
     (@pubkey $pubkey_seed: expr) => {
@@ -50,14 +51,105 @@ pub fn try_find_offer_pda(
     offer_mint: &Pubkey,
     accept_mint: &Pubkey,
     seed: u16,
+) -> Result<(Pubkey, u8), PubkeyError> {
+    try_find_offer_pda_with_program_id(owner, offer_mint, accept_mint, seed, &crate::id())
+}
+
+/// [`try_find_offer_pda`] for a deployment at `program_id`
+pub fn try_find_offer_pda_with_program_id(
+    owner: &Pubkey,
+    offer_mint: &Pubkey,
+    accept_mint: &Pubkey,
+    seed: u16,
+    program_id: &Pubkey,
 ) -> Result<(Pubkey, u8), PubkeyError> {
     Pubkey::try_find_program_address(
         offer_pda_seeds!(owner, offer_mint, accept_mint, seed),
-        &crate::id(),
+        program_id,
     )
     .ok_or(PubkeyError::InvalidSeeds)
 }
 
 pub fn try_create_offer_pda(offer: &Offer) -> Result<Pubkey, PubkeyError> {
-    Pubkey::create_program_address(offer_pda_seeds!(offer), &crate::id())
+    try_create_offer_pda_with_program_id(offer, &crate::id())
+}
+
+/// [`try_create_offer_pda`] for a deployment at `program_id`
+pub fn try_create_offer_pda_with_program_id(
+    offer: &Offer,
+    program_id: &Pubkey,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(offer_pda_seeds!(offer), program_id)
+}
+
+pub const TRADER_PDA_PREFIX: &[u8] = b"trader";
+
+macro_rules! trader_pda_seeds {
+    ($owner: expr $(,)?) => (
+        &[
+            $crate::pda::TRADER_PDA_PREFIX,
+            pda_seed!(@pubkey $owner),
+        ]
+    );
+    ($owner: expr, $bump: expr $(,)?) => (
+        &[
+            $crate::pda::TRADER_PDA_PREFIX,
+            pda_seed!(@pubkey $owner),
+            pda_seed!(@u_8 $bump),
+        ]
+    );
+}
+
+pub fn try_find_trader_pda(owner: &Pubkey) -> Result<(Pubkey, u8), PubkeyError> {
+    try_find_trader_pda_with_program_id(owner, &crate::id())
+}
+
+/// [`try_find_trader_pda`] for a deployment at `program_id`
+pub fn try_find_trader_pda_with_program_id(
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<(Pubkey, u8), PubkeyError> {
+    Pubkey::try_find_program_address(trader_pda_seeds!(owner), program_id)
+        .ok_or(PubkeyError::InvalidSeeds)
+}
+
+pub fn try_create_trader_pda(trader: &Trader) -> Result<Pubkey, PubkeyError> {
+    try_create_trader_pda_with_program_id(trader, &crate::id())
+}
+
+/// [`try_create_trader_pda`] for a deployment at `program_id`
+pub fn try_create_trader_pda_with_program_id(
+    trader: &Trader,
+    program_id: &Pubkey,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(trader_pda_seeds!(trader.owner, trader.bump), program_id)
+}
+
+pub const FEE_AUTHORITY_PDA_PREFIX: &[u8] = b"fee_authority";
+
+/// The program-controlled authority whose `accept_mint` ATAs collect TakeOffer's taker fees
+pub fn try_find_fee_authority_pda() -> Result<(Pubkey, u8), PubkeyError> {
+    try_find_fee_authority_pda_with_program_id(&crate::id())
+}
+
+/// [`try_find_fee_authority_pda`] for a deployment at `program_id`
+pub fn try_find_fee_authority_pda_with_program_id(
+    program_id: &Pubkey,
+) -> Result<(Pubkey, u8), PubkeyError> {
+    Pubkey::try_find_program_address(&[FEE_AUTHORITY_PDA_PREFIX], program_id)
+        .ok_or(PubkeyError::InvalidSeeds)
+}
+
+/// The only token account TakeOffer accepts as `fee_account` for fees in `mint`
+pub fn try_find_fee_account(mint: &Pubkey) -> Result<Pubkey, PubkeyError> {
+    try_find_fee_account_with_program_id(mint, &crate::id())
+}
+
+/// [`try_find_fee_account`] for a deployment at `program_id`
+pub fn try_find_fee_account_with_program_id(
+    mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Pubkey, PubkeyError> {
+    let (fee_authority, _bump) = try_find_fee_authority_pda_with_program_id(program_id)?;
+    Ok(get_associated_token_address(&fee_authority, mint))
 }
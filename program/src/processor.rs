@@ -1,15 +1,16 @@
 //! Program processor
 
-use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
-    pubkey::Pubkey,
-};
+#[cfg(not(feature = "any-program-id"))]
+use solana_program::program_error::ProgramError;
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
 
 use crate::{
-    id,
     instructions::{
         cancel_offer::process_cancel, create_offer::process_create_offer,
-        match_offers::process_match_offers, SimpleDexInstruction,
+        create_trader::process_create_trader, match_offers::process_match_offers,
+        migrate_offer::process_migrate_offer, quote_match::process_quote_match,
+        sync_trader_pair::process_sync_trader_pair, take_offer::process_take_offer,
+        withdraw_fees::process_withdraw_fees, SimpleDexInstruction,
     },
     packun::DeserializePacked,
 };
@@ -18,16 +19,27 @@ pub struct Processor {}
 
 impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
-        if program_id != &id() {
+        #[cfg(not(feature = "any-program-id"))]
+        if program_id != &crate::id() {
             return Err(ProgramError::IncorrectProgramId);
         }
         let mut reader = input;
         let instruction = SimpleDexInstruction::read_bytes(&mut reader)?;
         instruction.log_invocation();
         match instruction {
-            SimpleDexInstruction::CreateOffer(args) => process_create_offer(accounts, args),
-            SimpleDexInstruction::CancelOffer => process_cancel(accounts),
-            SimpleDexInstruction::MatchOffers => process_match_offers(accounts),
+            SimpleDexInstruction::CreateOffer(args) => {
+                process_create_offer(program_id, accounts, args)
+            }
+            SimpleDexInstruction::CancelOffer => process_cancel(program_id, accounts),
+            SimpleDexInstruction::MatchOffers => process_match_offers(program_id, accounts),
+            SimpleDexInstruction::QuoteMatch => process_quote_match(program_id, accounts),
+            SimpleDexInstruction::MigrateOffer => process_migrate_offer(program_id, accounts),
+            SimpleDexInstruction::CreateTrader => process_create_trader(program_id, accounts),
+            SimpleDexInstruction::TakeOffer(args) => process_take_offer(program_id, accounts, args),
+            SimpleDexInstruction::SyncTraderPair(args) => {
+                process_sync_trader_pair(program_id, accounts, args)
+            }
+            SimpleDexInstruction::WithdrawFees => process_withdraw_fees(program_id, accounts),
         }
     }
 }
@@ -0,0 +1,237 @@
+//! Economics of matching 2 offers, or of taking an offer directly.
+//!
+//! This is the exact code path MatchOffers, QuoteMatch and TakeOffer use on-chain,
+//! so off-chain matchers can use it to evaluate matches without drifting from the program.
+
+use crate::{
+    error::SimpleDexError,
+    fee::{calc_fee, MATCHER_EXCESS_BONUS_DIVISOR},
+    packun::{DeserializePacked, SerializePacked},
+    state::{Offer, OfferFields},
+    types::{OfferSeq, Ratio},
+};
+
+/// Result of matching offering_a, which offers token A for token B,
+/// with offering_b, which offers token B for token A.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchQuote {
+    /// token A taken out of offering_a's offering, not including fees
+    pub amt_a_gives: u64,
+    /// token B taken out of offering_b's offering, not including fees
+    pub amt_b_gives: u64,
+    pub receipt: Receipt,
+    pub offering_a_new_offering: u64,
+    pub offering_a_new_accept_at_least: u64,
+    pub offering_b_new_offering: u64,
+    pub offering_b_new_accept_at_least: u64,
+    /// whether offering_a is completely filled and will be closed
+    pub closes_a: bool,
+    /// whether offering_b is completely filled and will be closed
+    pub closes_b: bool,
+}
+
+impl MatchQuote {
+    pub fn calc<A: OfferFields, B: OfferFields>(
+        offering_a: &A,
+        offering_b: &B,
+    ) -> Result<Self, SimpleDexError> {
+        let (amt_a_gives, amt_b_gives) = Offer::try_match(offering_a, offering_b)?;
+        let receipt = Receipt::calc(amt_a_gives, amt_b_gives, offering_a, offering_b)?;
+        let (offering_a_new_offering, offering_a_new_accept_at_least) =
+            offering_a.matched_terms(amt_a_gives)?;
+        let (offering_b_new_offering, offering_b_new_accept_at_least) =
+            offering_b.matched_terms(amt_b_gives)?;
+        Ok(Self {
+            amt_a_gives,
+            amt_b_gives,
+            receipt,
+            offering_a_new_offering,
+            offering_a_new_accept_at_least,
+            offering_b_new_offering,
+            offering_b_new_accept_at_least,
+            closes_a: offering_a_new_offering == 0 || offering_a_new_accept_at_least == 0,
+            closes_b: offering_b_new_offering == 0 || offering_b_new_accept_at_least == 0,
+        })
+    }
+}
+
+/// Result of a taker paying up to `max_amount_in` of an offer's accept_mint
+/// for its offer_mint with TakeOffer, at the offer's limit price.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TakeQuote {
+    /// accept_mint credited to the offer, not including fees
+    pub amount_in: u64,
+    /// offer_mint taken out of the offer's offering and credited to the taker
+    pub amount_out: u64,
+    /// taker fee on `amount_in`, paid by the taker on top of it
+    pub fee: u64,
+    pub new_offering: u64,
+    pub new_accept_at_least: u64,
+    /// whether the offer is completely filled and will be closed
+    pub closes: bool,
+}
+
+impl TakeQuote {
+    /// The taker pays the least `amount_in <= max_amount_in` the offer
+    /// is willing to receive for the `amount_out` it gives, so rounding never costs the taker
+    /// more than the offer's limit price.
+    pub fn calc<O: OfferFields>(offer: &O, max_amount_in: u64) -> Result<Self, SimpleDexError> {
+        let (amount_in, amount_out) = match max_amount_in >= offer.accept_at_least() {
+            true => (offer.accept_at_least(), offer.offering()),
+            false => {
+                let amount_out = Ratio::new(offer.offering(), offer.accept_at_least())?
+                    .apply_floor_exact(max_amount_in)?;
+                (offer.min_willing_to_receive_for(amount_out)?, amount_out)
+            }
+        };
+        let fee = calc_fee(amount_in)?;
+        let (new_offering, new_accept_at_least) = offer.matched_terms(amount_out)?;
+        Ok(Self {
+            amount_in,
+            amount_out,
+            fee,
+            new_offering,
+            new_accept_at_least,
+            closes: new_offering == 0 || new_accept_at_least == 0,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub struct Receipt {
+    /// token A credited to offering_b
+    pub a_to_b: u64,
+    /// token B credited to offering_a
+    pub b_to_a: u64,
+    /// token A paid to the matcher, `a_fee + a_bonus`
+    pub a_to_matcher: u64,
+    /// token B paid to the matcher, `b_fee + b_bonus`
+    pub b_to_matcher: u64,
+    /// taker fee paid by offering_a
+    pub a_fee: u64,
+    /// taker fee paid by offering_b
+    pub b_fee: u64,
+    /// share of token A excess given to the matcher
+    pub a_bonus: u64,
+    /// share of token B excess given to the matcher
+    pub b_bonus: u64,
+    /// offering_a's relationship with offering_b
+    pub a_seq: OfferSeq,
+}
+
+impl Receipt {
+    fn calc<A: OfferFields, B: OfferFields>(
+        amt_a_gives: u64,
+        amt_b_gives: u64,
+        offering_a: &A,
+        offering_b: &B,
+    ) -> Result<Self, SimpleDexError> {
+        let a_seq = offering_a.relationship_with(offering_b);
+        let (a_fee, b_fee) = match a_seq {
+            OfferSeq::Maker => (0, calc_fee(amt_b_gives)?),
+            OfferSeq::Taker => (calc_fee(amt_a_gives)?, 0),
+            OfferSeq::Neither => (calc_fee(amt_a_gives)? / 2, calc_fee(amt_b_gives)? / 2),
+        };
+
+        let excess_a =
+            amt_a_gives.saturating_sub(offering_b.min_willing_to_receive_for(amt_b_gives)?);
+        let excess_b =
+            amt_b_gives.saturating_sub(offering_a.min_willing_to_receive_for(amt_a_gives)?);
+        let a_bonus = excess_a / MATCHER_EXCESS_BONUS_DIVISOR;
+        let b_bonus = excess_b / MATCHER_EXCESS_BONUS_DIVISOR;
+
+        // overflow safety:
+        // a_bonus in [0, amt_a_gives / MATCHER_EXCESS_BONUS_DIVISOR]
+        let a_to_b = amt_a_gives - a_bonus;
+        let b_to_a = amt_b_gives - b_bonus;
+
+        let a_to_matcher = a_fee
+            .checked_add(a_bonus)
+            .ok_or(SimpleDexError::NumericalError)?;
+        let b_to_matcher = b_fee
+            .checked_add(b_bonus)
+            .ok_or(SimpleDexError::NumericalError)?;
+
+        Ok(Self {
+            a_to_b,
+            b_to_a,
+            a_to_matcher,
+            b_to_matcher,
+            a_fee,
+            b_fee,
+            a_bonus,
+            b_bonus,
+            a_seq,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    prop_compose! {
+        fn valid_matches()
+            (a_offering in 0..=u64::MAX, b_offering in 0..=u64::MAX)
+            (
+                a_offering in Just(a_offering), b_offering in Just(b_offering), a_accept_at_least in 0..=b_offering, b_accept_at_least in 0..=a_offering,)
+            -> (u64, u64, u64, u64) {
+                (a_offering, a_accept_at_least, b_offering, b_accept_at_least)
+            }
+    }
+
+    proptest! {
+        #[test]
+        fn test_receipt_can_be_calculated_for_valid_matches(
+            a_slot in 1..=u64::MAX,
+            b_slot in 1..=u64::MAX,
+            (a_offering, a_accept_at_least, b_offering, b_accept_at_least) in valid_matches()
+        ) {
+            let offering_a = Offer {
+                slot: a_slot,
+                offering: a_offering,
+                accept_at_least: a_accept_at_least,
+                ..Offer::default()
+            };
+            let offering_b = Offer {
+                slot: b_slot,
+                offering: b_offering,
+                accept_at_least: b_accept_at_least,
+                ..Offer::default()
+            };
+
+            let quote = MatchQuote::calc(&offering_a, &offering_b)?;
+            let (amt_a_gives, amt_b_gives) = (quote.amt_a_gives, quote.amt_b_gives);
+            let receipt = quote.receipt;
+            prop_assert!(receipt.a_to_b <= amt_a_gives);
+            prop_assert!(receipt.b_to_a <= amt_b_gives);
+            prop_assert!(receipt.a_to_b >= offering_b.min_willing_to_receive_for(amt_b_gives)?);
+            prop_assert!(receipt.b_to_a >= offering_a.min_willing_to_receive_for(amt_a_gives)?);
+            prop_assert!(quote.closes_a || quote.closes_b);
+        }
+
+        #[test]
+        fn test_take_quote_respects_limit_price(
+            offering in 1..=u64::MAX,
+            accept_at_least in 1..=u64::MAX,
+            max_amount_in in 0..=u64::MAX,
+        ) {
+            let offer = Offer {
+                offering,
+                accept_at_least,
+                original_offering: offering,
+                original_accept_at_least: accept_at_least,
+                ..Offer::default()
+            };
+
+            let quote = TakeQuote::calc(&offer, max_amount_in)?;
+            prop_assert!(quote.amount_in <= max_amount_in);
+            prop_assert!(quote.amount_out <= offering);
+            prop_assert!(quote.amount_in >= offer.min_willing_to_receive_for(quote.amount_out)?);
+            prop_assert_eq!(quote.new_offering, offering - quote.amount_out);
+            prop_assert_eq!(quote.closes, max_amount_in >= accept_at_least);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Return data set by instructions for CPI callers and transaction simulation.
+//!
+//! Callers that CPI into simpledex can read the result with the `get()` fns,
+//! off-chain clients can decode the return data of a simulated transaction with
+//! [`DeserializePacked`](crate::packun::DeserializePacked).
+
+use std::io::Cursor;
+
+use solana_program::{
+    program::{get_return_data, set_return_data},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::{
+    error::SimpleDexError,
+    packun::{DeserializePacked, SerializePacked},
+    quote::{MatchQuote, Receipt, TakeQuote},
+};
+
+/// Set by CreateOffer
+#[derive(Clone, Copy, Debug, Default, PartialEq, DeserializePacked, SerializePacked)]
+pub struct CreateOfferReturnData {
+    pub offer: Pubkey,
+    pub bump: u8,
+}
+
+impl CreateOfferReturnData {
+    pub fn set(&self) -> Result<(), SimpleDexError> {
+        let mut data = [0; Self::PACKED_LEN];
+        let mut writer = Cursor::new(data.as_mut());
+        self.write_bytes(&mut writer)?;
+        set_return_data(&data);
+        Ok(())
+    }
+
+    /// Reads the return data of a just-invoked CreateOffer CPI
+    pub fn get() -> Result<Self, ProgramError> {
+        Self::get_with_program_id(&crate::id())
+    }
+
+    /// [`Self::get`] for a deployment at `program_id`
+    pub fn get_with_program_id(program_id: &Pubkey) -> Result<Self, ProgramError> {
+        let data = get_simpledex_return_data(program_id)?;
+        Ok(Self::read_bytes(&mut data.as_slice())?)
+    }
+}
+
+/// Set by MatchOffers
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub struct MatchOffersReturnData {
+    pub receipt: Receipt,
+    pub offering_a_new_offering: u64,
+    pub offering_a_new_accept_at_least: u64,
+    pub offering_b_new_offering: u64,
+    pub offering_b_new_accept_at_least: u64,
+}
+
+impl MatchOffersReturnData {
+    pub fn set(&self) -> Result<(), SimpleDexError> {
+        let mut data = [0; Self::PACKED_LEN];
+        let mut writer = Cursor::new(data.as_mut());
+        self.write_bytes(&mut writer)?;
+        set_return_data(&data);
+        Ok(())
+    }
+
+    /// Reads the return data of a just-invoked MatchOffers CPI
+    pub fn get() -> Result<Self, ProgramError> {
+        Self::get_with_program_id(&crate::id())
+    }
+
+    /// [`Self::get`] for a deployment at `program_id`
+    pub fn get_with_program_id(program_id: &Pubkey) -> Result<Self, ProgramError> {
+        let data = get_simpledex_return_data(program_id)?;
+        Ok(Self::read_bytes(&mut data.as_slice())?)
+    }
+}
+
+impl From<&MatchQuote> for MatchOffersReturnData {
+    fn from(quote: &MatchQuote) -> Self {
+        Self {
+            receipt: quote.receipt,
+            offering_a_new_offering: quote.offering_a_new_offering,
+            offering_a_new_accept_at_least: quote.offering_a_new_accept_at_least,
+            offering_b_new_offering: quote.offering_b_new_offering,
+            offering_b_new_accept_at_least: quote.offering_b_new_accept_at_least,
+        }
+    }
+}
+
+/// Set by TakeOffer
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
+pub struct TakeOfferReturnData {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub new_offering: u64,
+    pub new_accept_at_least: u64,
+}
+
+impl TakeOfferReturnData {
+    pub fn set(&self) -> Result<(), SimpleDexError> {
+        let mut data = [0; Self::PACKED_LEN];
+        let mut writer = Cursor::new(data.as_mut());
+        self.write_bytes(&mut writer)?;
+        set_return_data(&data);
+        Ok(())
+    }
+
+    /// Reads the return data of a just-invoked TakeOffer CPI
+    pub fn get() -> Result<Self, ProgramError> {
+        Self::get_with_program_id(&crate::id())
+    }
+
+    /// [`Self::get`] for a deployment at `program_id`
+    pub fn get_with_program_id(program_id: &Pubkey) -> Result<Self, ProgramError> {
+        let data = get_simpledex_return_data(program_id)?;
+        Ok(Self::read_bytes(&mut data.as_slice())?)
+    }
+}
+
+impl From<&TakeQuote> for TakeOfferReturnData {
+    fn from(quote: &TakeQuote) -> Self {
+        Self {
+            amount_in: quote.amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            new_offering: quote.new_offering,
+            new_accept_at_least: quote.new_accept_at_least,
+        }
+    }
+}
+
+fn get_simpledex_return_data(program_id: &Pubkey) -> Result<Vec<u8>, SimpleDexError> {
+    match get_return_data() {
+        Some((setter, data)) if &setter == program_id => Ok(data),
+        _ => Err(SimpleDexError::InvalidReturnData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::types::OfferSeq;
+
+    use super::*;
+
+    #[test]
+    fn test_return_data_packed_len() {
+        let mut data = [0; MatchOffersReturnData::PACKED_LEN];
+        let mut writer = Cursor::new(data.as_mut());
+        let match_offers = MatchOffersReturnData {
+            receipt: Receipt {
+                a_to_b: 1,
+                b_to_a: 2,
+                a_to_matcher: 3,
+                b_to_matcher: 4,
+                a_fee: 5,
+                b_fee: 6,
+                a_bonus: 7,
+                b_bonus: 8,
+                a_seq: OfferSeq::Neither,
+            },
+            offering_a_new_offering: 9,
+            offering_a_new_accept_at_least: 10,
+            offering_b_new_offering: 11,
+            offering_b_new_accept_at_least: 12,
+        };
+        match_offers.write_bytes(&mut writer).unwrap();
+        assert_eq!(
+            writer.position() as usize,
+            MatchOffersReturnData::PACKED_LEN
+        );
+        assert_eq!(
+            MatchOffersReturnData::read_bytes(&mut data.as_ref()).unwrap(),
+            match_offers
+        );
+
+        let mut data = [0; CreateOfferReturnData::PACKED_LEN];
+        let mut writer = Cursor::new(data.as_mut());
+        let create_offer = CreateOfferReturnData {
+            offer: Pubkey::new_unique(),
+            bump: 254,
+        };
+        create_offer.write_bytes(&mut writer).unwrap();
+        assert_eq!(
+            writer.position() as usize,
+            CreateOfferReturnData::PACKED_LEN
+        );
+        assert_eq!(
+            CreateOfferReturnData::read_bytes(&mut data.as_ref()).unwrap(),
+            create_offer
+        );
+    }
+}
@@ -15,7 +15,7 @@ use crate::{
     account::Account, checks::token_account_checked, error::SimpleDexError, fee::calc_fee,
 };
 
-use super::{Offer, OfferAccount};
+use super::{Offer, OfferFields};
 
 pub type HoldingAccount<'a, 'me> = Account<'a, 'me, TokenAccount>;
 
@@ -49,9 +49,9 @@ impl<'a, 'me> HoldingAccount<'a, 'me> {
         })
     }
 
-    pub fn load_checked(
+    pub fn load_checked<O: OfferFields>(
         holding_account: &'me AccountInfo<'a>,
-        offer_account: &OfferAccount,
+        offer_account: &Account<O>,
     ) -> Result<Self, ProgramError> {
         let data = token_account_checked(holding_account)?;
         let res = Self {
@@ -62,8 +62,9 @@ impl<'a, 'me> HoldingAccount<'a, 'me> {
         Ok(res)
     }
 
-    fn is_ata_of(&self, offer: &OfferAccount) -> Result<(), SimpleDexError> {
-        let expected = get_associated_token_address(offer.account_info.key, &offer.data.offer_mint);
+    fn is_ata_of<O: OfferFields>(&self, offer: &Account<O>) -> Result<(), SimpleDexError> {
+        let expected =
+            get_associated_token_address(offer.account_info.key, &offer.data.offer_mint());
         match expected == *self.account_info.key {
             true => Ok(()),
             false => Err(SimpleDexError::InvalidHoldingAccount),
@@ -98,9 +99,9 @@ impl<'a, 'me> HoldingAccount<'a, 'me> {
         )
     }
 
-    pub fn transfer(
+    pub fn transfer<O: OfferFields>(
         &self,
-        offer: &OfferAccount<'a, 'me>,
+        offer: &Account<'a, 'me, O>,
         to: &AccountInfo<'a>,
         amt: u64,
     ) -> Result<(), ProgramError> {
@@ -119,13 +120,19 @@ impl<'a, 'me> HoldingAccount<'a, 'me> {
                 to.to_owned(),
                 offer.account_info.to_owned(),
             ],
-            &[offer_pda_seeds!(offer.data)],
+            &[offer_pda_seeds!(
+                offer.data.owner(),
+                offer.data.offer_mint(),
+                offer.data.accept_mint(),
+                offer.data.seed(),
+                offer.data.bump(),
+            )],
         )
     }
 
-    pub fn close(
+    pub fn close<O: OfferFields>(
         self,
-        offer: &OfferAccount<'a, 'me>,
+        offer: &Account<'a, 'me, O>,
         refund_to: &AccountInfo<'a>,
         refund_rent_to: &AccountInfo<'a>,
     ) -> Result<(), ProgramError> {
@@ -144,7 +151,13 @@ impl<'a, 'me> HoldingAccount<'a, 'me> {
                 refund_rent_to.to_owned(),
                 offer.account_info.to_owned(),
             ],
-            &[offer_pda_seeds!(offer.data)],
+            &[offer_pda_seeds!(
+                offer.data.owner(),
+                offer.data.offer_mint(),
+                offer.data.accept_mint(),
+                offer.data.seed(),
+                offer.data.bump(),
+            )],
         )?;
         Ok(())
     }
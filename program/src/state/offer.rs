@@ -6,8 +6,7 @@ use std::io::Cursor;
 use solana_program::{
     account_info::AccountInfo,
     clock::{Clock, Slot},
-    entrypoint::ProgramResult,
-    program::{invoke, invoke_signed},
+    program::invoke,
     program_error::ProgramError,
     program_memory::sol_memset,
     program_pack::{IsInitialized, Pack, Sealed},
@@ -20,13 +19,47 @@ use solana_program::{
 use crate::{
     account::Account,
     error::SimpleDexError,
-    packun::{DeserializePacked, SerializePacked},
-    pda::try_create_offer_pda,
+    packun::{DeserializePacked, PackedLen, SerializePacked},
+    pda::try_create_offer_pda_with_program_id,
     types::{OfferSeq, Ratio},
 };
 
+use super::{create_pda_account, OfferView};
+
+/// Length of the free-form memo clients can attach to an offer
+pub const MEMO_LEN: usize = 16;
+
+/// First byte of every versioned offer account.
+/// Legacy offer accounts have no prefix and start with `slot` instead.
+pub const OFFER_DISCRIMINATOR: u8 = b'O';
+
+/// Layout version of an offer account, stored right after [`OFFER_DISCRIMINATOR`]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OfferVersion {
+    /// Unprefixed 221-byte layout, without `client_order_id` and `memo`
+    Legacy = 0,
+    /// Adds `client_order_id` and `memo`
+    V1 = 1,
+    /// Adds cumulative fill stats
+    V2 = 2,
+}
+
+impl OfferVersion {
+    pub const CURRENT: Self = Self::V2;
+
+    /// Length of offer accounts of this version
+    pub const fn packed_len(&self) -> usize {
+        match self {
+            Self::Legacy => Offer::LEGACY_LEN,
+            Self::V1 => 245,
+            Self::V2 => Offer::LEN,
+        }
+    }
+}
+
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, DeserializePacked, SerializePacked)]
 pub struct Offer {
     pub slot: Slot,
     pub offering: u64,
@@ -39,66 +72,245 @@ pub struct Offer {
     pub refund_to: Pubkey,
     pub credit_to: Pubkey,
     pub refund_rent_to: Pubkey,
+    pub client_order_id: u64,
+    pub memo: [u8; MEMO_LEN],
+    /// `offering` at creation
+    pub original_offering: u64,
+    /// `accept_at_least` at creation
+    pub original_accept_at_least: u64,
+    /// total `offer_mint` tokens taken out of `offering` by matches
+    pub filled_offer_amount: u64,
+    /// total `accept_mint` tokens credited to `credit_to` by matches
+    pub received_amount: u64,
+    /// number of matches this offer took part in
+    pub fill_count: u32,
+}
+
+/// Read access to an offer's fields, whether unpacked into an [`Offer`]
+/// or read in place from account data through an [`OfferView`]
+pub trait OfferFields {
+    fn slot(&self) -> Slot;
+    fn offering(&self) -> u64;
+    fn accept_at_least(&self) -> u64;
+    fn seed(&self) -> u16;
+    fn bump(&self) -> u8;
+    fn owner(&self) -> Pubkey;
+    fn offer_mint(&self) -> Pubkey;
+    fn accept_mint(&self) -> Pubkey;
+    fn refund_to(&self) -> Pubkey;
+    fn credit_to(&self) -> Pubkey;
+    fn refund_rent_to(&self) -> Pubkey;
+    fn client_order_id(&self) -> u64;
+    fn memo(&self) -> [u8; MEMO_LEN];
+    fn original_offering(&self) -> u64;
+    fn original_accept_at_least(&self) -> u64;
+    fn filled_offer_amount(&self) -> u64;
+    fn received_amount(&self) -> u64;
+    fn fill_count(&self) -> u32;
+
+    fn is_closed(&self) -> bool {
+        self.offering() == 0 || self.accept_at_least() == 0
+    }
+
+    fn relationship_with<O: OfferFields>(&self, other: &O) -> OfferSeq {
+        match self.slot().cmp(&other.slot()) {
+            Ordering::Equal => OfferSeq::Neither,
+            Ordering::Less => OfferSeq::Maker,
+            Ordering::Greater => OfferSeq::Taker,
+        }
+    }
+
+    fn min_willing_to_receive_for(&self, to_pay: u64) -> Result<u64, SimpleDexError> {
+        // shouldnt be greater than
+        if to_pay >= self.offering() {
+            return Ok(self.accept_at_least());
+        }
+        let proportion = Ratio::new(to_pay, self.offering())?;
+        proportion.apply_ceil(self.accept_at_least())
+    }
+
+    /// The limit price the offer was created with, `accept_at_least / offering`.
+    /// Falls back to the current values for offers without `original_*` fields set.
+    fn limit_ratio(&self) -> Result<Ratio, SimpleDexError> {
+        match self.original_offering() {
+            0 => Ratio::new(self.accept_at_least(), self.offering()),
+            _ => Ratio::new(self.original_accept_at_least(), self.original_offering()),
+        }
+    }
+
+    /// The new `offering` and `accept_at_least` after giving `amount_given`
+    fn matched_terms(&self, amount_given: u64) -> Result<(u64, u64), SimpleDexError> {
+        // derive from the original ratio instead of the current, already rounded, one
+        // so that rounding errors don't accumulate over partial fills
+        let accept_over_offer = self.limit_ratio()?;
+        let new_offering = self
+            .offering()
+            .checked_sub(amount_given)
+            .ok_or(SimpleDexError::NumericalError)?;
+        // round towards higher price, exactly: PreciseNumber's 1e-12 truncation
+        // would round down for original_offering > 1e12
+        let new_accept_at_least = accept_over_offer.apply_ceil_exact(new_offering)?;
+        Ok((new_offering, new_accept_at_least))
+    }
+}
+
+macro_rules! offer_field_getters {
+    ($($field: ident: $ty: ty),* $(,)?) => {
+        $(fn $field(&self) -> $ty {
+            self.$field
+        })*
+    };
+}
+
+impl OfferFields for Offer {
+    offer_field_getters!(
+        slot: Slot,
+        offering: u64,
+        accept_at_least: u64,
+        seed: u16,
+        bump: u8,
+        owner: Pubkey,
+        offer_mint: Pubkey,
+        accept_mint: Pubkey,
+        refund_to: Pubkey,
+        credit_to: Pubkey,
+        refund_rent_to: Pubkey,
+        client_order_id: u64,
+        memo: [u8; MEMO_LEN],
+        original_offering: u64,
+        original_accept_at_least: u64,
+        filled_offer_amount: u64,
+        received_amount: u64,
+        fill_count: u32,
+    );
 }
 
 impl Offer {
-    pub fn try_match(a: &Self, b: &Self) -> Result<(u64, u64), SimpleDexError> {
+    pub fn try_match<A: OfferFields, B: OfferFields>(
+        a: &A,
+        b: &B,
+    ) -> Result<(u64, u64), SimpleDexError> {
         if !Self::is_match(a, b) {
             return Err(SimpleDexError::OffersDontMatch);
         }
-        let a_can_fill_b = a.offering >= b.accept_at_least;
-        let b_can_fill_a = b.offering >= a.accept_at_least;
+        let a_can_fill_b = a.offering() >= b.accept_at_least();
+        let b_can_fill_a = b.offering() >= a.accept_at_least();
         let (amt_a_gives, amt_b_gives) = match (a_can_fill_b, b_can_fill_a) {
-            (true, true) => (a.offering, b.offering),
-            (true, false) => (b.accept_at_least, b.offering),
-            (false, true) => (a.offering, a.accept_at_least),
+            (true, true) => (a.offering(), b.offering()),
+            (true, false) => (b.accept_at_least(), b.offering()),
+            (false, true) => (a.offering(), a.accept_at_least()),
             (false, false) => return Err(SimpleDexError::OffersDontMatch),
         };
         Ok((amt_a_gives, amt_b_gives))
     }
 
-    fn is_match(a: &Self, b: &Self) -> bool {
+    fn is_match<A: OfferFields, B: OfferFields>(a: &A, b: &B) -> bool {
         // bid >= ask
         // (a.offering / a.accept_at_least) >= (b.accept_at_least / b.offering)
         // since all vals positive,
         // = a.offering * b.offering >= a.accept_at_least * b.accept_at_least
-        a.offering as u128 * b.offering as u128
-            >= a.accept_at_least as u128 * b.accept_at_least as u128
+        a.offering() as u128 * b.offering() as u128
+            >= a.accept_at_least() as u128 * b.accept_at_least() as u128
     }
 
-    pub fn is_closed(&self) -> bool {
-        self.offering == 0 || self.accept_at_least == 0
+    /// Length of [`OfferVersion::Legacy`] offer accounts
+    pub const LEGACY_LEN: usize = 221;
+
+    /// Unpacks an offer account of any known layout version.
+    /// Fields missing from older layouts are zeroed,
+    /// except the `original_*` fields, which are set to the current values.
+    pub fn unpack_any_version(src: &[u8]) -> Result<(Self, OfferVersion), ProgramError> {
+        let mut reader = src;
+        let version = match src.len() {
+            Self::LEGACY_LEN => OfferVersion::Legacy,
+            _ => Self::read_prefix(&mut reader)?,
+        };
+        if version == OfferVersion::CURRENT {
+            return Ok((Self::unpack(src)?, version));
+        }
+        if src.len() != version.packed_len() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let res = Self::read_fields(&mut reader, version)?;
+        if !res.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok((res, version))
     }
 
-    pub fn relationship_with(&self, other: &Self) -> OfferSeq {
-        match self.slot.cmp(&other.slot) {
-            Ordering::Equal => OfferSeq::Neither,
-            Ordering::Less => OfferSeq::Maker,
-            Ordering::Greater => OfferSeq::Taker,
+    fn read_prefix(reader: &mut &[u8]) -> Result<OfferVersion, ProgramError> {
+        match u8::read_bytes(reader)? {
+            OFFER_DISCRIMINATOR => (),
+            // zeroed out by close()
+            0 => return Err(ProgramError::UninitializedAccount),
+            _ => return Err(SimpleDexError::InvalidOfferAccount.into()),
+        }
+        match u8::read_bytes(reader)? {
+            1 => Ok(OfferVersion::V1),
+            2 => Ok(OfferVersion::V2),
+            _ => Err(SimpleDexError::InvalidOfferAccount.into()),
         }
     }
 
-    pub fn min_willing_to_receive_for(&self, to_pay: u64) -> Result<u64, SimpleDexError> {
-        // shouldnt be greater than
-        if to_pay >= self.offering {
-            return Ok(self.accept_at_least);
+    fn read_fields(reader: &mut &[u8], version: OfferVersion) -> Result<Self, ProgramError> {
+        let mut res = Self {
+            slot: Slot::read_bytes(reader)?,
+            offering: u64::read_bytes(reader)?,
+            accept_at_least: u64::read_bytes(reader)?,
+            seed: u16::read_bytes(reader)?,
+            bump: u8::read_bytes(reader)?,
+            owner: Pubkey::read_bytes(reader)?,
+            offer_mint: Pubkey::read_bytes(reader)?,
+            accept_mint: Pubkey::read_bytes(reader)?,
+            refund_to: Pubkey::read_bytes(reader)?,
+            credit_to: Pubkey::read_bytes(reader)?,
+            refund_rent_to: Pubkey::read_bytes(reader)?,
+            ..Self::default()
+        };
+        if version == OfferVersion::Legacy {
+            res.original_offering = res.offering;
+            res.original_accept_at_least = res.accept_at_least;
+            return Ok(res);
         }
-        let proportion = Ratio::new(to_pay, self.offering)?;
-        proportion.apply_ceil(self.accept_at_least)
+        res.client_order_id = u64::read_bytes(reader)?;
+        res.memo = <[u8; MEMO_LEN]>::read_bytes(reader)?;
+        if version == OfferVersion::V1 {
+            res.original_offering = res.offering;
+            res.original_accept_at_least = res.accept_at_least;
+            return Ok(res);
+        }
+        res.original_offering = u64::read_bytes(reader)?;
+        res.original_accept_at_least = u64::read_bytes(reader)?;
+        res.filled_offer_amount = u64::read_bytes(reader)?;
+        res.received_amount = u64::read_bytes(reader)?;
+        res.fill_count = u32::read_bytes(reader)?;
+        Ok(res)
     }
 
     pub fn update_offer_matched(mut self, amount_given: u64) -> Result<Self, SimpleDexError> {
-        let accept_over_offer = Ratio::new(self.accept_at_least, self.offering)?;
-        let new_offering = self
-            .offering
-            .checked_sub(amount_given)
-            .ok_or(SimpleDexError::NumericalError)?;
-        // round towards higher price
-        let new_accept_at_least = accept_over_offer.apply_ceil(new_offering)?;
+        let (new_offering, new_accept_at_least) = self.matched_terms(amount_given)?;
         self.offering = new_offering;
         self.accept_at_least = new_accept_at_least;
         Ok(self)
     }
+
+    /// Adds a match to this offer's cumulative fill stats
+    pub fn record_fill(
+        &mut self,
+        amount_given: u64,
+        amount_received: u64,
+    ) -> Result<(), SimpleDexError> {
+        self.filled_offer_amount = self
+            .filled_offer_amount
+            .checked_add(amount_given)
+            .ok_or(SimpleDexError::NumericalError)?;
+        self.received_amount = self
+            .received_amount
+            .checked_add(amount_received)
+            .ok_or(SimpleDexError::NumericalError)?;
+        self.fill_count = self.fill_count.saturating_add(1);
+        Ok(())
+    }
 }
 
 pub type OfferAccount<'a, 'me> = Account<'a, 'me, Offer>;
@@ -120,6 +332,9 @@ impl<'a, 'me> OfferAccount<'a, 'me> {
         refund_to: &Pubkey,
         credit_to: &Pubkey,
         refund_rent_to: &Pubkey,
+        client_order_id: u64,
+        memo: [u8; MEMO_LEN],
+        program_id: &Pubkey,
     ) -> Result<Self, ProgramError> {
         let clock = Clock::get()?;
         let res = Offer {
@@ -134,6 +349,11 @@ impl<'a, 'me> OfferAccount<'a, 'me> {
             refund_to: refund_to.to_owned(),
             credit_to: credit_to.to_owned(),
             refund_rent_to: refund_rent_to.to_owned(),
+            client_order_id,
+            memo,
+            original_offering: offering,
+            original_accept_at_least: accept_at_least,
+            ..Offer::default()
         };
         create_pda_account(
             Offer::LEN,
@@ -141,6 +361,7 @@ impl<'a, 'me> OfferAccount<'a, 'me> {
             system_program,
             new_offer_account,
             offer_pda_seeds!(res),
+            program_id,
         )?;
         Offer::pack(res, &mut new_offer_account.data.borrow_mut())?;
         Ok(Self {
@@ -149,18 +370,74 @@ impl<'a, 'me> OfferAccount<'a, 'me> {
         })
     }
 
-    pub fn load_checked(offer_account: &'me AccountInfo<'a>) -> Result<Self, ProgramError> {
-        let data = Offer::unpack(*offer_account.data.borrow())?;
-        let expected_pda = try_create_offer_pda(&data)?;
+    /// Loads an offer account, erroring if it uses an outdated layout
+    pub fn load_checked(
+        offer_account: &'me AccountInfo<'a>,
+        program_id: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        match Self::load_checked_any_version(offer_account, program_id)? {
+            (res, OfferVersion::CURRENT) => Ok(res),
+            _ => Err(SimpleDexError::LegacyOfferAccount.into()),
+        }
+    }
+
+    /// Loads an offer account of any layout version.
+    /// Only use this for instructions that don't save the offer back.
+    pub fn load_checked_any_version(
+        offer_account: &'me AccountInfo<'a>,
+        program_id: &Pubkey,
+    ) -> Result<(Self, OfferVersion), ProgramError> {
+        let (data, version) = Offer::unpack_any_version(*offer_account.data.borrow())?;
+        let expected_pda = try_create_offer_pda_with_program_id(&data, program_id)?;
         if &expected_pda != offer_account.key {
             return Err(SimpleDexError::IncorrectOfferAccount.into());
         }
-        Ok(Self {
-            account_info: offer_account,
-            data,
-        })
+        Ok((
+            Self {
+                account_info: offer_account,
+                data,
+            },
+            version,
+        ))
+    }
+
+    /// Reallocs an outdated offer account to the current layout and saves it,
+    /// `payer` funds the additional rent
+    pub fn migrate(
+        self,
+        payer: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+    ) -> Result<(), ProgramError> {
+        let required_lamports = Rent::get()?
+            .minimum_balance(Offer::LEN)
+            .saturating_sub(self.account_info.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, self.account_info.key, required_lamports),
+                &[
+                    payer.clone(),
+                    self.account_info.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+        self.account_info.realloc(Offer::LEN, false)?;
+        self.save()
+    }
+
+    pub fn save(self) -> Result<(), ProgramError> {
+        Offer::pack(self.data, &mut self.account_info.data.borrow_mut())
+    }
+
+    /// Like [`Self::save`], but only writes the fields a match changes
+    pub fn save_fill(self) -> Result<(), ProgramError> {
+        let mut data = self.account_info.data.borrow_mut();
+        OfferView::new(&mut **data)?.write_fill(&self.data);
+        Ok(())
     }
+}
 
+impl<'a, 'me, O: OfferFields> Account<'a, 'me, O> {
     pub fn close(self, refund_rent_to: &AccountInfo<'a>) -> Result<(), ProgramError> {
         let refund_rent_to_starting_lamports = refund_rent_to.lamports();
         **refund_rent_to.lamports.borrow_mut() = refund_rent_to_starting_lamports
@@ -176,66 +453,6 @@ impl<'a, 'me> OfferAccount<'a, 'me> {
         sol_memset(*data, 0, data_len);
         Ok(())
     }
-
-    pub fn save(self) -> Result<(), ProgramError> {
-        Offer::pack(self.data, &mut self.account_info.data.borrow_mut())
-    }
-}
-
-fn create_pda_account<'a>(
-    space: usize,
-    payer: &AccountInfo<'a>,
-    system_program: &AccountInfo<'a>,
-    new_pda_account: &AccountInfo<'a>,
-    new_pda_signer_seeds: &[&[u8]],
-) -> ProgramResult {
-    let owner = &crate::id();
-    let rent = Rent::get()?;
-    if new_pda_account.lamports() > 0 {
-        let required_lamports = rent
-            .minimum_balance(space)
-            .max(1)
-            .saturating_sub(new_pda_account.lamports());
-
-        if required_lamports > 0 {
-            invoke(
-                &system_instruction::transfer(payer.key, new_pda_account.key, required_lamports),
-                &[
-                    payer.clone(),
-                    new_pda_account.clone(),
-                    system_program.clone(),
-                ],
-            )?;
-        }
-
-        invoke_signed(
-            &system_instruction::allocate(new_pda_account.key, space as u64),
-            &[new_pda_account.clone(), system_program.clone()],
-            &[new_pda_signer_seeds],
-        )?;
-
-        invoke_signed(
-            &system_instruction::assign(new_pda_account.key, owner),
-            &[new_pda_account.clone(), system_program.clone()],
-            &[new_pda_signer_seeds],
-        )
-    } else {
-        invoke_signed(
-            &system_instruction::create_account(
-                payer.key,
-                new_pda_account.key,
-                rent.minimum_balance(space).max(1),
-                space as u64,
-                owner,
-            ),
-            &[
-                payer.clone(),
-                new_pda_account.clone(),
-                system_program.clone(),
-            ],
-            &[new_pda_signer_seeds],
-        )
-    }
 }
 
 impl IsInitialized for Offer {
@@ -246,51 +463,178 @@ impl IsInitialized for Offer {
 
 impl Sealed for Offer {}
 
-// TODO: this should be derived
 impl Pack for Offer {
-    const LEN: usize = 221;
+    // 2-byte discriminator + version prefix
+    const LEN: usize = 2 + <Self as PackedLen>::PACKED_LEN;
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
         // unwrap safety: length should be checked in pack() already,
         // cursor is just into a byte slice, should have no IO errors
         let mut writer = Cursor::new(dst);
-        self.slot.write_bytes(&mut writer).unwrap();
-        self.offering.write_bytes(&mut writer).unwrap();
-        self.accept_at_least.write_bytes(&mut writer).unwrap();
-        self.seed.write_bytes(&mut writer).unwrap();
-        self.bump.write_bytes(&mut writer).unwrap();
-        self.owner.write_bytes(&mut writer).unwrap();
-        self.offer_mint.write_bytes(&mut writer).unwrap();
-        self.accept_mint.write_bytes(&mut writer).unwrap();
-        self.refund_to.write_bytes(&mut writer).unwrap();
-        self.credit_to.write_bytes(&mut writer).unwrap();
-        self.refund_rent_to.write_bytes(&mut writer).unwrap();
+        OFFER_DISCRIMINATOR.write_bytes(&mut writer).unwrap();
+        (OfferVersion::CURRENT as u8)
+            .write_bytes(&mut writer)
+            .unwrap();
+        self.write_bytes(&mut writer).unwrap();
     }
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let mut reader = src;
-        Ok(Self {
-            slot: Slot::read_bytes(&mut reader)?,
-            offering: u64::read_bytes(&mut reader)?,
-            accept_at_least: u64::read_bytes(&mut reader)?,
-            seed: u16::read_bytes(&mut reader)?,
-            bump: u8::read_bytes(&mut reader)?,
-            owner: Pubkey::read_bytes(&mut reader)?,
-            offer_mint: Pubkey::read_bytes(&mut reader)?,
-            accept_mint: Pubkey::read_bytes(&mut reader)?,
-            refund_to: Pubkey::read_bytes(&mut reader)?,
-            credit_to: Pubkey::read_bytes(&mut reader)?,
-            refund_rent_to: Pubkey::read_bytes(&mut reader)?,
-        })
+        Ok(OfferView::new(src)?.to_offer())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
+    use crate::{
+        quote::{MatchQuote, TakeQuote},
+        state::Trader,
+    };
+
     use super::*;
 
     #[test]
     fn test_struct_packing() {
-        assert_eq!(224, std::mem::size_of::<Offer>());
+        assert_eq!(288, std::mem::size_of::<Offer>());
+        // on-chain layout must not change with the derive
+        assert_eq!(281, Offer::LEN);
+        assert_eq!(580, Trader::LEN);
+    }
+
+    #[test]
+    fn test_unpack_versions() {
+        let offer = Offer {
+            slot: 1,
+            offering: 2,
+            accept_at_least: 3,
+            client_order_id: 4,
+            memo: [5; MEMO_LEN],
+            ..Offer::default()
+        };
+        let mut data = [0; Offer::LEN];
+        Offer::pack(offer, &mut data).unwrap();
+        assert_eq!(data[0], OFFER_DISCRIMINATOR);
+        let (current, version) = Offer::unpack_any_version(&data).unwrap();
+        assert_eq!(version, OfferVersion::CURRENT);
+        assert_eq!(current.client_order_id, 4);
+
+        // legacy layout has no prefix and ends at refund_rent_to
+        let (legacy, version) = Offer::unpack_any_version(&data[2..2 + Offer::LEGACY_LEN]).unwrap();
+        assert_eq!(version, OfferVersion::Legacy);
+        assert_eq!(legacy.slot, 1);
+        assert_eq!(legacy.accept_at_least, 3);
+        assert_eq!(legacy.client_order_id, 0);
+
+        // v1 ends at memo, fill stats start from the current values
+        let mut v1 = data[..OfferVersion::V1.packed_len()].to_vec();
+        v1[1] = OfferVersion::V1 as u8;
+        let (v1, version) = Offer::unpack_any_version(&v1).unwrap();
+        assert_eq!(version, OfferVersion::V1);
+        assert_eq!(v1.memo, [5; MEMO_LEN]);
+        assert_eq!(v1.original_offering, 2);
+        assert_eq!(v1.original_accept_at_least, 3);
+        assert_eq!(v1.fill_count, 0);
+
+        assert!(Offer::unpack_any_version(&[0; Offer::LEN]).is_err());
+        assert!(Offer::unpack_any_version(&[0; Offer::LEGACY_LEN]).is_err());
+    }
+
+    #[test]
+    fn test_matched_terms_large_original_offering() {
+        // 1 accept atomic for 1e13 offer atomics: what's left after a fill
+        // is worth less than 1e-12 accept atomics but still more than nothing
+        let offer = Offer {
+            offering: 10_000_000_000_000,
+            accept_at_least: 1,
+            original_offering: 10_000_000_000_000,
+            original_accept_at_least: 1,
+            ..Offer::default()
+        };
+        assert_eq!(offer.matched_terms(9_999_999_999_999).unwrap(), (1, 1));
+        assert_eq!(
+            offer.matched_terms(5_000_000_000_000).unwrap(),
+            (5_000_000_000_000, 1)
+        );
+        assert_eq!(offer.matched_terms(10_000_000_000_000).unwrap(), (0, 0));
+    }
+
+    proptest! {
+        #[test]
+        fn test_partial_fills_dont_drift(
+            original_offering in 1..=u64::MAX,
+            original_accept_at_least in 1..=u64::MAX,
+            fills in prop::collection::vec(1..=u64::MAX, 50..200),
+        ) {
+            let mut offer = Offer {
+                slot: 1,
+                offering: original_offering,
+                accept_at_least: original_accept_at_least,
+                original_offering,
+                original_accept_at_least,
+                ..Offer::default()
+            };
+            // cumulative amounts the offer gave and received
+            let (mut given, mut received) = (0u128, 0u128);
+            for fill in fills {
+                if offer.is_closed() {
+                    break;
+                }
+                // at most ~1/8 of what's left so sequences stay long
+                let (amount_given, amount_received, new_offering, new_accept_at_least) =
+                    match fill % 2 {
+                        0 => {
+                            let max_amount_in = fill % (offer.accept_at_least / 8 + 1) + 1;
+                            let quote = TakeQuote::calc(&offer, max_amount_in)?;
+                            (
+                                quote.amount_out,
+                                quote.amount_in,
+                                quote.new_offering,
+                                quote.new_accept_at_least,
+                            )
+                        }
+                        // by a later offer paying up to 2 more than the limit price
+                        _ => {
+                            let amount = fill % (offer.offering / 8 + 1) + 1;
+                            let taker = Offer {
+                                slot: 2,
+                                offering: offer
+                                    .min_willing_to_receive_for(amount)?
+                                    .saturating_add(fill % 3),
+                                accept_at_least: amount,
+                                ..Offer::default()
+                            };
+                            let quote = MatchQuote::calc(&taker, &offer)?;
+                            (
+                                quote.amt_b_gives,
+                                quote.receipt.a_to_b,
+                                quote.offering_b_new_offering,
+                                quote.offering_b_new_accept_at_least,
+                            )
+                        }
+                    };
+                offer.offering = new_offering;
+                offer.accept_at_least = new_accept_at_least;
+                given += u128::from(amount_given);
+                received += u128::from(amount_received);
+
+                // exact remaining = original_accept_at_least * offering / original_offering,
+                // accept_at_least must be within one atomic unit above it
+                let exact_numer = original_accept_at_least as u128 * offer.offering as u128;
+                let rounded_numer = offer.accept_at_least as u128 * original_offering as u128;
+                prop_assert!(rounded_numer >= exact_numer);
+                prop_assert!(rounded_numer - exact_numer < original_offering as u128);
+                // everything given so far was paid for at the original limit price,
+                // less at most one atomic unit
+                let owed_numer = original_accept_at_least as u128 * given;
+                let paid_for = match received.checked_mul(original_offering as u128) {
+                    Some(received_numer) => {
+                        received_numer.saturating_add(original_offering as u128) >= owed_numer
+                    }
+                    None => true,
+                };
+                prop_assert!(paid_for);
+            }
+        }
     }
 }
@@ -0,0 +1,308 @@
+//! Zero-copy access to current-version offer account data.
+//!
+//! Fields are read and written in place at fixed, unaligned offsets,
+//! so instructions only touch the bytes they need instead of
+//! (de)serializing the whole [`Offer`].
+
+use core::convert::TryInto;
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Slot,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+
+use crate::{account::Account, error::SimpleDexError};
+
+use super::{Offer, OfferFields, OfferVersion, MEMO_LEN, OFFER_DISCRIMINATOR};
+
+/// Byte offsets of each field in the current layout,
+/// e.g. for `getProgramAccounts` memcmp filters
+pub mod offer_offsets {
+    pub const DISCRIMINATOR: usize = 0;
+    pub const VERSION: usize = 1;
+    pub const SLOT: usize = 2;
+    pub const OFFERING: usize = 10;
+    pub const ACCEPT_AT_LEAST: usize = 18;
+    pub const SEED: usize = 26;
+    pub const BUMP: usize = 28;
+    pub const OWNER: usize = 29;
+    pub const OFFER_MINT: usize = 61;
+    pub const ACCEPT_MINT: usize = 93;
+    pub const REFUND_TO: usize = 125;
+    pub const CREDIT_TO: usize = 157;
+    pub const REFUND_RENT_TO: usize = 189;
+    pub const CLIENT_ORDER_ID: usize = 221;
+    pub const MEMO: usize = 229;
+    pub const ORIGINAL_OFFERING: usize = 245;
+    pub const ORIGINAL_ACCEPT_AT_LEAST: usize = 253;
+    pub const FILLED_OFFER_AMOUNT: usize = 261;
+    pub const RECEIVED_AMOUNT: usize = 269;
+    pub const FILL_COUNT: usize = 277;
+}
+
+use offer_offsets::*;
+
+/// Bytes an [`OfferView`] reads its fields from
+pub trait OfferBytes {
+    fn bytes_at<const N: usize>(&self, offset: usize) -> [u8; N];
+}
+
+/// Bytes an [`OfferView`] writes its fields to
+pub trait OfferBytesMut: OfferBytes {
+    fn set_bytes_at(&mut self, offset: usize, bytes: &[u8]);
+}
+
+impl<D: AsRef<[u8]>> OfferBytes for D {
+    fn bytes_at<const N: usize>(&self, offset: usize) -> [u8; N] {
+        // unwrap safety: OfferView::new() checked data.len() == Offer::LEN
+        self.as_ref()[offset..offset + N].try_into().unwrap()
+    }
+}
+
+impl<D: AsRef<[u8]> + AsMut<[u8]>> OfferBytesMut for D {
+    fn set_bytes_at(&mut self, offset: usize, bytes: &[u8]) {
+        self.as_mut()[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+/// An account's data, borrowed for each field read or written
+/// so no borrow is held across CPIs
+pub struct AccountData<'a, 'me>(&'me AccountInfo<'a>);
+
+impl OfferBytes for AccountData<'_, '_> {
+    fn bytes_at<const N: usize>(&self, offset: usize) -> [u8; N] {
+        let data = self.0.data.borrow();
+        // unwrap safety: OfferViewAccount::load_checked() checked data.len() == Offer::LEN
+        data[offset..offset + N].try_into().unwrap()
+    }
+}
+
+impl OfferBytesMut for AccountData<'_, '_> {
+    fn set_bytes_at(&mut self, offset: usize, bytes: &[u8]) {
+        self.0.data.borrow_mut()[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+macro_rules! le_getter {
+    ($field: ident, $ty: ty, $offset: expr) => {
+        fn $field(&self) -> $ty {
+            <$ty>::from_le_bytes(self.data.bytes_at($offset))
+        }
+    };
+}
+
+macro_rules! pubkey_getter {
+    ($field: ident, $offset: expr) => {
+        fn $field(&self) -> Pubkey {
+            Pubkey::new_from_array(self.data.bytes_at::<PUBKEY_BYTES>($offset))
+        }
+    };
+}
+
+macro_rules! le_setter {
+    ($setter: ident, $ty: ty, $offset: expr) => {
+        pub fn $setter(&mut self, val: $ty) {
+            self.data.set_bytes_at($offset, &val.to_le_bytes());
+        }
+    };
+}
+
+/// View over the data of an offer account in the current layout
+pub struct OfferView<D> {
+    data: D,
+}
+
+impl<D: AsRef<[u8]>> OfferView<D> {
+    pub fn new(data: D) -> Result<Self, ProgramError> {
+        let src = data.as_ref();
+        // the legacy layout has no prefix to read the version from
+        if src.len() == Offer::LEGACY_LEN {
+            return Err(SimpleDexError::LegacyOfferAccount.into());
+        }
+        if src.len() != Offer::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        match src[DISCRIMINATOR] {
+            OFFER_DISCRIMINATOR => (),
+            // zeroed out by close()
+            0 => return Err(ProgramError::UninitializedAccount),
+            _ => return Err(SimpleDexError::InvalidOfferAccount.into()),
+        }
+        if src[VERSION] != OfferVersion::CURRENT as u8 {
+            return Err(SimpleDexError::LegacyOfferAccount.into());
+        }
+        Ok(Self { data })
+    }
+}
+
+impl<D: OfferBytes> OfferView<D> {
+    pub fn to_offer(&self) -> Offer {
+        Offer {
+            slot: self.slot(),
+            offering: self.offering(),
+            accept_at_least: self.accept_at_least(),
+            seed: self.seed(),
+            bump: self.bump(),
+            owner: self.owner(),
+            offer_mint: self.offer_mint(),
+            accept_mint: self.accept_mint(),
+            refund_to: self.refund_to(),
+            credit_to: self.credit_to(),
+            refund_rent_to: self.refund_rent_to(),
+            client_order_id: self.client_order_id(),
+            memo: self.memo(),
+            original_offering: self.original_offering(),
+            original_accept_at_least: self.original_accept_at_least(),
+            filled_offer_amount: self.filled_offer_amount(),
+            received_amount: self.received_amount(),
+            fill_count: self.fill_count(),
+        }
+    }
+}
+
+impl<D: OfferBytes> OfferFields for OfferView<D> {
+    le_getter!(slot, Slot, SLOT);
+    le_getter!(offering, u64, OFFERING);
+    le_getter!(accept_at_least, u64, ACCEPT_AT_LEAST);
+    le_getter!(seed, u16, SEED);
+    le_getter!(bump, u8, BUMP);
+    pubkey_getter!(owner, OWNER);
+    pubkey_getter!(offer_mint, OFFER_MINT);
+    pubkey_getter!(accept_mint, ACCEPT_MINT);
+    pubkey_getter!(refund_to, REFUND_TO);
+    pubkey_getter!(credit_to, CREDIT_TO);
+    pubkey_getter!(refund_rent_to, REFUND_RENT_TO);
+    le_getter!(client_order_id, u64, CLIENT_ORDER_ID);
+    le_getter!(original_offering, u64, ORIGINAL_OFFERING);
+    le_getter!(original_accept_at_least, u64, ORIGINAL_ACCEPT_AT_LEAST);
+    le_getter!(filled_offer_amount, u64, FILLED_OFFER_AMOUNT);
+    le_getter!(received_amount, u64, RECEIVED_AMOUNT);
+    le_getter!(fill_count, u32, FILL_COUNT);
+
+    fn memo(&self) -> [u8; MEMO_LEN] {
+        self.data.bytes_at(MEMO)
+    }
+}
+
+/// Only the fields a match changes are writable
+impl<D: OfferBytesMut> OfferView<D> {
+    le_setter!(set_offering, u64, OFFERING);
+    le_setter!(set_accept_at_least, u64, ACCEPT_AT_LEAST);
+    le_setter!(set_filled_offer_amount, u64, FILLED_OFFER_AMOUNT);
+    le_setter!(set_received_amount, u64, RECEIVED_AMOUNT);
+    le_setter!(set_fill_count, u32, FILL_COUNT);
+
+    /// Writes `offer`'s fill state: `offering`, `accept_at_least` and fill stats
+    pub fn write_fill<O: OfferFields>(&mut self, offer: &O) {
+        self.set_offering(offer.offering());
+        self.set_accept_at_least(offer.accept_at_least());
+        self.set_filled_offer_amount(offer.filled_offer_amount());
+        self.set_received_amount(offer.received_amount());
+        self.set_fill_count(offer.fill_count());
+    }
+
+    /// Adds a match to the offer's cumulative fill stats, like [`Offer::record_fill`]
+    pub fn record_fill(
+        &mut self,
+        amount_given: u64,
+        amount_received: u64,
+    ) -> Result<(), SimpleDexError> {
+        let filled_offer_amount = self
+            .filled_offer_amount()
+            .checked_add(amount_given)
+            .ok_or(SimpleDexError::NumericalError)?;
+        let received_amount = self
+            .received_amount()
+            .checked_add(amount_received)
+            .ok_or(SimpleDexError::NumericalError)?;
+        self.set_filled_offer_amount(filled_offer_amount);
+        self.set_received_amount(received_amount);
+        self.set_fill_count(self.fill_count().saturating_add(1));
+        Ok(())
+    }
+}
+
+/// An offer account whose fields are read and written in place through [`OfferView`],
+/// without deserializing it into an [`Offer`]
+pub type OfferViewAccount<'a, 'me> = Account<'a, 'me, OfferView<AccountData<'a, 'me>>>;
+
+impl<'a, 'me> OfferViewAccount<'a, 'me> {
+    /// Loads a current-version offer account, erroring with `LegacyOfferAccount` otherwise
+    pub fn load_checked(
+        offer_account: &'me AccountInfo<'a>,
+        program_id: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        OfferView::new(&**offer_account.data.borrow())?;
+        let view = OfferView {
+            data: AccountData(offer_account),
+        };
+        let expected_pda = Pubkey::create_program_address(
+            offer_pda_seeds!(
+                view.owner(),
+                view.offer_mint(),
+                view.accept_mint(),
+                view.seed(),
+                view.bump(),
+            ),
+            program_id,
+        )?;
+        if &expected_pda != offer_account.key {
+            return Err(SimpleDexError::IncorrectOfferAccount.into());
+        }
+        Ok(Self {
+            account_info: offer_account,
+            data: view,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_matches_pack() {
+        let offer = Offer {
+            slot: 1,
+            offering: 2,
+            accept_at_least: 3,
+            seed: 4,
+            bump: 5,
+            owner: Pubkey::new_unique(),
+            offer_mint: Pubkey::new_unique(),
+            accept_mint: Pubkey::new_unique(),
+            refund_to: Pubkey::new_unique(),
+            credit_to: Pubkey::new_unique(),
+            refund_rent_to: Pubkey::new_unique(),
+            client_order_id: 6,
+            memo: [7; MEMO_LEN],
+            original_offering: 8,
+            original_accept_at_least: 9,
+            filled_offer_amount: 10,
+            received_amount: 11,
+            fill_count: 12,
+        };
+        let mut data = [0; Offer::LEN];
+        Offer::pack(offer, &mut data).unwrap();
+        assert_eq!(OfferView::new(&data[..]).unwrap().to_offer(), offer);
+
+        let filled = Offer {
+            offering: 1,
+            accept_at_least: 2,
+            filled_offer_amount: 11,
+            received_amount: 12,
+            fill_count: 13,
+            ..offer
+        };
+        OfferView::new(&mut data[..]).unwrap().write_fill(&filled);
+        assert_eq!(Offer::unpack(&data).unwrap(), filled);
+
+        assert_eq!(
+            OfferView::new(&data[2..2 + Offer::LEGACY_LEN]).err(),
+            Some(SimpleDexError::LegacyOfferAccount.into())
+        );
+    }
+}
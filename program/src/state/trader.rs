@@ -0,0 +1,326 @@
+//! Optional per-owner bookkeeping account
+
+use std::io::{Cursor, Read, Write};
+
+use solana_program::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+};
+
+use crate::{
+    account::Account,
+    error::SimpleDexError,
+    packun::{DeserializePacked, Layout, PackedLayout, PackedLen, SerializePacked},
+    pda::try_create_trader_pda_with_program_id,
+};
+
+use super::create_pda_account;
+
+/// First byte of every trader account
+pub const TRADER_DISCRIMINATOR: u8 = b'T';
+
+/// Number of pairs a trader account can track open offers in at once
+pub const TRADER_PAIRS: usize = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, DeserializePacked, SerializePacked)]
+pub struct Trader {
+    pub owner: Pubkey,
+    pub bump: u8,
+    /// Seed the owner's next offer should use.
+    /// Seeds at or after this one have not been used by an offer created with this account supplied.
+    pub next_seed: u16,
+    /// Open offers per pair. Entries without open offers are free for other pairs.
+    pub pairs: [TraderPair; TRADER_PAIRS],
+}
+
+/// Open offers the owner has in one direction of a pair
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, DeserializePacked, SerializePacked)]
+pub struct TraderPair {
+    pub offer_mint: Pubkey,
+    pub accept_mint: Pubkey,
+    /// Offers created minus offers canceled with the trader account supplied.
+    /// Offers closed by being completely filled are not subtracted.
+    pub open_offers: u32,
+}
+
+impl Trader {
+    pub fn on_offer_created(
+        &mut self,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+        seed: u16,
+    ) -> Result<(), SimpleDexError> {
+        // offers created out of order shouldn't move next_seed backwards
+        if seed >= self.next_seed {
+            // wrapping around would hand out seeds that may still be in use
+            self.next_seed = seed
+                .checked_add(1)
+                .ok_or(SimpleDexError::TraderSeedsExhausted)?;
+        }
+        let pair = self.pair_or_free_mut(offer_mint, accept_mint)?;
+        pair.open_offers = pair
+            .open_offers
+            .checked_add(1)
+            .ok_or(SimpleDexError::NumericalError)?;
+        Ok(())
+    }
+
+    pub fn on_offer_canceled(&mut self, offer_mint: &Pubkey, accept_mint: &Pubkey) {
+        // offers created without this account supplied were never counted
+        if let Some(pair) = self.pair_mut(offer_mint, accept_mint) {
+            pair.open_offers = pair.open_offers.saturating_sub(1);
+        }
+    }
+
+    /// Sets the pair's `open_offers`, e.g. to subtract offers closed by being completely filled
+    pub fn sync_pair(
+        &mut self,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+        open_offers: u32,
+    ) -> Result<(), SimpleDexError> {
+        if open_offers > 0 {
+            self.pair_or_free_mut(offer_mint, accept_mint)?.open_offers = open_offers;
+        } else if let Some(pair) = self.pair_mut(offer_mint, accept_mint) {
+            // frees the entry
+            pair.open_offers = 0;
+        }
+        Ok(())
+    }
+
+    /// Open offers in the `offer_mint` -> `accept_mint` direction
+    pub fn open_offers(&self, offer_mint: &Pubkey, accept_mint: &Pubkey) -> u32 {
+        self.pairs
+            .iter()
+            .find(|p| p.open_offers > 0 && p.is(offer_mint, accept_mint))
+            .map_or(0, |p| p.open_offers)
+    }
+
+    fn pair_mut(&mut self, offer_mint: &Pubkey, accept_mint: &Pubkey) -> Option<&mut TraderPair> {
+        self.pairs
+            .iter_mut()
+            .find(|p| p.open_offers > 0 && p.is(offer_mint, accept_mint))
+    }
+
+    /// The pair's entry, claiming a free one if it has no open offers
+    fn pair_or_free_mut(
+        &mut self,
+        offer_mint: &Pubkey,
+        accept_mint: &Pubkey,
+    ) -> Result<&mut TraderPair, SimpleDexError> {
+        let i = match self
+            .pairs
+            .iter()
+            .position(|p| p.open_offers > 0 && p.is(offer_mint, accept_mint))
+        {
+            Some(i) => i,
+            None => {
+                let i = self
+                    .pairs
+                    .iter()
+                    .position(|p| p.open_offers == 0)
+                    .ok_or(SimpleDexError::TraderPairsFull)?;
+                self.pairs[i] = TraderPair {
+                    offer_mint: *offer_mint,
+                    accept_mint: *accept_mint,
+                    open_offers: 0,
+                };
+                i
+            }
+        };
+        Ok(&mut self.pairs[i])
+    }
+}
+
+impl TraderPair {
+    fn is(&self, offer_mint: &Pubkey, accept_mint: &Pubkey) -> bool {
+        &self.offer_mint == offer_mint && &self.accept_mint == accept_mint
+    }
+}
+
+impl PackedLen for [TraderPair; TRADER_PAIRS] {
+    const PACKED_LEN: usize = TRADER_PAIRS * TraderPair::PACKED_LEN;
+}
+
+impl PackedLayout for [TraderPair; TRADER_PAIRS] {
+    fn layout() -> Layout {
+        Layout::DefinedArray("TraderPair", TRADER_PAIRS)
+    }
+}
+
+impl<R: Read> DeserializePacked<R, SimpleDexError> for [TraderPair; TRADER_PAIRS] {
+    fn read_bytes(buf: &mut R) -> Result<Self, SimpleDexError> {
+        let mut res = [TraderPair::default(); TRADER_PAIRS];
+        for pair in res.iter_mut() {
+            *pair = TraderPair::read_bytes(buf)?;
+        }
+        Ok(res)
+    }
+}
+
+impl<W: Write> SerializePacked<W, SimpleDexError> for [TraderPair; TRADER_PAIRS] {
+    fn write_bytes(&self, buf: &mut W) -> Result<(), SimpleDexError> {
+        self.iter().try_for_each(|pair| pair.write_bytes(buf))
+    }
+}
+
+pub type TraderAccount<'a, 'me> = Account<'a, 'me, Trader>;
+
+impl<'a, 'me> TraderAccount<'a, 'me> {
+    /// Create and save trader account to storage
+    pub fn create_to(
+        new_trader_account: &'me AccountInfo<'a>,
+        payer: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        owner: &Pubkey,
+        bump: u8,
+        program_id: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let res = Trader {
+            owner: *owner,
+            bump,
+            ..Trader::default()
+        };
+        create_pda_account(
+            Trader::LEN,
+            payer,
+            system_program,
+            new_trader_account,
+            trader_pda_seeds!(res.owner, res.bump),
+            program_id,
+        )?;
+        Trader::pack(res, &mut new_trader_account.data.borrow_mut())?;
+        Ok(Self {
+            account_info: new_trader_account,
+            data: res,
+        })
+    }
+
+    /// Loads `owner`'s trader account
+    pub fn load_checked(
+        trader_account: &'me AccountInfo<'a>,
+        owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        let data = Trader::unpack(*trader_account.data.borrow())?;
+        if &data.owner != owner
+            || &try_create_trader_pda_with_program_id(&data, program_id)? != trader_account.key
+        {
+            return Err(SimpleDexError::IncorrectTraderAccount.into());
+        }
+        Ok(Self {
+            account_info: trader_account,
+            data,
+        })
+    }
+
+    pub fn save(self) -> Result<(), ProgramError> {
+        Trader::pack(self.data, &mut self.account_info.data.borrow_mut())
+    }
+}
+
+impl IsInitialized for Trader {
+    fn is_initialized(&self) -> bool {
+        // unpack_from_slice() already checked the discriminator
+        true
+    }
+}
+
+impl Sealed for Trader {}
+
+impl Pack for Trader {
+    // 1-byte discriminator prefix
+    const LEN: usize = 1 + <Self as PackedLen>::PACKED_LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        // unwrap safety: length should be checked in pack() already,
+        // cursor is just into a byte slice, should have no IO errors
+        let mut writer = Cursor::new(dst);
+        TRADER_DISCRIMINATOR.write_bytes(&mut writer).unwrap();
+        self.write_bytes(&mut writer).unwrap();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let mut reader = src;
+        match u8::read_bytes(&mut reader)? {
+            TRADER_DISCRIMINATOR => (),
+            0 => return Err(ProgramError::UninitializedAccount),
+            _ => return Err(SimpleDexError::IncorrectTraderAccount.into()),
+        }
+        Ok(Self::read_bytes(&mut reader)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_seed() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut trader = Trader::default();
+        trader.on_offer_created(&a, &b, 0).unwrap();
+        assert_eq!(trader.next_seed, 1);
+        // landed out of order
+        trader.on_offer_created(&a, &b, 2).unwrap();
+        trader.on_offer_created(&a, &b, 1).unwrap();
+        assert_eq!(trader.next_seed, 3);
+        assert_eq!(
+            trader.on_offer_created(&a, &b, u16::MAX),
+            Err(SimpleDexError::TraderSeedsExhausted)
+        );
+    }
+
+    #[test]
+    fn test_open_offers() {
+        let mints: Vec<Pubkey> = (0..=TRADER_PAIRS).map(|_| Pubkey::new_unique()).collect();
+        let mut trader = Trader::default();
+        trader.on_offer_created(&mints[0], &mints[1], 0).unwrap();
+        trader.on_offer_created(&mints[0], &mints[1], 1).unwrap();
+        trader.on_offer_created(&mints[1], &mints[0], 2).unwrap();
+        assert_eq!(trader.open_offers(&mints[0], &mints[1]), 2);
+        assert_eq!(trader.open_offers(&mints[1], &mints[0]), 1);
+
+        trader.on_offer_canceled(&mints[0], &mints[1]);
+        assert_eq!(trader.open_offers(&mints[0], &mints[1]), 1);
+        // never counted
+        trader.on_offer_canceled(&mints[2], &mints[1]);
+        assert_eq!(trader.open_offers(&mints[2], &mints[1]), 0);
+
+        for (i, mint) in mints[2..TRADER_PAIRS].iter().enumerate() {
+            trader
+                .on_offer_created(mint, &mints[0], 3 + i as u16)
+                .unwrap();
+        }
+        assert_eq!(
+            trader.on_offer_created(&mints[TRADER_PAIRS], &mints[0], 100),
+            Err(SimpleDexError::TraderPairsFull)
+        );
+        // canceling a pair's last open offer frees its entry
+        trader.on_offer_canceled(&mints[1], &mints[0]);
+        trader
+            .on_offer_created(&mints[TRADER_PAIRS], &mints[0], 100)
+            .unwrap();
+        assert_eq!(trader.open_offers(&mints[TRADER_PAIRS], &mints[0]), 1);
+        assert_eq!(trader.open_offers(&mints[1], &mints[0]), 0);
+
+        // offers closed by fills are only subtracted by syncing
+        trader.sync_pair(&mints[0], &mints[1], 0).unwrap();
+        assert_eq!(trader.open_offers(&mints[0], &mints[1]), 0);
+        trader.sync_pair(&mints[1], &mints[0], 2).unwrap();
+        assert_eq!(trader.open_offers(&mints[1], &mints[0]), 2);
+        assert_eq!(
+            trader.sync_pair(&mints[0], &mints[1], 1),
+            Err(SimpleDexError::TraderPairsFull)
+        );
+        trader.sync_pair(&mints[0], &mints[1], 0).unwrap();
+
+        let mut data = [0; Trader::LEN];
+        Trader::pack(trader, &mut data).unwrap();
+        assert_eq!(Trader::unpack(&data).unwrap(), trader);
+    }
+}
@@ -1,19 +1,20 @@
 //! Custom types
 
 use core::convert::TryInto;
-use spl_math::precise_number::PreciseNumber;
 
-use crate::error::SimpleDexError;
+use crate::{
+    error::SimpleDexError,
+    packun::{DeserializePacked, SerializePacked},
+};
 
-fn try_into_precise_number(n: u64) -> Result<PreciseNumber, SimpleDexError> {
-    PreciseNumber::new(n as u128).ok_or(SimpleDexError::NumericalError)
-}
-
-fn try_from_precise_number(p: PreciseNumber) -> Result<u64, SimpleDexError> {
-    let expanded = p.to_imprecise().ok_or(SimpleDexError::NumericalError)?;
-    Ok(expanded.try_into()?)
-}
+/// Fixed point precision of the spl-math PreciseNumber `Ratio` used to be computed with, 12 decimals
+const PRECISION: u128 = 1_000_000_000_000;
 
+/// `num / denom`, applied to token amounts.
+///
+/// `apply_floor` and `apply_ceil` round bit-for-bit like the PreciseNumber implementation
+/// they replaced, since fees and limit prices of existing offers depend on them.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Ratio {
     num: u64,
     denom: u64,
@@ -31,31 +32,215 @@ impl Ratio {
         Self { num, denom }
     }
 
+    /// num * token_amt / denom to 12 decimals, rounded half up.
+    /// Despite the name, this is what PreciseNumber's `to_imprecise()` did.
     pub fn apply_floor(&self, token_amt: u64) -> Result<u64, SimpleDexError> {
-        try_from_precise_number(self.apply(token_amt)?)
+        let (quot, frac) = self.apply(token_amt)?;
+        // overflow safety: quot <= (u64::MAX)^2 / 1 < u128::MAX
+        let res = match frac >= PRECISION / 2 {
+            true => quot + 1,
+            false => quot,
+        };
+        Ok(res.try_into()?)
     }
 
+    /// ceil(num * token_amt / denom to 12 decimals).
+    /// Fractional parts below 1e-12 are lost, like PreciseNumber's `ceiling()`.
     pub fn apply_ceil(&self, token_amt: u64) -> Result<u64, SimpleDexError> {
-        let res = self
-            .apply(token_amt)?
-            .ceiling()
-            .ok_or(SimpleDexError::NumericalError)?;
-        try_from_precise_number(res)
-    }
-
-    fn apply(&self, token_amt: u64) -> Result<PreciseNumber, SimpleDexError> {
-        let num_precise = try_into_precise_number(self.num)?;
-        let denom_precise = try_into_precise_number(self.denom)?;
-        let token_amt_precise = try_into_precise_number(token_amt)?;
-        num_precise
-            .checked_mul(&token_amt_precise)
-            .and_then(|u| u.checked_div(&denom_precise))
-            .ok_or(SimpleDexError::NumericalError)
+        let (quot, frac) = self.apply(token_amt)?;
+        let res = match frac {
+            0 => quot,
+            _ => quot + 1,
+        };
+        Ok(res.try_into()?)
+    }
+
+    /// Exact floor(num * token_amt / denom), for amounts that must never round up
+    pub fn apply_floor_exact(&self, token_amt: u64) -> Result<u64, SimpleDexError> {
+        let (quot, _rem) = self.div_rem(token_amt)?;
+        Ok(quot.try_into()?)
+    }
+
+    /// Exact ceil(num * token_amt / denom), for limits that must never round down
+    pub fn apply_ceil_exact(&self, token_amt: u64) -> Result<u64, SimpleDexError> {
+        let res = match self.div_rem(token_amt)? {
+            (quot, 0) => quot,
+            (quot, _rem) => quot + 1,
+        };
+        Ok(res.try_into()?)
+    }
+
+    /// Integer part of num * token_amt / denom and its fractional part to 12 decimals,
+    /// `floor(frac * 1e12)`.
+    ///
+    /// PreciseNumber computes `(num * 1e12) * (token_amt * 1e12) / 1e12`, exact,
+    /// then `(product * 1e12 + 1e12 / 2) / (denom * 1e12)`, which is always
+    /// `floor(num * token_amt * 1e12 / denom)` since the numerator is a multiple of 1e12.
+    fn apply(&self, token_amt: u64) -> Result<(u128, u128), SimpleDexError> {
+        let (quot, rem) = self.div_rem(token_amt)?;
+        // overflow safety: rem < denom <= u64::MAX, so rem * 1e12 < 2^104
+        let frac = rem * PRECISION / self.denom as u128;
+        Ok((quot, frac))
+    }
+
+    /// Exact quotient and remainder of num * token_amt / denom.
+    /// The product of 2 u64s always fits in a u128.
+    fn div_rem(&self, token_amt: u64) -> Result<(u128, u128), SimpleDexError> {
+        if self.denom == 0 {
+            return Err(SimpleDexError::NumericalError);
+        }
+        let product = self.num as u128 * token_amt as u128;
+        let denom = self.denom as u128;
+        Ok((product / denom, product % denom))
     }
 }
 
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, DeserializePacked, SerializePacked)]
 pub enum OfferSeq {
-    Maker,
-    Taker,
-    Neither,
+    Maker = 0,
+    Taker = 1,
+    Neither = 2,
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use spl_math::precise_number::PreciseNumber;
+
+    use super::*;
+
+    /// The previous spl-math based implementation, kept as a reference.
+    /// PreciseNumber has 12 decimals of precision and rounds half up on division
+    /// and on conversion back to an integer.
+    mod precise_number_reference {
+        use super::*;
+
+        fn precise(n: u64) -> PreciseNumber {
+            PreciseNumber::new(n as u128).unwrap()
+        }
+
+        fn apply(ratio: &Ratio, token_amt: u64) -> Option<PreciseNumber> {
+            precise(ratio.num)
+                .checked_mul(&precise(token_amt))?
+                .checked_div(&precise(ratio.denom))
+        }
+
+        pub fn apply_floor(ratio: &Ratio, token_amt: u64) -> Option<u64> {
+            apply(ratio, token_amt)?.to_imprecise()?.try_into().ok()
+        }
+
+        pub fn apply_ceil(ratio: &Ratio, token_amt: u64) -> Option<u64> {
+            apply(ratio, token_amt)?
+                .ceiling()?
+                .to_imprecise()?
+                .try_into()
+                .ok()
+        }
+    }
+
+    prop_compose! {
+        fn ratio_and_amt()
+            (denom in 1..=u64::MAX)
+            // keep num <= denom like all on-chain ratios so results fit in a u64
+            (num in 0..=denom, denom in Just(denom), token_amt in 0..=u64::MAX)
+            -> (Ratio, u64) {
+                (Ratio::new_unchecked(num, denom), token_amt)
+            }
+    }
+
+    proptest! {
+        #[test]
+        fn test_apply_floor_exact((ratio, token_amt) in ratio_and_amt()) {
+            let product = ratio.num as u128 * token_amt as u128;
+            let floor = ratio.apply_floor_exact(token_amt)? as u128;
+            prop_assert!(floor * ratio.denom as u128 <= product);
+            prop_assert!(product < (floor + 1) * ratio.denom as u128);
+        }
+
+        #[test]
+        fn test_apply_ceil_exact((ratio, token_amt) in ratio_and_amt()) {
+            let product = ratio.num as u128 * token_amt as u128;
+            let ceil = ratio.apply_ceil_exact(token_amt)? as u128;
+            prop_assert!(ceil * ratio.denom as u128 >= product);
+            prop_assert!(product + (ratio.denom as u128) > ceil * ratio.denom as u128);
+        }
+
+        #[test]
+        fn test_differential_against_precise_number((ratio, token_amt) in ratio_and_amt()) {
+            prop_assert_eq!(
+                precise_number_reference::apply_floor(&ratio, token_amt).unwrap(),
+                ratio.apply_floor(token_amt)?
+            );
+            prop_assert_eq!(
+                precise_number_reference::apply_ceil(&ratio, token_amt).unwrap(),
+                ratio.apply_ceil(token_amt)?
+            );
+        }
+
+        #[test]
+        fn test_differential_small_denoms(
+            num in 0..=10_000u64,
+            denom in 1..=10_000u64,
+            token_amt in 0..=u64::MAX / 10_000,
+        ) {
+            // fee-sized ratios, where rounding half up matters most
+            let ratio = Ratio::new_unchecked(num, denom);
+            prop_assert_eq!(
+                precise_number_reference::apply_floor(&ratio, token_amt).unwrap(),
+                ratio.apply_floor(token_amt)?
+            );
+            prop_assert_eq!(
+                precise_number_reference::apply_ceil(&ratio, token_amt).unwrap(),
+                ratio.apply_ceil(token_amt)?
+            );
+        }
+
+        #[test]
+        fn test_differential_tiny_fractions(
+            denom in 1_000_000_000_000u64..=u64::MAX,
+            token_amt in 0..=u64::MAX,
+        ) {
+            // fractional parts below 1e-12 and exact halves
+            for num in [1, denom / 2, denom - 1] {
+                let ratio = Ratio::new_unchecked(num, denom);
+                prop_assert_eq!(
+                    precise_number_reference::apply_floor(&ratio, token_amt).unwrap(),
+                    ratio.apply_floor(token_amt)?
+                );
+                prop_assert_eq!(
+                    precise_number_reference::apply_ceil(&ratio, token_amt).unwrap(),
+                    ratio.apply_ceil(token_amt)?
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounding_edges() {
+        // 10 bps fee of 49_500 is 49.5, rounded half up
+        let fee = Ratio::new_unchecked(10, 10_000);
+        assert_eq!(fee.apply_floor(49_500).unwrap(), 50);
+        assert_eq!(fee.apply_floor(49_499).unwrap(), 49);
+        assert_eq!(fee.apply_floor_exact(49_500).unwrap(), 49);
+        // PreciseNumber's ceiling loses fractions below 1e-12
+        let tiny = Ratio::new_unchecked(1, 10_000_000_000_000);
+        assert_eq!(tiny.apply_ceil(1).unwrap(), 0);
+        assert_eq!(tiny.apply_ceil_exact(1).unwrap(), 1);
+        for (ratio, token_amt) in [
+            (Ratio::new_unchecked(1, 2), 1),
+            (Ratio::new_unchecked(1, u64::MAX), 1),
+            (Ratio::new_unchecked(u64::MAX - 1, u64::MAX), u64::MAX),
+            (Ratio::new_unchecked(10, 10_000), 49_500),
+        ] {
+            assert_eq!(
+                precise_number_reference::apply_floor(&ratio, token_amt).unwrap(),
+                ratio.apply_floor(token_amt).unwrap()
+            );
+            assert_eq!(
+                precise_number_reference::apply_ceil(&ratio, token_amt).unwrap(),
+                ratio.apply_ceil(token_amt).unwrap()
+            );
+        }
+    }
 }
@@ -0,0 +1,313 @@
+#![cfg(feature = "test-bpf")]
+
+//! Compute units consumed by each instruction, printed with `--nocapture`.
+//!
+//! Only meaningful against a BPF build, e.g. `cargo test-sbf --test compute_units -- --nocapture`:
+//! natively, program-test only meters the token and system program CPIs an instruction makes.
+
+mod helpers;
+
+use helpers::{
+    add_program_data, create_and_get_offer, create_ata, create_token_account, create_two_mints,
+    mint_tokens, program_test,
+};
+use simpledex::{
+    instructions::{
+        create_trader, match_offers, migrate_offer, quote_match, sync_trader_pair, withdraw_fees,
+        CancelOfferAccounts, CreateOfferAccounts, TakeOfferAccounts,
+    },
+    pda::{try_find_fee_authority_pda, try_find_offer_pda},
+    state::{Offer, MEMO_LEN},
+};
+use solana_program::{
+    hash::Hash, instruction::Instruction, program_pack::Pack, pubkey::Pubkey, rent::Rent,
+};
+use solana_program_test::{tokio, BanksClient};
+use solana_sdk::{account::Account, signature::Keypair, signer::Signer, transaction::Transaction};
+
+async fn funded_account(
+    client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let account = Keypair::new();
+    create_token_account(client, payer, recent_blockhash, &account, mint, owner)
+        .await
+        .unwrap();
+    if amount > 0 {
+        mint_tokens(
+            client,
+            payer,
+            recent_blockhash,
+            mint,
+            &account.pubkey(),
+            payer,
+            amount,
+        )
+        .await
+        .unwrap();
+    }
+    account.pubkey()
+}
+
+/// Outdated offer account of `owner`, see migrate_offer.rs
+fn legacy_offer(owner: &Pubkey) -> (Pubkey, Offer, Account) {
+    let offer_mint = Pubkey::new_unique();
+    let accept_mint = Pubkey::new_unique();
+    let (offer_addr, bump) = try_find_offer_pda(owner, &offer_mint, &accept_mint, 0).unwrap();
+    let offer = Offer {
+        slot: 1,
+        offering: 45,
+        accept_at_least: 2,
+        bump,
+        owner: *owner,
+        offer_mint,
+        accept_mint,
+        refund_to: *owner,
+        credit_to: *owner,
+        refund_rent_to: *owner,
+        ..Offer::default()
+    };
+    let mut current = vec![0; Offer::LEN];
+    Offer::pack(offer, &mut current).unwrap();
+    let data = current[2..2 + Offer::LEGACY_LEN].to_vec();
+    let account = Account {
+        lamports: Rent::default().minimum_balance(Offer::LEGACY_LEN),
+        data,
+        owner: simpledex::id(),
+        executable: false,
+        rent_epoch: 0,
+    };
+    (offer_addr, offer, account)
+}
+
+async fn units_consumed(
+    client: &mut BanksClient,
+    recent_blockhash: &Hash,
+    ix: Instruction,
+    signers: &[&Keypair],
+) -> u64 {
+    let mut tx = Transaction::new_with_payer(&[ix], Some(&signers[0].pubkey()));
+    tx.sign(&signers.to_vec(), *recent_blockhash);
+    let res = client.simulate_transaction(tx).await.unwrap();
+    res.result.unwrap().unwrap();
+    res.simulation_details.unwrap().units_consumed
+}
+
+#[tokio::test]
+async fn compute_units() {
+    let maker = Keypair::new();
+    let (legacy_addr, legacy, legacy_account) = legacy_offer(&maker.pubkey());
+    let upgrade_authority = Keypair::new();
+    let mut pt = program_test();
+    pt.add_account(legacy_addr, legacy_account);
+    add_program_data(&mut pt, &simpledex::id(), &upgrade_authority.pubkey());
+    let (mut client, payer, recent_blockhash) = pt.start().await;
+    let client = &mut client;
+    let (token_a, token_b) =
+        create_two_mints(client, &payer, &recent_blockhash, &payer.pubkey()).await;
+
+    let other = Keypair::new();
+    let maker_a = funded_account(
+        client,
+        &payer,
+        &recent_blockhash,
+        &token_a,
+        &maker.pubkey(),
+        10_000_000,
+    )
+    .await;
+    let maker_b = funded_account(
+        client,
+        &payer,
+        &recent_blockhash,
+        &token_b,
+        &maker.pubkey(),
+        10_000_000,
+    )
+    .await;
+    let other_a = funded_account(
+        client,
+        &payer,
+        &recent_blockhash,
+        &token_a,
+        &other.pubkey(),
+        0,
+    )
+    .await;
+    let other_b = funded_account(
+        client,
+        &payer,
+        &recent_blockhash,
+        &token_b,
+        &other.pubkey(),
+        1_000_000,
+    )
+    .await;
+    let matcher_a = funded_account(
+        client,
+        &payer,
+        &recent_blockhash,
+        &token_a,
+        &payer.pubkey(),
+        0,
+    )
+    .await;
+    let matcher_b = funded_account(
+        client,
+        &payer,
+        &recent_blockhash,
+        &token_b,
+        &payer.pubkey(),
+        0,
+    )
+    .await;
+    let (fee_authority, _bump) = try_find_fee_authority_pda().unwrap();
+    create_ata(client, &payer, &recent_blockhash, &token_b, &fee_authority)
+        .await
+        .unwrap();
+
+    let mut trader_tx = Transaction::new_with_payer(
+        &[create_trader(&payer.pubkey(), &maker.pubkey()).unwrap()],
+        Some(&payer.pubkey()),
+    );
+    trader_tx.sign(&[&payer, &maker], recent_blockhash);
+    client.process_transaction(trader_tx).await.unwrap();
+
+    // maker offers 1_000_000 A for 100_000 B,
+    // other offers 50_000 B for 500_000 A, which only partially fills maker's offer
+    let (_, offer_a) = create_and_get_offer(
+        client,
+        &recent_blockhash,
+        &payer,
+        &maker,
+        &maker_a,
+        &maker_b,
+        &token_a,
+        &token_b,
+        1,
+        1_000_000,
+        100_000,
+    )
+    .await;
+    let (_, offer_b) = create_and_get_offer(
+        client,
+        &recent_blockhash,
+        &payer,
+        &other,
+        &other_b,
+        &other_a,
+        &token_b,
+        &token_a,
+        0,
+        50_000,
+        500_000,
+    )
+    .await;
+
+    let create = CreateOfferAccounts::new(&maker.pubkey(), &token_a, &token_b, 2)
+        .unwrap()
+        .payer(&payer.pubkey())
+        .pay_from(&maker_a)
+        .refund_to(&maker_a)
+        .credit_to(&maker_b)
+        .refund_rent_to(&payer.pubkey())
+        .with_trader()
+        .unwrap()
+        .instruction(2, 1_000_000, 100_000, 0, [0; MEMO_LEN])
+        .unwrap();
+    let cancel = CancelOfferAccounts::new(&offer_a)
+        .unwrap()
+        .with_trader()
+        .unwrap()
+        .instruction()
+        .unwrap();
+    let take = TakeOfferAccounts::new(&offer_a, &other.pubkey())
+        .unwrap()
+        .pay_from(&other_b)
+        .receive_to(&other_a)
+        .instruction(25_000, 0)
+        .unwrap();
+
+    let fresh = Keypair::new();
+    let measured = [
+        (
+            "CreateTrader",
+            units_consumed(
+                client,
+                &recent_blockhash,
+                create_trader(&payer.pubkey(), &fresh.pubkey()).unwrap(),
+                &[&payer, &fresh],
+            )
+            .await,
+        ),
+        (
+            "CreateOffer",
+            units_consumed(client, &recent_blockhash, create, &[&payer, &maker]).await,
+        ),
+        (
+            "CancelOffer",
+            units_consumed(client, &recent_blockhash, cancel, &[&payer, &maker]).await,
+        ),
+        (
+            "MatchOffers",
+            units_consumed(
+                client,
+                &recent_blockhash,
+                match_offers(&offer_a, &offer_b, &matcher_a, &matcher_b).unwrap(),
+                &[&payer],
+            )
+            .await,
+        ),
+        (
+            "QuoteMatch",
+            units_consumed(
+                client,
+                &recent_blockhash,
+                quote_match(&offer_a, &offer_b).unwrap(),
+                &[&payer],
+            )
+            .await,
+        ),
+        (
+            "TakeOffer",
+            units_consumed(client, &recent_blockhash, take, &[&payer, &other]).await,
+        ),
+        (
+            "MigrateOffer",
+            units_consumed(
+                client,
+                &recent_blockhash,
+                migrate_offer(&payer.pubkey(), &legacy).unwrap(),
+                &[&payer],
+            )
+            .await,
+        ),
+        (
+            "SyncTraderPair",
+            units_consumed(
+                client,
+                &recent_blockhash,
+                sync_trader_pair(&maker.pubkey(), &token_a, &token_b, 0).unwrap(),
+                &[&payer, &maker],
+            )
+            .await,
+        ),
+        (
+            "WithdrawFees",
+            units_consumed(
+                client,
+                &recent_blockhash,
+                withdraw_fees(&upgrade_authority.pubkey(), &token_b, &matcher_b).unwrap(),
+                &[&payer, &upgrade_authority],
+            )
+            .await,
+        ),
+    ];
+    for (name, units) in measured {
+        println!("{:<14} {:>7} CU", name, units);
+    }
+}
@@ -5,7 +5,13 @@ mod helpers;
 use std::assert_eq;
 
 use helpers::{create_token_account, create_two_mints, mint_tokens, program_test};
-use simpledex::{instructions::create_offer, pda::try_find_offer_pda, state::Offer};
+use simpledex::{
+    instructions::{create_offer, CreateOfferAccounts},
+    packun::DeserializePacked,
+    pda::try_find_offer_pda,
+    return_data::CreateOfferReturnData,
+    state::{Offer, MEMO_LEN},
+};
 use solana_program::{hash::Hash, program_pack::Pack, pubkey::Pubkey};
 use solana_program_test::{tokio, BanksClient};
 use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
@@ -82,11 +88,15 @@ async fn setup(mint_tokens_a: u64, mint_tokens_b: u64) -> CreateOfferEnv {
     }
 }
 
+/// the deprecated positional builder still works
+#[allow(deprecated)]
 #[tokio::test]
 async fn success() {
     let seed = 0;
     let offering = 45;
     let accept_at_least = 2;
+    let client_order_id = 1234;
+    let memo = *b"grid-bot-level-3";
     let mut env = setup(offering + 5, 0).await;
     let ix = create_offer(
         &env.payer.pubkey(),
@@ -100,6 +110,8 @@ async fn success() {
         seed,
         offering,
         accept_at_least,
+        client_order_id,
+        memo,
     )
     .unwrap();
     let mut transaction = Transaction::new_with_payer(&[ix], Some(&env.payer.pubkey()));
@@ -119,6 +131,41 @@ async fn success() {
     assert_eq!(offer.refund_to, env.token_a_account);
     assert_eq!(offer.credit_to, env.token_b_account);
     assert_eq!(offer.refund_rent_to, env.payer.pubkey());
+    assert_eq!(offer.client_order_id, client_order_id);
+    assert_eq!(offer.memo, memo);
+}
+
+#[tokio::test]
+async fn success_return_data() {
+    let seed = 3;
+    let mut env = setup(100, 0).await;
+    let ix = CreateOfferAccounts::new(&env.owner.pubkey(), &env.token_a, &env.token_b, seed)
+        .unwrap()
+        .payer(&env.payer.pubkey())
+        .pay_from(&env.token_a_account)
+        .refund_to(&env.token_a_account)
+        .credit_to(&env.token_b_account)
+        .instruction(seed, 50, 2, 0, [0; MEMO_LEN])
+        .unwrap();
+    let mut transaction = Transaction::new_with_payer(&[ix], Some(&env.payer.pubkey()));
+    transaction.sign(&[&env.payer, &env.owner], env.recent_blockhash);
+    let res = env
+        .client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    res.result.unwrap();
+    let return_data = res.metadata.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, simpledex::id());
+    let (offer_addr, bump) =
+        try_find_offer_pda(&env.owner.pubkey(), &env.token_a, &env.token_b, seed).unwrap();
+    assert_eq!(
+        CreateOfferReturnData::read_bytes(&mut return_data.data.as_slice()).unwrap(),
+        CreateOfferReturnData {
+            offer: offer_addr,
+            bump
+        }
+    );
 }
 
 // TODO: more tests
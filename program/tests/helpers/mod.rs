@@ -1,4 +1,4 @@
-#![allow(dead_code)]
+#![allow(dead_code, unused_imports)]
 
 mod simpledex_helpers;
 mod spl_token_helpers;
@@ -9,12 +9,17 @@ pub use spl_token_helpers::*;
 pub use sys_helpers::*;
 
 use simpledex::processor;
+use solana_program::pubkey::Pubkey;
 use solana_program_test::{processor, ProgramTest};
 
 pub fn program_test() -> ProgramTest {
+    program_test_with_program_id(simpledex::id())
+}
+
+pub fn program_test_with_program_id(program_id: Pubkey) -> ProgramTest {
     ProgramTest::new(
         "simpledex",
-        simpledex::id(),
+        program_id,
         processor!(processor::Processor::process),
     )
 }
@@ -1,8 +1,13 @@
-use simpledex::{instructions::create_offer, pda::try_find_offer_pda, state::Offer};
+use simpledex::{
+    instructions::CreateOfferAccounts,
+    pda::try_find_offer_pda,
+    state::{Offer, MEMO_LEN},
+};
 use solana_program::{hash::Hash, program_pack::Pack, pubkey::Pubkey};
 use solana_program_test::BanksClient;
 use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_and_get_offer(
     client: &mut BanksClient,
     recent_blockhash: &Hash,
@@ -16,20 +21,15 @@ pub async fn create_and_get_offer(
     offering: u64,
     accept_at_least: u64,
 ) -> (Pubkey, Offer) {
-    let create_ix = create_offer(
-        &payer.pubkey(),
-        &owner.pubkey(),
-        token_a_account,
-        token_a_account,
-        token_b_account,
-        &payer.pubkey(),
-        token_a,
-        token_b,
-        seed,
-        offering,
-        accept_at_least,
-    )
-    .unwrap();
+    let create_ix = CreateOfferAccounts::new(&owner.pubkey(), token_a, token_b, seed)
+        .unwrap()
+        .payer(&payer.pubkey())
+        .pay_from(token_a_account)
+        .refund_to(token_a_account)
+        .credit_to(token_b_account)
+        .refund_rent_to(&payer.pubkey())
+        .instruction(seed, offering, accept_at_least, 0, [0; MEMO_LEN])
+        .unwrap();
     let mut create_tx = Transaction::new_with_payer(&[create_ix], Some(&payer.pubkey()));
     create_tx.sign(&[payer, owner], *recent_blockhash);
     client.process_transaction(create_tx).await.unwrap();
@@ -3,6 +3,7 @@ use solana_program_test::BanksClient;
 use solana_sdk::{
     signature::Keypair, signer::Signer, transaction::Transaction, transport::TransportError,
 };
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
 use spl_token::state::Account;
 
 pub async fn create_two_mints(
@@ -108,6 +109,27 @@ pub async fn create_token_account(
         .map_err(|e| e.into())
 }
 
+pub async fn create_ata(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Pubkey, TransportError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[create_associated_token_account(
+            &payer.pubkey(),
+            owner,
+            mint,
+        )],
+        Some(&payer.pubkey()),
+        &[payer],
+        *recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await?;
+    Ok(get_associated_token_address(owner, mint))
+}
+
 pub async fn mint_tokens(
     banks_client: &mut BanksClient,
     payer: &Keypair,
@@ -1,6 +1,7 @@
-use solana_program::{hash::Hash, pubkey::Pubkey, system_instruction};
-use solana_program_test::BanksClient;
+use solana_program::{bpf_loader_upgradeable, hash::Hash, pubkey::Pubkey, system_instruction};
+use solana_program_test::{BanksClient, ProgramTest};
 use solana_sdk::{
+    account::Account,
     signature::{Keypair, Signer},
     transaction::Transaction,
 };
@@ -24,3 +25,29 @@ pub async fn transfer(
     );
     banks_client.process_transaction(transaction).await.unwrap();
 }
+
+/// Adds the ProgramData account an upgradeable deployment at `program_id` would have,
+/// since program-test's native programs have none
+pub fn add_program_data(
+    program_test: &mut ProgramTest,
+    program_id: &Pubkey,
+    upgrade_authority: &Pubkey,
+) {
+    let (program_data, _bump) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    // bincode UpgradeableLoaderState::ProgramData { slot: 0, upgrade_authority_address: Some(_) }
+    let mut data = vec![3, 0, 0, 0];
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(upgrade_authority.as_ref());
+    program_test.add_account(
+        program_data,
+        Account {
+            lamports: 1_000_000_000,
+            data,
+            owner: bpf_loader_upgradeable::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
@@ -8,8 +8,14 @@ use helpers::{
     create_and_get_offer, create_token_account, create_two_mints, get_token_acc, mint_tokens,
     program_test, transfer,
 };
-use simpledex::instructions::match_offers;
-use solana_program::{hash::Hash, pubkey::Pubkey};
+use simpledex::{
+    instructions::{match_offers, quote_match},
+    packun::DeserializePacked,
+    quote::MatchQuote,
+    return_data::MatchOffersReturnData,
+    state::Offer,
+};
+use solana_program::{hash::Hash, program_pack::Pack, pubkey::Pubkey};
 use solana_program_test::{tokio, BanksClient};
 use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
 use spl_associated_token_account::get_associated_token_address;
@@ -34,6 +40,7 @@ struct MatchOffersEnv {
     matcher_token_b_account: Pubkey,
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn setup_accounts(
     client: &mut BanksClient,
     payer: &Keypair,
@@ -250,4 +257,246 @@ async fn success_match_exact() {
     assert_eq!(owner_b_token_a_acc.amount, offer_b_accept_at_least);
 }
 
+#[tokio::test]
+async fn success_match_partial_records_fill() {
+    let mint_a_tokens = 1_000_000;
+    let mint_b_tokens = 123_456;
+    let offer_a_offering = 900_000;
+    let offer_a_accept_at_least = 99_000;
+    let offer_b_offering = offer_a_accept_at_least / 2;
+    let offer_b_accept_at_least = offer_a_offering / 2;
+
+    let mut env = setup(mint_a_tokens, mint_b_tokens).await;
+
+    let (offering_a_addr, offering_a) = create_and_get_offer(
+        &mut env.client,
+        &env.recent_blockhash,
+        &env.payer,
+        &env.owner_a,
+        &env.owner_a_token_a_account,
+        &env.owner_a_token_b_account,
+        &env.token_a,
+        &env.token_b,
+        0,
+        offer_a_offering,
+        offer_a_accept_at_least,
+    )
+    .await;
+
+    let (_offering_b_addr, offering_b) = create_and_get_offer(
+        &mut env.client,
+        &env.recent_blockhash,
+        &env.owner_b,
+        &env.owner_b,
+        &env.owner_b_token_b_account,
+        &env.owner_b_token_a_account,
+        &env.token_b,
+        &env.token_a,
+        0,
+        offer_b_offering,
+        offer_b_accept_at_least,
+    )
+    .await;
+
+    let match_ix = match_offers(
+        &offering_a,
+        &offering_b,
+        &env.matcher_token_a_account,
+        &env.matcher_token_b_account,
+    )
+    .unwrap();
+    let mut match_tx = Transaction::new_with_payer(&[match_ix], Some(&env.matcher.pubkey()));
+    match_tx.sign(&[&env.matcher], env.recent_blockhash);
+    env.client.process_transaction(match_tx).await.unwrap();
+
+    // offering_a is half filled and remains open
+    let offering_a_acc = env
+        .client
+        .get_account(offering_a_addr)
+        .await
+        .unwrap()
+        .unwrap();
+    let offering_a_after = Offer::unpack(&offering_a_acc.data).unwrap();
+    assert_eq!(offering_a_after.offering, offer_a_offering / 2);
+    assert_eq!(offering_a_after.original_offering, offer_a_offering);
+    assert_eq!(
+        offering_a_after.original_accept_at_least,
+        offer_a_accept_at_least
+    );
+    assert_eq!(
+        offering_a_after.filled_offer_amount,
+        offer_b_accept_at_least
+    );
+    assert_eq!(offering_a_after.received_amount, offer_b_offering);
+    assert_eq!(offering_a_after.fill_count, 1);
+
+    let owner_a_token_b_acc = get_token_acc(&mut env.client, &env.owner_a_token_b_account).await;
+    assert_eq!(owner_a_token_b_acc.amount, offering_a_after.received_amount);
+}
+
+#[tokio::test]
+async fn success_return_data() {
+    let mint_a_tokens = 1_000_000;
+    let mint_b_tokens = 123_456;
+    let offer_a_offering = 900_000;
+    let offer_a_accept_at_least = 99_000;
+    let offer_b_offering = offer_a_accept_at_least / 2;
+    let offer_b_accept_at_least = offer_a_offering / 3;
+
+    let mut env = setup(mint_a_tokens, mint_b_tokens).await;
+
+    let (offering_a_addr, offering_a) = create_and_get_offer(
+        &mut env.client,
+        &env.recent_blockhash,
+        &env.payer,
+        &env.owner_a,
+        &env.owner_a_token_a_account,
+        &env.owner_a_token_b_account,
+        &env.token_a,
+        &env.token_b,
+        0,
+        offer_a_offering,
+        offer_a_accept_at_least,
+    )
+    .await;
+
+    let (offering_b_addr, offering_b) = create_and_get_offer(
+        &mut env.client,
+        &env.recent_blockhash,
+        &env.owner_b,
+        &env.owner_b,
+        &env.owner_b_token_b_account,
+        &env.owner_b_token_a_account,
+        &env.token_b,
+        &env.token_a,
+        0,
+        offer_b_offering,
+        offer_b_accept_at_least,
+    )
+    .await;
+    let expected =
+        MatchOffersReturnData::from(&MatchQuote::calc(&offering_a, &offering_b).unwrap());
+
+    let match_ix = match_offers(
+        &offering_a,
+        &offering_b,
+        &env.matcher_token_a_account,
+        &env.matcher_token_b_account,
+    )
+    .unwrap();
+    let mut match_tx = Transaction::new_with_payer(&[match_ix], Some(&env.matcher.pubkey()));
+    match_tx.sign(&[&env.matcher], env.recent_blockhash);
+    let res = env
+        .client
+        .process_transaction_with_metadata(match_tx)
+        .await
+        .unwrap();
+    res.result.unwrap();
+    let return_data = res.metadata.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, simpledex::id());
+    let returned = MatchOffersReturnData::read_bytes(&mut return_data.data.as_slice()).unwrap();
+    assert_eq!(returned, expected);
+
+    // offering_a remains open with the returned terms, offering_b is filled and closed
+    let offering_a_acc = env
+        .client
+        .get_account(offering_a_addr)
+        .await
+        .unwrap()
+        .unwrap();
+    let offering_a_after = Offer::unpack(&offering_a_acc.data).unwrap();
+    assert_eq!(offering_a_after.offering, returned.offering_a_new_offering);
+    assert_eq!(
+        offering_a_after.accept_at_least,
+        returned.offering_a_new_accept_at_least
+    );
+    assert_eq!(returned.offering_b_new_offering, 0);
+    assert!(env
+        .client
+        .get_account(offering_b_addr)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn success_quote_match_return_data() {
+    let mut env = setup(1_000_000, 123_456).await;
+
+    let (offering_a_addr, offering_a) = create_and_get_offer(
+        &mut env.client,
+        &env.recent_blockhash,
+        &env.payer,
+        &env.owner_a,
+        &env.owner_a_token_a_account,
+        &env.owner_a_token_b_account,
+        &env.token_a,
+        &env.token_b,
+        0,
+        900_000,
+        99_000,
+    )
+    .await;
+
+    let (offering_b_addr, offering_b) = create_and_get_offer(
+        &mut env.client,
+        &env.recent_blockhash,
+        &env.owner_b,
+        &env.owner_b,
+        &env.owner_b_token_b_account,
+        &env.owner_b_token_a_account,
+        &env.token_b,
+        &env.token_a,
+        0,
+        49_500,
+        300_000,
+    )
+    .await;
+
+    let quote_ix = quote_match(&offering_a, &offering_b).unwrap();
+    let mut quote_tx = Transaction::new_with_payer(&[quote_ix], Some(&env.matcher.pubkey()));
+    quote_tx.sign(&[&env.matcher], env.recent_blockhash);
+    let res = env.client.simulate_transaction(quote_tx).await.unwrap();
+    res.result.unwrap().unwrap();
+    let return_data = res.simulation_details.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, simpledex::id());
+    assert_eq!(
+        MatchOffersReturnData::read_bytes(&mut return_data.data.as_slice()).unwrap(),
+        MatchOffersReturnData::from(&MatchQuote::calc(&offering_a, &offering_b).unwrap())
+    );
+
+    // the quote matches what MatchOffers then returns
+    let match_ix = match_offers(
+        &offering_a,
+        &offering_b,
+        &env.matcher_token_a_account,
+        &env.matcher_token_b_account,
+    )
+    .unwrap();
+    let mut match_tx = Transaction::new_with_payer(&[match_ix], Some(&env.matcher.pubkey()));
+    match_tx.sign(&[&env.matcher], env.recent_blockhash);
+    let match_res = env
+        .client
+        .process_transaction_with_metadata(match_tx)
+        .await
+        .unwrap();
+    match_res.result.unwrap();
+    assert_eq!(
+        match_res.metadata.unwrap().return_data.unwrap().data,
+        return_data.data
+    );
+    assert!(env
+        .client
+        .get_account(offering_a_addr)
+        .await
+        .unwrap()
+        .is_some());
+    assert!(env
+        .client
+        .get_account(offering_b_addr)
+        .await
+        .unwrap()
+        .is_none());
+}
+
 // TODO: more tests
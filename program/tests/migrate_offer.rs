@@ -0,0 +1,149 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::program_test;
+use simpledex::{
+    instructions::{cancel_offer, migrate_offer},
+    pda::try_find_offer_pda,
+    state::{Offer, OfferVersion},
+};
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent};
+use solana_program_test::tokio;
+use solana_sdk::{account::Account, signature::Keypair, signer::Signer, transaction::Transaction};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account as TokenAccount, AccountState};
+
+fn legacy_offer(owner: Pubkey) -> (Pubkey, Offer, Vec<u8>) {
+    let offer_mint = Pubkey::new_unique();
+    let accept_mint = Pubkey::new_unique();
+    let seed = 3;
+    let (offer_addr, bump) = try_find_offer_pda(&owner, &offer_mint, &accept_mint, seed).unwrap();
+    let offer = Offer {
+        slot: 1,
+        offering: 45,
+        accept_at_least: 2,
+        seed,
+        bump,
+        owner,
+        offer_mint,
+        accept_mint,
+        refund_to: Pubkey::new_unique(),
+        credit_to: Pubkey::new_unique(),
+        refund_rent_to: owner,
+        ..Offer::default()
+    };
+    // legacy layout is the current one without the 2-byte prefix,
+    // the fields that came after refund_rent_to and 2 bytes of padding
+    let mut current = vec![0; Offer::LEN];
+    Offer::pack(offer, &mut current).unwrap();
+    let mut legacy = current[2..Offer::LEGACY_LEN].to_vec();
+    legacy.resize(Offer::LEGACY_LEN, 0);
+    (offer_addr, offer, legacy)
+}
+
+#[tokio::test]
+async fn success() {
+    let (offer_addr, offer, legacy) = legacy_offer(Pubkey::new_unique());
+    let mut pt = program_test();
+    pt.add_account(
+        offer_addr,
+        Account {
+            lamports: Rent::default().minimum_balance(Offer::LEGACY_LEN),
+            data: legacy,
+            owner: simpledex::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    let (mut client, payer, recent_blockhash) = pt.start().await;
+
+    let before = client.get_account(offer_addr).await.unwrap().unwrap();
+    let (_, version) = Offer::unpack_any_version(&before.data).unwrap();
+    assert_eq!(version, OfferVersion::Legacy);
+
+    let migrate_ix = migrate_offer(&payer.pubkey(), &offer).unwrap();
+    let mut migrate_tx = Transaction::new_with_payer(&[migrate_ix], Some(&payer.pubkey()));
+    migrate_tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(migrate_tx).await.unwrap();
+
+    let after = client.get_account(offer_addr).await.unwrap().unwrap();
+    assert_eq!(after.data.len(), Offer::LEN);
+    assert!(after.lamports >= Rent::default().minimum_balance(Offer::LEN));
+    let migrated = Offer::unpack(&after.data).unwrap();
+    assert_eq!(migrated.slot, offer.slot);
+    assert_eq!(migrated.offering, offer.offering);
+    assert_eq!(migrated.accept_at_least, offer.accept_at_least);
+    assert_eq!(migrated.owner, offer.owner);
+    assert_eq!(migrated.refund_to, offer.refund_to);
+    assert_eq!(migrated.credit_to, offer.credit_to);
+    assert_eq!(migrated.client_order_id, 0);
+    assert_eq!(migrated.original_offering, offer.offering);
+    assert_eq!(migrated.original_accept_at_least, offer.accept_at_least);
+    assert_eq!(migrated.fill_count, 0);
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(
+        TokenAccount {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        &mut data,
+    )
+    .unwrap();
+    Account {
+        lamports: Rent::default().minimum_balance(TokenAccount::LEN),
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+#[tokio::test]
+async fn success_cancel_without_migrating() {
+    let owner = Keypair::new();
+    let (offer_addr, offer, legacy) = legacy_offer(owner.pubkey());
+    let holding_addr = get_associated_token_address(&offer_addr, &offer.offer_mint);
+    let mut pt = program_test();
+    pt.add_account(
+        offer_addr,
+        Account {
+            lamports: Rent::default().minimum_balance(Offer::LEGACY_LEN),
+            data: legacy,
+            owner: simpledex::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    pt.add_account(
+        holding_addr,
+        token_account(offer.offer_mint, offer_addr, offer.offering),
+    );
+    pt.add_account(
+        offer.refund_to,
+        token_account(offer.offer_mint, owner.pubkey(), 0),
+    );
+    let (mut client, payer, recent_blockhash) = pt.start().await;
+
+    let cancel_ix = cancel_offer(&offer).unwrap();
+    let mut cancel_tx = Transaction::new_with_payer(&[cancel_ix], Some(&payer.pubkey()));
+    cancel_tx.sign(&[&payer, &owner], recent_blockhash);
+    client.process_transaction(cancel_tx).await.unwrap();
+
+    assert!(client.get_account(offer_addr).await.unwrap().is_none());
+    assert!(client.get_account(holding_addr).await.unwrap().is_none());
+    let refund_to = client.get_account(offer.refund_to).await.unwrap().unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&refund_to.data).unwrap().amount,
+        offer.offering
+    );
+}
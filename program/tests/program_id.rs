@@ -0,0 +1,173 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::{create_token_account, create_two_mints, mint_tokens, program_test_with_program_id};
+use solana_program_test::{tokio, BanksClient};
+use solana_sdk::{
+    hash::Hash, pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction,
+};
+
+#[cfg(feature = "any-program-id")]
+use simpledex::{
+    instructions::CancelOfferAccounts,
+    pda::{
+        try_find_offer_pda, try_find_offer_pda_with_program_id, try_find_trader_pda_with_program_id,
+    },
+    state::{Offer, Trader},
+};
+use simpledex::{
+    instructions::{create_trader_with_program_id, CreateOfferAccounts},
+    state::MEMO_LEN,
+};
+#[cfg(feature = "any-program-id")]
+use solana_program::program_pack::Pack;
+
+struct ProgramIdEnv {
+    client: BanksClient,
+    recent_blockhash: Hash,
+    payer: Keypair,
+    token_a: Pubkey,
+    token_a_account: Pubkey,
+    token_b: Pubkey,
+    token_b_account: Pubkey,
+}
+
+async fn setup(program_id: Pubkey) -> ProgramIdEnv {
+    let (mut client, payer, recent_blockhash) =
+        program_test_with_program_id(program_id).start().await;
+    let token_a_account = Keypair::new();
+    let token_b_account = Keypair::new();
+    let (token_a, token_b) =
+        create_two_mints(&mut client, &payer, &recent_blockhash, &payer.pubkey()).await;
+    create_token_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_a_account,
+        &token_a,
+        &payer.pubkey(),
+    )
+    .await
+    .unwrap();
+    create_token_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_b_account,
+        &token_b,
+        &payer.pubkey(),
+    )
+    .await
+    .unwrap();
+    mint_tokens(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_a,
+        &token_a_account.pubkey(),
+        &payer,
+        1_000,
+    )
+    .await
+    .unwrap();
+    ProgramIdEnv {
+        client,
+        recent_blockhash,
+        payer,
+        token_a,
+        token_a_account: token_a_account.pubkey(),
+        token_b,
+        token_b_account: token_b_account.pubkey(),
+    }
+}
+
+#[cfg(feature = "any-program-id")]
+#[tokio::test]
+async fn success_at_other_program_id() {
+    let program_id = Pubkey::new_unique();
+    let ProgramIdEnv {
+        mut client,
+        recent_blockhash,
+        payer,
+        token_a,
+        token_a_account,
+        token_b,
+        token_b_account,
+    } = setup(program_id).await;
+    let owner = payer.pubkey();
+
+    let create_trader_ix = create_trader_with_program_id(&owner, &owner, &program_id).unwrap();
+    let seed = 0;
+    let accounts =
+        CreateOfferAccounts::new_with_program_id(&owner, &token_a, &token_b, seed, &program_id)
+            .unwrap()
+            .pay_from(&token_a_account)
+            .refund_to(&token_a_account)
+            .credit_to(&token_b_account)
+            .with_trader_with_program_id(&program_id)
+            .unwrap();
+    let create_ix = accounts
+        .instruction_with_program_id(&program_id, seed, 100, 10, 0, [0; MEMO_LEN])
+        .unwrap();
+    let mut tx = Transaction::new_with_payer(&[create_trader_ix, create_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+
+    // accounts are derived from, and owned by, the deployed program id
+    let (offer_addr, _bump) =
+        try_find_offer_pda_with_program_id(&owner, &token_a, &token_b, seed, &program_id).unwrap();
+    let (default_offer_addr, _bump) = try_find_offer_pda(&owner, &token_a, &token_b, seed).unwrap();
+    assert_eq!(accounts.offer, offer_addr);
+    assert_ne!(offer_addr, default_offer_addr);
+    let offer_acc = client.get_account(offer_addr).await.unwrap().unwrap();
+    assert_eq!(offer_acc.owner, program_id);
+    let offer = Offer::unpack(&offer_acc.data).unwrap();
+    let (trader_addr, _bump) = try_find_trader_pda_with_program_id(&owner, &program_id).unwrap();
+    let trader_acc = client.get_account(trader_addr).await.unwrap().unwrap();
+    assert_eq!(trader_acc.owner, program_id);
+    assert_eq!(
+        Trader::unpack(&trader_acc.data).unwrap().next_seed,
+        seed + 1
+    );
+
+    let cancel_ix = CancelOfferAccounts::new_with_program_id(&offer, &program_id)
+        .unwrap()
+        .instruction_with_program_id(&program_id)
+        .unwrap();
+    let mut tx = Transaction::new_with_payer(&[cancel_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+    assert!(client.get_account(offer_addr).await.unwrap().is_none());
+}
+
+#[cfg(not(feature = "any-program-id"))]
+#[tokio::test]
+async fn fail_at_other_program_id() {
+    let program_id = Pubkey::new_unique();
+    let ProgramIdEnv {
+        mut client,
+        recent_blockhash,
+        payer,
+        token_a,
+        token_a_account,
+        token_b,
+        token_b_account,
+    } = setup(program_id).await;
+    let owner = payer.pubkey();
+
+    let create_trader_ix = create_trader_with_program_id(&owner, &owner, &program_id).unwrap();
+    let create_ix =
+        CreateOfferAccounts::new_with_program_id(&owner, &token_a, &token_b, 0, &program_id)
+            .unwrap()
+            .pay_from(&token_a_account)
+            .refund_to(&token_a_account)
+            .credit_to(&token_b_account)
+            .instruction_with_program_id(&program_id, 0, 100, 10, 0, [0; MEMO_LEN])
+            .unwrap();
+    for ix in [create_trader_ix, create_ix] {
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&owner));
+        tx.sign(&[&payer], recent_blockhash);
+        assert!(client.process_transaction(tx).await.is_err());
+    }
+}
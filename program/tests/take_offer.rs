@@ -0,0 +1,362 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::{
+    add_program_data, create_and_get_offer, create_token_account, create_two_mints, get_token_acc,
+    mint_tokens, program_test, transfer,
+};
+use simpledex::{
+    error::SimpleDexError,
+    instructions::{create_fee_account, withdraw_fees, TakeOfferAccounts},
+    pda::try_find_fee_account,
+    state::Offer,
+};
+use solana_program::{
+    hash::Hash, instruction::InstructionError, program_pack::Pack, pubkey::Pubkey,
+};
+use solana_program_test::{tokio, BanksClient};
+use solana_sdk::{
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::get_associated_token_address;
+
+const MAKER_OFFERING: u64 = 900_000;
+const MAKER_ACCEPT_AT_LEAST: u64 = 99_000;
+const TAKER_TOKENS_B: u64 = 123_456;
+
+struct TakeOfferEnv {
+    client: BanksClient,
+    recent_blockhash: Hash,
+    offer_addr: Pubkey,
+    offer: Offer,
+
+    maker_token_b_account: Pubkey,
+
+    taker: Keypair,
+    taker_token_a_account: Pubkey,
+    taker_token_b_account: Pubkey,
+
+    fee_account: Pubkey,
+    upgrade_authority: Keypair,
+}
+
+async fn create_funded_account(
+    client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let account = Keypair::new();
+    create_token_account(client, payer, recent_blockhash, &account, mint, owner)
+        .await
+        .unwrap();
+    if amount > 0 {
+        mint_tokens(
+            client,
+            payer,
+            recent_blockhash,
+            mint,
+            &account.pubkey(),
+            payer,
+            amount,
+        )
+        .await
+        .unwrap();
+    }
+    account.pubkey()
+}
+
+/// maker offers `MAKER_OFFERING` token A for `MAKER_ACCEPT_AT_LEAST` token B
+async fn setup() -> TakeOfferEnv {
+    let upgrade_authority = Keypair::new();
+    let mut program_test = program_test();
+    add_program_data(
+        &mut program_test,
+        &simpledex::id(),
+        &upgrade_authority.pubkey(),
+    );
+    let (mut client, payer, recent_blockhash) = program_test.start().await;
+    let (token_a, token_b) =
+        create_two_mints(&mut client, &payer, &recent_blockhash, &payer.pubkey()).await;
+
+    let maker = Keypair::new();
+    let taker = Keypair::new();
+    let maker_token_a_account = create_funded_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_a,
+        &maker.pubkey(),
+        1_000_000,
+    )
+    .await;
+    let maker_token_b_account = create_funded_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_b,
+        &maker.pubkey(),
+        0,
+    )
+    .await;
+    let taker_token_a_account = create_funded_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_a,
+        &taker.pubkey(),
+        0,
+    )
+    .await;
+    let taker_token_b_account = create_funded_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_b,
+        &taker.pubkey(),
+        TAKER_TOKENS_B,
+    )
+    .await;
+    // deployment step: create the fee account of each accept mint
+    let create_fee_account_tx = Transaction::new_signed_with_payer(
+        &[create_fee_account(&payer.pubkey(), &token_b).unwrap()],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    client
+        .process_transaction(create_fee_account_tx)
+        .await
+        .unwrap();
+    let fee_account = try_find_fee_account(&token_b).unwrap();
+    transfer(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &taker.pubkey(),
+        1_000_000_000,
+    )
+    .await;
+
+    let (offer_addr, offer) = create_and_get_offer(
+        &mut client,
+        &recent_blockhash,
+        &payer,
+        &maker,
+        &maker_token_a_account,
+        &maker_token_b_account,
+        &token_a,
+        &token_b,
+        0,
+        MAKER_OFFERING,
+        MAKER_ACCEPT_AT_LEAST,
+    )
+    .await;
+
+    TakeOfferEnv {
+        client,
+        recent_blockhash,
+        offer_addr,
+        offer,
+        maker_token_b_account,
+        taker,
+        taker_token_a_account,
+        taker_token_b_account,
+        fee_account,
+        upgrade_authority,
+    }
+}
+
+fn take_accounts(env: &TakeOfferEnv) -> TakeOfferAccounts {
+    TakeOfferAccounts::new(&env.offer, &env.taker.pubkey())
+        .unwrap()
+        .pay_from(&env.taker_token_b_account)
+        .receive_to(&env.taker_token_a_account)
+}
+
+async fn take(
+    env: &mut TakeOfferEnv,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Result<(), TransactionError> {
+    let accounts = take_accounts(env);
+    process_take(env, accounts, amount_in, min_amount_out).await
+}
+
+async fn process_take(
+    env: &mut TakeOfferEnv,
+    accounts: TakeOfferAccounts,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Result<(), TransactionError> {
+    let ix = accounts.instruction(amount_in, min_amount_out).unwrap();
+    let mut tx = Transaction::new_with_payer(&[ix], Some(&env.taker.pubkey()));
+    tx.sign(&[&env.taker], env.recent_blockhash);
+    env.client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.unwrap())
+}
+
+#[tokio::test]
+async fn success_take_partial() {
+    let mut env = setup().await;
+    // half the offer at its limit price, 0.1% fee of 49.5 rounded half up
+    take(&mut env, 49_500, 450_000).await.unwrap();
+
+    let offer_acc = env
+        .client
+        .get_account(env.offer_addr)
+        .await
+        .unwrap()
+        .unwrap();
+    let offer_after = Offer::unpack(&offer_acc.data).unwrap();
+    assert_eq!(offer_after.offering, 450_000);
+    assert_eq!(offer_after.accept_at_least, 49_500);
+    assert_eq!(offer_after.filled_offer_amount, 450_000);
+    assert_eq!(offer_after.received_amount, 49_500);
+    assert_eq!(offer_after.fill_count, 1);
+
+    let taker_a = get_token_acc(&mut env.client, &env.taker_token_a_account).await;
+    assert_eq!(taker_a.amount, 450_000);
+    let taker_b = get_token_acc(&mut env.client, &env.taker_token_b_account).await;
+    assert_eq!(taker_b.amount, TAKER_TOKENS_B - 49_500 - 50);
+    let maker_b = get_token_acc(&mut env.client, &env.maker_token_b_account).await;
+    assert_eq!(maker_b.amount, 49_500);
+    let fee = get_token_acc(&mut env.client, &env.fee_account).await;
+    assert_eq!(fee.amount, 50);
+}
+
+#[tokio::test]
+async fn success_take_all_closes_offer() {
+    let mut env = setup().await;
+    // only the offer's accept_at_least is taken
+    take(&mut env, TAKER_TOKENS_B - 1_000, MAKER_OFFERING)
+        .await
+        .unwrap();
+
+    assert!(env
+        .client
+        .get_account(env.offer_addr)
+        .await
+        .unwrap()
+        .is_none());
+    let holding_addr = get_associated_token_address(&env.offer_addr, &env.offer.offer_mint);
+    assert!(env
+        .client
+        .get_account(holding_addr)
+        .await
+        .unwrap()
+        .is_none());
+
+    let taker_a = get_token_acc(&mut env.client, &env.taker_token_a_account).await;
+    assert_eq!(taker_a.amount, MAKER_OFFERING);
+    let taker_b = get_token_acc(&mut env.client, &env.taker_token_b_account).await;
+    assert_eq!(taker_b.amount, TAKER_TOKENS_B - MAKER_ACCEPT_AT_LEAST - 99);
+    let maker_b = get_token_acc(&mut env.client, &env.maker_token_b_account).await;
+    assert_eq!(maker_b.amount, MAKER_ACCEPT_AT_LEAST);
+}
+
+#[tokio::test]
+async fn fail_take_slippage_exceeded() {
+    let mut env = setup().await;
+    let slippage_exceeded = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(SimpleDexError::SlippageExceeded as u32),
+    );
+    assert_eq!(
+        take(&mut env, 49_500, 450_001).await.unwrap_err(),
+        slippage_exceeded
+    );
+    // too little to get any tokens out
+    assert_eq!(take(&mut env, 0, 0).await.unwrap_err(), slippage_exceeded);
+
+    let offer_acc = env
+        .client
+        .get_account(env.offer_addr)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Offer::unpack(&offer_acc.data).unwrap().offering,
+        MAKER_OFFERING
+    );
+}
+
+#[tokio::test]
+async fn fail_take_fee_to_self() {
+    let mut env = setup().await;
+    let accounts = take_accounts(&env);
+    assert_eq!(accounts.fee_account, env.fee_account);
+    // paying the fee back into the taker's own account
+    let fee_to_self = TakeOfferAccounts {
+        fee_account: env.taker_token_b_account,
+        ..accounts
+    };
+    assert_eq!(
+        process_take(&mut env, fee_to_self, 49_500, 450_000)
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(SimpleDexError::IncorrectFeeAccount as u32),
+        )
+    );
+}
+
+/// signed by the upgrade authority unless `signer` is given
+async fn process_withdraw_fees(
+    env: &mut TakeOfferEnv,
+    signer: Option<&Keypair>,
+    withdraw_to: &Pubkey,
+) -> Result<(), TransactionError> {
+    let signer = signer.unwrap_or(&env.upgrade_authority);
+    let ix = withdraw_fees(&signer.pubkey(), &env.offer.accept_mint, withdraw_to).unwrap();
+    let mut tx = Transaction::new_with_payer(&[ix], Some(&env.taker.pubkey()));
+    tx.sign(&[&env.taker, signer], env.recent_blockhash);
+    env.client
+        .process_transaction(tx)
+        .await
+        .map_err(|e| e.unwrap())
+}
+
+#[tokio::test]
+async fn success_withdraw_fees() {
+    let mut env = setup().await;
+    take(&mut env, 49_500, 450_000).await.unwrap();
+
+    let withdraw_to = env.maker_token_b_account;
+    process_withdraw_fees(&mut env, None, &withdraw_to)
+        .await
+        .unwrap();
+
+    let fee = get_token_acc(&mut env.client, &env.fee_account).await;
+    assert_eq!(fee.amount, 0);
+    let maker_b = get_token_acc(&mut env.client, &env.maker_token_b_account).await;
+    assert_eq!(maker_b.amount, 49_500 + 50);
+}
+
+#[tokio::test]
+async fn fail_withdraw_fees_not_upgrade_authority() {
+    let mut env = setup().await;
+    take(&mut env, 49_500, 450_000).await.unwrap();
+
+    let not_upgrade_authority = Keypair::new();
+    let withdraw_to = env.taker_token_b_account;
+    assert_eq!(
+        process_withdraw_fees(&mut env, Some(&not_upgrade_authority), &withdraw_to)
+            .await
+            .unwrap_err(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(SimpleDexError::IncorrectUpgradeAuthority as u32),
+        )
+    );
+    let fee = get_token_acc(&mut env.client, &env.fee_account).await;
+    assert_eq!(fee.amount, 50);
+}
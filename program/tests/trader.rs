@@ -0,0 +1,166 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::{create_token_account, create_two_mints, mint_tokens, program_test};
+use simpledex::{
+    error::SimpleDexError,
+    instructions::{create_trader, sync_trader_pair, CancelOfferAccounts, CreateOfferAccounts},
+    pda::{try_find_offer_pda, try_find_trader_pda},
+    state::{Offer, Trader, MEMO_LEN},
+};
+use solana_program::program_pack::Pack;
+use solana_program_test::{tokio, BanksClient};
+use solana_sdk::{
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+async fn get_trader(client: &mut BanksClient, owner: &Pubkey) -> Trader {
+    let (trader_addr, _bump) = try_find_trader_pda(owner).unwrap();
+    let trader = client.get_account(trader_addr).await.unwrap().unwrap();
+    Trader::unpack(&trader.data).unwrap()
+}
+
+#[tokio::test]
+async fn success_create_with_trader() {
+    let (mut client, payer, recent_blockhash) = program_test().start().await;
+    let token_a_account = Keypair::new();
+    let token_b_account = Keypair::new();
+    let (token_a, token_b) =
+        create_two_mints(&mut client, &payer, &recent_blockhash, &payer.pubkey()).await;
+    create_token_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_a_account,
+        &token_a,
+        &payer.pubkey(),
+    )
+    .await
+    .unwrap();
+    create_token_account(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_b_account,
+        &token_b,
+        &payer.pubkey(),
+    )
+    .await
+    .unwrap();
+    mint_tokens(
+        &mut client,
+        &payer,
+        &recent_blockhash,
+        &token_a,
+        &token_a_account.pubkey(),
+        &payer,
+        1_000,
+    )
+    .await
+    .unwrap();
+
+    let create_trader_ix = create_trader(&payer.pubkey(), &payer.pubkey()).unwrap();
+    let mut tx = Transaction::new_with_payer(&[create_trader_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+
+    let trader = get_trader(&mut client, &payer.pubkey()).await;
+    assert_eq!(trader.owner, payer.pubkey());
+    assert_eq!(trader.next_seed, 0);
+
+    let seed = trader.next_seed;
+    let create_ix = CreateOfferAccounts::new(&payer.pubkey(), &token_a, &token_b, seed)
+        .unwrap()
+        .pay_from(&token_a_account.pubkey())
+        .refund_to(&token_a_account.pubkey())
+        .credit_to(&token_b_account.pubkey())
+        .with_trader()
+        .unwrap()
+        .instruction(seed, 100, 10, 0, [0; MEMO_LEN])
+        .unwrap();
+    let mut tx = Transaction::new_with_payer(&[create_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+
+    let trader = get_trader(&mut client, &payer.pubkey()).await;
+    assert_eq!(trader.next_seed, seed + 1);
+    assert_eq!(trader.open_offers(&token_a, &token_b), 1);
+    assert_eq!(trader.open_offers(&token_b, &token_a), 0);
+
+    let (offer_addr, _bump) =
+        try_find_offer_pda(&payer.pubkey(), &token_a, &token_b, seed).unwrap();
+    let offer_acc = client.get_account(offer_addr).await.unwrap().unwrap();
+    let offer = Offer::unpack(&offer_acc.data).unwrap();
+    let cancel_ix = CancelOfferAccounts::new(&offer)
+        .unwrap()
+        .with_trader()
+        .unwrap()
+        .instruction()
+        .unwrap();
+    let mut tx = Transaction::new_with_payer(&[cancel_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+
+    // the seed isn't handed out again once its offer is closed
+    let trader = get_trader(&mut client, &payer.pubkey()).await;
+    assert_eq!(trader.next_seed, seed + 1);
+    assert_eq!(trader.open_offers(&token_a, &token_b), 0);
+}
+
+#[tokio::test]
+async fn success_sync_trader_pair() {
+    let (mut client, payer, recent_blockhash) = program_test().start().await;
+    let (token_a, token_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let create_trader_ix = create_trader(&payer.pubkey(), &payer.pubkey()).unwrap();
+    let sync_ix = sync_trader_pair(&payer.pubkey(), &token_a, &token_b, 3).unwrap();
+    let mut tx = Transaction::new_with_payer(&[create_trader_ix, sync_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+
+    let trader = get_trader(&mut client, &payer.pubkey()).await;
+    assert_eq!(trader.open_offers(&token_a, &token_b), 3);
+
+    // offers closed by fills are subtracted by syncing to the actual count
+    let sync_ix = sync_trader_pair(&payer.pubkey(), &token_a, &token_b, 0).unwrap();
+    let mut tx = Transaction::new_with_payer(&[sync_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+
+    let trader = get_trader(&mut client, &payer.pubkey()).await;
+    assert_eq!(trader.open_offers(&token_a, &token_b), 0);
+}
+
+#[tokio::test]
+async fn fail_sync_trader_pair_not_owner() {
+    let (mut client, payer, recent_blockhash) = program_test().start().await;
+    let owner = Keypair::new();
+    let create_trader_ix = create_trader(&payer.pubkey(), &owner.pubkey()).unwrap();
+    let mut tx = Transaction::new_with_payer(&[create_trader_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer, &owner], recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+
+    // payer signs, but the trader account is owner's
+    let mut sync_ix = sync_trader_pair(
+        &owner.pubkey(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        1,
+    )
+    .unwrap();
+    sync_ix.accounts[0].pubkey = payer.pubkey();
+    let mut tx = Transaction::new_with_payer(&[sync_ix], Some(&payer.pubkey()));
+    tx.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        client.process_transaction(tx).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(SimpleDexError::IncorrectTraderAccount as u32)
+        )
+    );
+}
@@ -1,9 +1,10 @@
 import { useState, VFC } from "react";
-import { Side } from "simpledex";
+import { findFeeAuthorityPda, Side } from "simpledex";
 import u from "@/styles/u.module.css";
 import { TokenValueInputRow } from "./TokenValueInputRow";
 import { useProvider } from "@/hooks/useProvider";
 import { parseTokenVal, sendSignConfirm, unshiftCreateATA } from "@/utils";
+import { getAssociatedTokenAddress } from "@solana/spl-token";
 import { Transaction } from "@solana/web3.js";
 import { useSolana } from "@/contexts/SolanaContext";
 import { useMarket } from "@/contexts/MarketContext";
@@ -72,6 +73,83 @@ export const NewOrder: VFC = () => {
     setIsAwaitingTx(false);
   };
 
+  // takes the best resting offer on the other side directly,
+  // without creating an offer of our own
+  const swap = async () => {
+    const walletPubkey = wallet.publicKey;
+    if (!walletPubkey || !wallet.signTransaction) return;
+    const baseToken = market.baseToken ?? (await market.loadBaseToken());
+    const quoteToken = market.quoteToken ?? (await market.loadQuoteToken());
+    const baseVal = parseTokenVal(baseValStr, baseToken.decimals);
+    const quoteVal = parseTokenVal(quoteValStr, quoteToken.decimals);
+    if (!baseVal || !quoteVal) {
+      setBaseValStr("");
+      setQuoteValStr("");
+      return;
+    }
+    const best = market.offers.get(
+      isBid ? market.askOffers[0] : market.bidOffers[0]
+    );
+    if (!best) {
+      alert("no offers to swap with");
+      return;
+    }
+    // spend up to quoteVal for at least baseVal when buying, and vice versa
+    const [amountIn, minAmountOut] = isBid
+      ? [quoteVal, baseVal]
+      : [baseVal, quoteVal];
+    const payFrom = await getAssociatedTokenAddress(
+      best.acceptMint,
+      walletPubkey,
+      true
+    );
+    const receiveTo = await getAssociatedTokenAddress(
+      best.offerMint,
+      walletPubkey,
+      true
+    );
+    const [feeAuthority] = await findFeeAuthorityPda();
+    const feeAccount = await getAssociatedTokenAddress(
+      best.acceptMint,
+      feeAuthority,
+      true
+    );
+    const ix = best.takeOfferInstruction(
+      walletPubkey,
+      payFrom,
+      receiveTo,
+      feeAccount,
+      amountIn,
+      minAmountOut
+    );
+    const tx = new Transaction();
+    tx.add(ix);
+    await unshiftCreateATA(
+      market.connection,
+      tx,
+      best.offerMint,
+      walletPubkey
+    );
+    // the first swap paying fees in a token creates the fee authority's ATA
+    await unshiftCreateATA(
+      market.connection,
+      tx,
+      best.acceptMint,
+      feeAuthority,
+      walletPubkey
+    );
+    setIsAwaitingTx(true);
+    await sendSignConfirm(
+      cluster.network,
+      market.connection,
+      wallet,
+      tx,
+      undefined,
+      "Swapped"
+    );
+    setIsAwaitingTx(false);
+  };
+
   return (
     <div
       className={`${u["round-border"]} ${u["grey-bg"]} ${u["flex"]} ${u["flex-col"]} ${u["padding-20"]} ${u["children-vert-margin"]}`}
@@ -101,6 +179,13 @@ export const NewOrder: VFC = () => {
       >
         {isAwaitingTx ? "CONFIRMING..." : "ORDER"}
       </button>
+      <button
+        disabled={!wallet.connected || isAwaitingTx}
+        className={`${u["full-width"]} ${u["background-contrast"]} ${u["no-border"]}`}
+        onClick={swap}
+      >
+        {isAwaitingTx ? "CONFIRMING..." : "SWAP"}
+      </button>
     </div>
   );
 };
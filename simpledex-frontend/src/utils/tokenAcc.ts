@@ -10,7 +10,8 @@ export async function unshiftCreateATA(
   connection: Connection,
   tx: Transaction,
   token: PublicKey,
-  user: PublicKey
+  user: PublicKey,
+  payer: PublicKey = user
 ): Promise<void> {
   const userATA = await getAssociatedTokenAddress(token, user, true);
   try {
@@ -21,7 +22,7 @@ export async function unshiftCreateATA(
       `User ATA for target mint ${token.toString()} does not exist, creating...`
     );
     tx.instructions.unshift(
-      createAssociatedTokenAccountInstruction(user, userATA, user, token)
+      createAssociatedTokenAccountInstruction(payer, userATA, user, token)
     );
   }
 }
@@ -0,0 +1,126 @@
+use simpledex::{instructions::CreateOfferAccounts, processor, state::MEMO_LEN};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, program_pack::Pack, pubkey::Pubkey, signature::Keypair,
+    signer::Signer, system_instruction, transaction::Transaction,
+};
+
+/// simpledex as a native builtin at [`simpledex::id`]
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new(
+        "simpledex",
+        simpledex::id(),
+        processor!(processor::Processor::process),
+    )
+}
+
+/// Signs `instructions` with `payer` and `signers` and unwraps their result
+pub async fn process(
+    client: &mut BanksClient,
+    recent_blockhash: &Hash,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    let mut tx = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+    tx.sign(&all_signers, *recent_blockhash);
+    client.process_transaction(tx).await.unwrap();
+}
+
+pub async fn create_mint(
+    client: &mut BanksClient,
+    recent_blockhash: &Hash,
+    payer: &Keypair,
+) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &payer.pubkey(),
+            None,
+            0,
+        )
+        .unwrap(),
+    ];
+    process(client, recent_blockhash, payer, &ixs, &[&mint]).await;
+    mint.pubkey()
+}
+
+/// Creates a token account owned by `owner` with `amount` tokens minted to it
+pub async fn create_funded_token_account(
+    client: &mut BanksClient,
+    recent_blockhash: &Hash,
+    payer: &Keypair,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &account.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &account.pubkey(),
+            mint,
+            owner,
+        )
+        .unwrap(),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            mint,
+            &account.pubkey(),
+            &payer.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap(),
+    ];
+    process(client, recent_blockhash, payer, &ixs, &[&account]).await;
+    account.pubkey()
+}
+
+/// Returns the created offer's address
+#[allow(clippy::too_many_arguments)]
+pub async fn create_offer(
+    client: &mut BanksClient,
+    recent_blockhash: &Hash,
+    payer: &Keypair,
+    owner: &Keypair,
+    pay_from: &Pubkey,
+    credit_to: &Pubkey,
+    offer_mint: &Pubkey,
+    accept_mint: &Pubkey,
+    seed: u16,
+    offering: u64,
+    accept_at_least: u64,
+) -> Pubkey {
+    let accounts = CreateOfferAccounts::new(&owner.pubkey(), offer_mint, accept_mint, seed)
+        .unwrap()
+        .payer(&payer.pubkey())
+        .pay_from(pay_from)
+        .refund_to(pay_from)
+        .credit_to(credit_to);
+    let ix = accounts
+        .instruction(seed, offering, accept_at_least, 0, [0; MEMO_LEN])
+        .unwrap();
+    process(client, recent_blockhash, payer, &[ix], &[owner]).await;
+    accounts.offer
+}
@@ -0,0 +1,11 @@
+//! Test helpers shared by the client and matcher crates, not published.
+//!
+//! [`market`] builds offers of a fresh pair for unit tests,
+//! [`banks`] sets up a [`ProgramTest`](solana_program_test::ProgramTest) with mints,
+//! token accounts and offers for integration tests.
+
+pub mod banks;
+pub mod market;
+
+pub use banks::{create_funded_token_account, create_mint, create_offer, process, program_test};
+pub use market::Market;
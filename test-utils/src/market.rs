@@ -0,0 +1,85 @@
+use simpledex::{
+    events::OfferEvent,
+    state::{Offer, MEMO_LEN},
+};
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+
+/// A pair of unique mints to create offers of
+pub struct Market {
+    pub base: Pubkey,
+    pub quote: Pubkey,
+}
+
+impl Market {
+    pub fn new() -> Self {
+        Self {
+            base: Pubkey::new_unique(),
+            quote: Pubkey::new_unique(),
+        }
+    }
+
+    /// bid for `base` base atomics at `quote` quote atomics
+    pub fn bid(&self, quote: u64, base: u64, slot: Slot) -> (Pubkey, Offer) {
+        self.offer(self.quote, self.base, quote, base, slot)
+    }
+
+    /// ask of `base` base atomics for `quote` quote atomics
+    pub fn ask(&self, base: u64, quote: u64, slot: Slot) -> (Pubkey, Offer) {
+        self.offer(self.base, self.quote, base, quote, slot)
+    }
+
+    /// offer at a unique address
+    pub fn offer(
+        &self,
+        offer_mint: Pubkey,
+        accept_mint: Pubkey,
+        offering: u64,
+        accept_at_least: u64,
+        slot: Slot,
+    ) -> (Pubkey, Offer) {
+        let offer = Offer {
+            offer_mint,
+            accept_mint,
+            offering,
+            accept_at_least,
+            slot,
+            ..Offer::default()
+        };
+        (Pubkey::new_unique(), offer)
+    }
+
+    /// CreateOffer event of a bid, see [`Self::bid`]
+    pub fn bid_event(&self, quote: u64, base: u64) -> OfferEvent {
+        self.offer_event(self.quote, self.base, quote, base)
+    }
+
+    /// CreateOffer event of an ask, see [`Self::ask`]
+    pub fn ask_event(&self, base: u64, quote: u64) -> OfferEvent {
+        self.offer_event(self.base, self.quote, base, quote)
+    }
+
+    /// CreateOffer event of an offer at a unique address
+    pub fn offer_event(
+        &self,
+        offer_mint: Pubkey,
+        accept_mint: Pubkey,
+        offering: u64,
+        accept_at_least: u64,
+    ) -> OfferEvent {
+        OfferEvent {
+            offer: Pubkey::new_unique(),
+            offer_mint,
+            offering,
+            accept_mint,
+            accept_at_least,
+            client_order_id: 0,
+            memo: [0; MEMO_LEN],
+        }
+    }
+}
+
+impl Default for Market {
+    fn default() -> Self {
+        Self::new()
+    }
+}
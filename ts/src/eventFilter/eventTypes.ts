@@ -3,15 +3,18 @@ import { PublicKey } from "@solana/web3.js";
 export const CREATE_OFFER_EVENT_TAG = "CREATE";
 export const CANCEL_OFFER_EVENT_TAG = "CANCEL";
 export const MATCH_OFFERS_EVENT_TAG = "MATCH";
+export const TAKE_OFFER_EVENT_TAG = "TAKE";
 
 export type CreateOfferEventTag = typeof CREATE_OFFER_EVENT_TAG;
 export type CancelOfferEventTag = typeof CANCEL_OFFER_EVENT_TAG;
 export type MatchOffersEventTag = typeof MATCH_OFFERS_EVENT_TAG;
+export type TakeOfferEventTag = typeof TAKE_OFFER_EVENT_TAG;
 
 export type EventTypeTag =
   | CreateOfferEventTag
   | CancelOfferEventTag
-  | MatchOffersEventTag;
+  | MatchOffersEventTag
+  | TakeOfferEventTag;
 
 export interface OfferFields {
   address: PublicKey;
@@ -21,9 +24,15 @@ export interface OfferFields {
   acceptAtLeast: bigint;
 }
 
-export type CreateOffer = OfferFields & { tag: CreateOfferEventTag };
+export type CreateOffer = OfferFields & {
+  tag: CreateOfferEventTag;
+  clientOrderId: bigint;
+};
 
-export type CancelOffer = OfferFields & { tag: CancelOfferEventTag };
+export type CancelOffer = OfferFields & {
+  tag: CancelOfferEventTag;
+  clientOrderId: bigint;
+};
 
 export type MatchOffers = {
   tag: MatchOffersEventTag;
@@ -37,7 +46,27 @@ export type MatchOffers = {
   };
 };
 
-export type SimpleDexEvent = CreateOffer | CancelOffer | MatchOffers;
+export type TakeOffer = {
+  tag: TakeOfferEventTag;
+  updatedOffer: OfferFields;
+  taker: PublicKey;
+  feeAccount: PublicKey;
+  clientOrderId: bigint;
+  trade: {
+    /** offerMint paid to the taker */
+    amountOut: bigint;
+    /** acceptMint paid to the offer, not including fees */
+    amountIn: bigint;
+    /** taker fee in acceptMint */
+    fee: bigint;
+  };
+};
+
+export type SimpleDexEvent =
+  | CreateOffer
+  | CancelOffer
+  | MatchOffers
+  | TakeOffer;
 
 export function isCreateOffer(x: SimpleDexEvent | null): x is CreateOffer {
   return x ? x.tag === CREATE_OFFER_EVENT_TAG : false;
@@ -50,3 +79,7 @@ export function isCancelOffer(x: SimpleDexEvent | null): x is CancelOffer {
 export function isMatchOffers(x: SimpleDexEvent | null): x is MatchOffers {
   return x ? x.tag === MATCH_OFFERS_EVENT_TAG : false;
 }
+
+export function isTakeOffer(x: SimpleDexEvent | null): x is TakeOffer {
+  return x ? x.tag === TAKE_OFFER_EVENT_TAG : false;
+}
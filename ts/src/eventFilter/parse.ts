@@ -1,5 +1,8 @@
+import { blob, Layout, struct, u8 } from "@solana/buffer-layout";
+import { publicKey, u64 } from "@solana/buffer-layout-utils";
 import { PublicKey } from "@solana/web3.js";
 
+import { PROGRAM_ID } from "@/consts";
 import { ParseError } from "@/eventFilter/err";
 import {
   CANCEL_OFFER_EVENT_TAG,
@@ -10,7 +13,10 @@ import {
   MatchOffers,
   OfferFields,
   SimpleDexEvent,
+  TAKE_OFFER_EVENT_TAG,
+  TakeOffer,
 } from "@/eventFilter/eventTypes";
+import { MEMO_LEN } from "@/state";
 import { isTuple, Tuple } from "@/typeUtils";
 
 /**
@@ -68,36 +74,318 @@ function parseMatchOffers(body: string): MatchOffers {
   };
 }
 
-function parseCreateOffer(body: string): CreateOffer {
+/**
+ *
+ * @param body csv string with
+ *             [0] - offer base58
+ *             [1] - offerMint base58
+ *             [2] - amountOut
+ *             [3] - acceptMint base58
+ *             [4] - amountIn
+ *             [5] - fee
+ *             [6] - new offering
+ *             [7] - new acceptAtLeast
+ *             [8] - taker base58
+ *             [9] - feeAccount base58
+ *             [10] - slot
+ *             [11] - clientOrderId
+ * @throws if malformed event log
+ */
+function parseTakeOffer(body: string): TakeOffer {
   const csv = body.split(",");
-  if (!isTuple(csv, 5)) {
+  if (!isTuple(csv, 12)) {
     throw new ParseError();
   }
   return {
-    tag: CREATE_OFFER_EVENT_TAG,
-    ...parseOfferFields(csv),
+    tag: TAKE_OFFER_EVENT_TAG,
+    updatedOffer: parseOfferFields([csv[0], csv[1], csv[6], csv[3], csv[7]]),
+    taker: new PublicKey(csv[8]),
+    feeAccount: new PublicKey(csv[9]),
+    clientOrderId: BigInt(csv[11]),
+    trade: {
+      amountOut: BigInt(csv[2]),
+      amountIn: BigInt(csv[4]),
+      fee: BigInt(csv[5]),
+    },
   };
 }
 
-function parseCancelOffer(body: string): CancelOffer {
+/**
+ *
+ * @param body csv string with the 5 offer fields followed by
+ *             [5] - clientOrderId
+ * @throws if malformed event log
+ */
+function parseOfferFieldsWithClientOrderId(
+  body: string,
+): OfferFields & { clientOrderId: bigint } {
   const csv = body.split(",");
-  if (!isTuple(csv, 5)) {
+  if (!isTuple(csv, 6)) {
     throw new ParseError();
   }
+  return {
+    ...parseOfferFields([csv[0], csv[1], csv[2], csv[3], csv[4]]),
+    clientOrderId: BigInt(csv[5]),
+  };
+}
+
+function parseCreateOffer(body: string): CreateOffer {
+  return {
+    tag: CREATE_OFFER_EVENT_TAG,
+    ...parseOfferFieldsWithClientOrderId(body),
+  };
+}
+
+function parseCancelOffer(body: string): CancelOffer {
   return {
     tag: CANCEL_OFFER_EVENT_TAG,
-    ...parseOfferFields(csv),
+    ...parseOfferFieldsWithClientOrderId(body),
+  };
+}
+
+// Packed events emitted through sol_log_data, see the rust crate's events module
+
+type OfferEventData = {
+  offer: PublicKey;
+  offerMint: PublicKey;
+  offering: bigint;
+  acceptMint: PublicKey;
+  acceptAtLeast: bigint;
+  clientOrderId: bigint;
+  memo: Uint8Array;
+};
+
+const OFFER_EVENT_LAYOUT = struct<OfferEventData>([
+  publicKey("offer"),
+  publicKey("offerMint"),
+  u64("offering"),
+  publicKey("acceptMint"),
+  u64("acceptAtLeast"),
+  u64("clientOrderId"),
+  blob(MEMO_LEN, "memo"),
+]);
+
+type ReceiptData = {
+  aToB: bigint;
+  bToA: bigint;
+  aToMatcher: bigint;
+  bToMatcher: bigint;
+  aFee: bigint;
+  bFee: bigint;
+  aBonus: bigint;
+  bBonus: bigint;
+  aSeq: number;
+};
+
+type MatchOffersEventData = {
+  tokenA: PublicKey;
+  tokenB: PublicKey;
+  receipt: ReceiptData;
+  offeringA: PublicKey;
+  offeringANewOffering: bigint;
+  offeringANewAcceptAtLeast: bigint;
+  offeringAClientOrderId: bigint;
+  offeringAMemo: Uint8Array;
+  offeringB: PublicKey;
+  offeringBNewOffering: bigint;
+  offeringBNewAcceptAtLeast: bigint;
+  offeringBClientOrderId: bigint;
+  offeringBMemo: Uint8Array;
+  matcherA: PublicKey;
+  matcherB: PublicKey;
+  slot: bigint;
+};
+
+const MATCH_OFFERS_EVENT_LAYOUT = struct<MatchOffersEventData>([
+  publicKey("tokenA"),
+  publicKey("tokenB"),
+  struct<ReceiptData>(
+    [
+      u64("aToB"),
+      u64("bToA"),
+      u64("aToMatcher"),
+      u64("bToMatcher"),
+      u64("aFee"),
+      u64("bFee"),
+      u64("aBonus"),
+      u64("bBonus"),
+      u8("aSeq"),
+    ],
+    "receipt",
+  ),
+  publicKey("offeringA"),
+  u64("offeringANewOffering"),
+  u64("offeringANewAcceptAtLeast"),
+  u64("offeringAClientOrderId"),
+  blob(MEMO_LEN, "offeringAMemo"),
+  publicKey("offeringB"),
+  u64("offeringBNewOffering"),
+  u64("offeringBNewAcceptAtLeast"),
+  u64("offeringBClientOrderId"),
+  blob(MEMO_LEN, "offeringBMemo"),
+  publicKey("matcherA"),
+  publicKey("matcherB"),
+  u64("slot"),
+]);
+
+type TakeOfferEventData = {
+  offer: PublicKey;
+  offerMint: PublicKey;
+  acceptMint: PublicKey;
+  taker: PublicKey;
+  amountIn: bigint;
+  amountOut: bigint;
+  fee: bigint;
+  feeAccount: PublicKey;
+  newOffering: bigint;
+  newAcceptAtLeast: bigint;
+  clientOrderId: bigint;
+  memo: Uint8Array;
+  slot: bigint;
+};
+
+const TAKE_OFFER_EVENT_LAYOUT = struct<TakeOfferEventData>([
+  publicKey("offer"),
+  publicKey("offerMint"),
+  publicKey("acceptMint"),
+  publicKey("taker"),
+  u64("amountIn"),
+  u64("amountOut"),
+  u64("fee"),
+  publicKey("feeAccount"),
+  u64("newOffering"),
+  u64("newAcceptAtLeast"),
+  u64("clientOrderId"),
+  blob(MEMO_LEN, "memo"),
+  u64("slot"),
+]);
+
+/** SimpleDexEvent variant tags */
+enum PackedEventTag {
+  CreateOffer = 0,
+  CancelOffer = 1,
+  MatchOffers = 2,
+  TakeOffer = 3,
+}
+
+/**
+ *
+ * @param layout the variant's layout
+ * @param data the packed event, including its tag
+ * @throws if data is not exactly the tag followed by `layout`
+ */
+function decodeVariant<T>(layout: Layout<T>, data: Buffer): T {
+  if (data.length !== 1 + layout.span) {
+    throw new ParseError();
+  }
+  return layout.decode(data, 1);
+}
+
+function offerEventFields(e: OfferEventData): Omit<CreateOffer, "tag"> {
+  return {
+    address: e.offer,
+    offerMint: e.offerMint,
+    offering: e.offering,
+    acceptMint: e.acceptMint,
+    acceptAtLeast: e.acceptAtLeast,
+    clientOrderId: e.clientOrderId,
+  };
+}
+
+function toMatchOffers(e: MatchOffersEventData): MatchOffers {
+  return {
+    tag: MATCH_OFFERS_EVENT_TAG,
+    updatedOfferA: {
+      address: e.offeringA,
+      offerMint: e.tokenA,
+      offering: e.offeringANewOffering,
+      acceptMint: e.tokenB,
+      acceptAtLeast: e.offeringANewAcceptAtLeast,
+    },
+    updatedOfferB: {
+      address: e.offeringB,
+      offerMint: e.tokenB,
+      offering: e.offeringBNewOffering,
+      acceptMint: e.tokenA,
+      acceptAtLeast: e.offeringBNewAcceptAtLeast,
+    },
+    trade: {
+      tokenA: e.tokenA,
+      tokenB: e.tokenB,
+      tokenAAmount: e.receipt.aToB,
+      tokenBAmount: e.receipt.bToA,
+    },
+  };
+}
+
+function toTakeOffer(e: TakeOfferEventData): TakeOffer {
+  return {
+    tag: TAKE_OFFER_EVENT_TAG,
+    updatedOffer: {
+      address: e.offer,
+      offerMint: e.offerMint,
+      offering: e.newOffering,
+      acceptMint: e.acceptMint,
+      acceptAtLeast: e.newAcceptAtLeast,
+    },
+    taker: e.taker,
+    feeAccount: e.feeAccount,
+    clientOrderId: e.clientOrderId,
+    trade: {
+      amountOut: e.amountOut,
+      amountIn: e.amountIn,
+      fee: e.fee,
+    },
   };
 }
 
+/**
+ *
+ * @param body the base64 fields of a `Program data: ` log line
+ * @returns null if not a single field with a known event tag
+ * @throws if malformed event
+ */
+function parseProgramData(body: string): SimpleDexEvent | null {
+  const fields = body.split(" ");
+  if (!isTuple(fields, 1)) {
+    return null;
+  }
+  const data = Buffer.from(fields[0], "base64");
+  switch (data[0]) {
+    case PackedEventTag.CreateOffer:
+      return {
+        tag: CREATE_OFFER_EVENT_TAG,
+        ...offerEventFields(decodeVariant(OFFER_EVENT_LAYOUT, data)),
+      };
+    case PackedEventTag.CancelOffer:
+      return {
+        tag: CANCEL_OFFER_EVENT_TAG,
+        ...offerEventFields(decodeVariant(OFFER_EVENT_LAYOUT, data)),
+      };
+    case PackedEventTag.MatchOffers:
+      return toMatchOffers(decodeVariant(MATCH_OFFERS_EVENT_LAYOUT, data));
+    case PackedEventTag.TakeOffer:
+      return toTakeOffer(decodeVariant(TAKE_OFFER_EVENT_LAYOUT, data));
+    default:
+      return null;
+  }
+}
+
+const PROGRAM_DATA_PREFIX = "Program data: ";
 const PROGRAM_LOG_PREFIX = "Program log: ";
 
 /**
+ * Parses a single log line, either a packed `Program data: ` event or a csv
+ * `Program log: ` event of programs built with `csv-logs`.
+ * Only pass lines logged by simpledex itself, see {@link parseLogs}.
  *
  * @param log the raw log string returned in @solana/web3.js:Log
  * @returns
  */
 export function parseLog(log: string): SimpleDexEvent | null {
+  if (log.startsWith(PROGRAM_DATA_PREFIX)) {
+    return parseProgramData(log.slice(PROGRAM_DATA_PREFIX.length));
+  }
   const firstSplit = log.split(PROGRAM_LOG_PREFIX);
   if (firstSplit.length < 2) {
     return null;
@@ -112,7 +400,68 @@ export function parseLog(log: string): SimpleDexEvent | null {
       return parseMatchOffers(tagAndBody[1]);
     case CANCEL_OFFER_EVENT_TAG:
       return parseCancelOffer(tagAndBody[1]);
+    case TAKE_OFFER_EVENT_TAG:
+      return parseTakeOffer(tagAndBody[1]);
     default:
       return null;
   }
 }
+
+const PROGRAM_INVOKE_REGEX = /^Program (\w+) invoke \[\d+\]$/;
+const PROGRAM_EXIT_REGEX = /^Program (\w+) (success|failed)/;
+
+type Invocation = {
+  programId: string;
+  packed: SimpleDexEvent[];
+  csv: SimpleDexEvent[];
+};
+
+/**
+ * Parses the events of a successful transaction's logs.
+ * Only lines logged by the simpledex invocations themselves are parsed,
+ * so other programs, including ones simpledex CPIs into, can't fake events.
+ * Programs built with `csv-logs` log each event twice, once packed and once as csv:
+ * the packed event is preferred.
+ *
+ * @param logs the raw log strings returned in @solana/web3.js:Logs
+ * @param programId
+ * @throws if malformed event log
+ */
+export function parseLogs(
+  logs: string[],
+  programId: PublicKey = PROGRAM_ID,
+): SimpleDexEvent[] {
+  const simpledex = programId.toBase58();
+  const invocations: Invocation[] = [];
+  const events: SimpleDexEvent[] = [];
+  logs.forEach((log) => {
+    const invoke = PROGRAM_INVOKE_REGEX.exec(log);
+    if (invoke !== null) {
+      invocations.push({ programId: invoke[1], packed: [], csv: [] });
+      return;
+    }
+    const exit = PROGRAM_EXIT_REGEX.exec(log);
+    if (exit !== null) {
+      const invocation = invocations.pop();
+      if (invocation !== undefined && exit[2] === "success") {
+        const { packed, csv } = invocation;
+        events.push(...(packed.length > 0 ? packed : csv));
+      }
+      return;
+    }
+    const current = invocations[invocations.length - 1];
+    if (current === undefined || current.programId !== simpledex) {
+      return;
+    }
+    const event = parseLog(log);
+    if (event === null) {
+      return;
+    }
+    if (log.startsWith(PROGRAM_DATA_PREFIX)) {
+      current.packed.push(event);
+    } else {
+      current.csv.push(event);
+    }
+  });
+  return events;
+}
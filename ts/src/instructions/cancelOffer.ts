@@ -6,9 +6,16 @@ import { PROGRAM_ID } from "@/consts";
 import { SimpleDexInstruction } from "@/instructions/types";
 import { Offer } from "@/state/offer";
 
+/**
+ *
+ * @param offer
+ * @param programId
+ * @param trader address of the owner's Trader account, if it should be updated
+ */
 export function cancelOfferInstruction(
   offer: Offer,
   programId: PublicKey = PROGRAM_ID,
+  trader?: PublicKey,
 ): TransactionInstruction {
   const keys = [
     { pubkey: offer.owner, isSigner: true, isWritable: false },
@@ -18,6 +25,9 @@ export function cancelOfferInstruction(
     { pubkey: offer.refundRentTo, isSigner: false, isWritable: true },
     { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
   ];
+  if (trader) {
+    keys.push({ pubkey: trader, isSigner: false, isWritable: true });
+  }
 
   const data = Buffer.alloc(1);
   u8().encode(SimpleDexInstruction.CancelOffer, data);
@@ -1,4 +1,4 @@
-import { struct, u8, u16 } from "@solana/buffer-layout";
+import { blob, struct, u8, u16 } from "@solana/buffer-layout";
 import { u64 } from "@solana/buffer-layout-utils";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -13,14 +13,16 @@ import {
 
 import { PROGRAM_ID } from "@/consts";
 import { SimpleDexInstruction } from "@/instructions/types";
-import { findOfferPda } from "@/pda";
-import { Offer } from "@/state";
+import { findOfferPda, findTraderPda } from "@/pda";
+import { MEMO_LEN, Offer } from "@/state";
 
 export type CreateOfferArgs = {
   bump: number;
   seed: number;
   offering: bigint;
   acceptAtLeast: bigint;
+  clientOrderId: bigint;
+  memo: Uint8Array;
 };
 
 export type CreateOfferInstructionData = {
@@ -32,7 +34,14 @@ export const CREATE_OFFER_INSTRUCTION_DATA = struct<CreateOfferInstructionData>(
   [
     u8("instruction"),
     struct<CreateOfferArgs>(
-      [u8("bump"), u16("seed"), u64("offering"), u64("acceptAtLeast")],
+      [
+        u8("bump"),
+        u16("seed"),
+        u64("offering"),
+        u64("acceptAtLeast"),
+        u64("clientOrderId"),
+        blob(MEMO_LEN, "memo"),
+      ],
       "args",
     ),
   ],
@@ -50,7 +59,10 @@ export async function createOfferInstruction(
   seed: number,
   offering: bigint,
   acceptAtLeast: bigint,
+  clientOrderId: bigint = BigInt(0),
+  memo: Uint8Array = new Uint8Array(MEMO_LEN),
   programId: PublicKey = PROGRAM_ID,
+  withTrader: boolean = false,
 ): Promise<TransactionInstruction> {
   const [offer, bump] = await findOfferPda(
     owner,
@@ -78,6 +90,10 @@ export async function createOfferInstruction(
     // TODO: remove once ata 1.0.5 drops
     { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
   ];
+  if (withTrader) {
+    const [trader] = await findTraderPda(owner, programId);
+    keys.push({ pubkey: trader, isSigner: false, isWritable: true });
+  }
 
   const data = Buffer.alloc(CREATE_OFFER_INSTRUCTION_DATA.span);
   CREATE_OFFER_INSTRUCTION_DATA.encode(
@@ -88,6 +104,8 @@ export async function createOfferInstruction(
         seed,
         offering,
         acceptAtLeast,
+        clientOrderId,
+        memo,
       },
     },
     data,
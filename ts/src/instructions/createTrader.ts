@@ -0,0 +1,30 @@
+import { u8 } from "@solana/buffer-layout";
+import {
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+} from "@solana/web3.js";
+
+import { PROGRAM_ID } from "@/consts";
+import { SimpleDexInstruction } from "@/instructions/types";
+import { findTraderPda } from "@/pda";
+
+export async function createTraderInstruction(
+  payer: PublicKey,
+  owner: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): Promise<TransactionInstruction> {
+  const [trader] = await findTraderPda(owner, programId);
+
+  const keys = [
+    { pubkey: payer, isSigner: true, isWritable: true },
+    { pubkey: owner, isSigner: true, isWritable: false },
+    { pubkey: trader, isSigner: false, isWritable: true },
+    { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+  ];
+
+  const data = Buffer.alloc(1);
+  u8().encode(SimpleDexInstruction.CreateTrader, data);
+
+  return new TransactionInstruction({ keys, programId, data });
+}
@@ -1,4 +1,9 @@
 export * from "@/instructions/cancelOffer";
 export * from "@/instructions/createOffer";
+export * from "@/instructions/createTrader";
 export * from "@/instructions/matchOffers";
+export * from "@/instructions/migrateOffer";
+export * from "@/instructions/syncTraderPair";
+export * from "@/instructions/takeOffer";
 export * from "@/instructions/types";
+export * from "@/instructions/withdrawFees";
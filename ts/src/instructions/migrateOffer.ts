@@ -0,0 +1,27 @@
+import { u8 } from "@solana/buffer-layout";
+import {
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+} from "@solana/web3.js";
+
+import { PROGRAM_ID } from "@/consts";
+import { SimpleDexInstruction } from "@/instructions/types";
+import { Offer } from "@/state/offer";
+
+export function migrateOfferInstruction(
+  offer: Offer,
+  payer: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): TransactionInstruction {
+  const keys = [
+    { pubkey: payer, isSigner: true, isWritable: true },
+    { pubkey: offer.address, isSigner: false, isWritable: true },
+    { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+  ];
+
+  const data = Buffer.alloc(1);
+  u8().encode(SimpleDexInstruction.MigrateOffer, data);
+
+  return new TransactionInstruction({ keys, programId, data });
+}
@@ -0,0 +1,64 @@
+import { struct, u8, u32 } from "@solana/buffer-layout";
+import { publicKey } from "@solana/buffer-layout-utils";
+import { PublicKey, TransactionInstruction } from "@solana/web3.js";
+
+import { PROGRAM_ID } from "@/consts";
+import { SimpleDexInstruction } from "@/instructions/types";
+import { findTraderPda } from "@/pda";
+
+export type SyncTraderPairArgs = {
+  offerMint: PublicKey;
+  acceptMint: PublicKey;
+  openOffers: number;
+};
+
+export type SyncTraderPairInstructionData = {
+  instruction: SimpleDexInstruction.SyncTraderPair;
+  args: SyncTraderPairArgs;
+};
+
+export const SYNC_TRADER_PAIR_INSTRUCTION_DATA =
+  struct<SyncTraderPairInstructionData>([
+    u8("instruction"),
+    struct<SyncTraderPairArgs>(
+      [publicKey("offerMint"), publicKey("acceptMint"), u32("openOffers")],
+      "args",
+    ),
+  ]);
+
+/**
+ * Sets `owner`'s open offers of `offerMint` for `acceptMint` in their Trader account,
+ * e.g. to subtract offers that were closed by being completely filled.
+ * Setting it to 0 frees the pair's entry.
+ *
+ * @param owner
+ * @param offerMint
+ * @param acceptMint
+ * @param openOffers the owner's actual open offers in the pair
+ * @param programId
+ */
+export async function syncTraderPairInstruction(
+  owner: PublicKey,
+  offerMint: PublicKey,
+  acceptMint: PublicKey,
+  openOffers: number,
+  programId: PublicKey = PROGRAM_ID,
+): Promise<TransactionInstruction> {
+  const [trader] = await findTraderPda(owner, programId);
+
+  const keys = [
+    { pubkey: owner, isSigner: true, isWritable: false },
+    { pubkey: trader, isSigner: false, isWritable: true },
+  ];
+
+  const data = Buffer.alloc(SYNC_TRADER_PAIR_INSTRUCTION_DATA.span);
+  SYNC_TRADER_PAIR_INSTRUCTION_DATA.encode(
+    {
+      instruction: SimpleDexInstruction.SyncTraderPair,
+      args: { offerMint, acceptMint, openOffers },
+    },
+    data,
+  );
+
+  return new TransactionInstruction({ keys, programId, data });
+}
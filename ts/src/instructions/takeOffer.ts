@@ -0,0 +1,72 @@
+import { struct, u8 } from "@solana/buffer-layout";
+import { u64 } from "@solana/buffer-layout-utils";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { PublicKey, TransactionInstruction } from "@solana/web3.js";
+
+import { PROGRAM_ID } from "@/consts";
+import { SimpleDexInstruction } from "@/instructions/types";
+import { Offer } from "@/state";
+
+export type TakeOfferArgs = {
+  amountIn: bigint;
+  minAmountOut: bigint;
+};
+
+export type TakeOfferInstructionData = {
+  instruction: SimpleDexInstruction.TakeOffer;
+  args: TakeOfferArgs;
+};
+
+export const TAKE_OFFER_INSTRUCTION_DATA = struct<TakeOfferInstructionData>([
+  u8("instruction"),
+  struct<TakeOfferArgs>([u64("amountIn"), u64("minAmountOut")], "args"),
+]);
+
+/**
+ * Swaps up to `amountIn` of the offer's acceptMint directly for its offerMint,
+ * at the offer's limit price, without creating a counter-offer.
+ * The taker pays the taker fee on top of the amount paid to the offer.
+ *
+ * @param offer
+ * @param taker
+ * @param payFrom taker's acceptMint token account
+ * @param receiveTo taker's offerMint token account
+ * @param feeAccount `findFeeAccount(offer.acceptMint)`, the program checks it
+ * @param amountIn max acceptMint tokens to pay the offer, not including fees
+ * @param minAmountOut fails with SlippageExceeded if the offer gives less
+ * @param programId
+ */
+export function takeOfferInstruction(
+  offer: Offer,
+  taker: PublicKey,
+  payFrom: PublicKey,
+  receiveTo: PublicKey,
+  feeAccount: PublicKey,
+  amountIn: bigint,
+  minAmountOut: bigint,
+  programId: PublicKey = PROGRAM_ID,
+): TransactionInstruction {
+  const keys = [
+    { pubkey: taker, isSigner: true, isWritable: false },
+    { pubkey: payFrom, isSigner: false, isWritable: true },
+    { pubkey: receiveTo, isSigner: false, isWritable: true },
+    { pubkey: offer.address, isSigner: false, isWritable: true },
+    { pubkey: offer.holdingAddress, isSigner: false, isWritable: true },
+    { pubkey: offer.creditTo, isSigner: false, isWritable: true },
+    { pubkey: offer.refundTo, isSigner: false, isWritable: true },
+    { pubkey: offer.refundRentTo, isSigner: false, isWritable: true },
+    { pubkey: feeAccount, isSigner: false, isWritable: true },
+    { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+  ];
+
+  const data = Buffer.alloc(TAKE_OFFER_INSTRUCTION_DATA.span);
+  TAKE_OFFER_INSTRUCTION_DATA.encode(
+    {
+      instruction: SimpleDexInstruction.TakeOffer,
+      args: { amountIn, minAmountOut },
+    },
+    data,
+  );
+
+  return new TransactionInstruction({ keys, programId, data });
+}
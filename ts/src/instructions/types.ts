@@ -2,4 +2,10 @@ export enum SimpleDexInstruction {
   CreateOffer = 0,
   CancelOffer = 1,
   MatchOffers = 2,
+  QuoteMatch = 3,
+  MigrateOffer = 4,
+  CreateTrader = 5,
+  TakeOffer = 6,
+  SyncTraderPair = 7,
+  WithdrawFees = 8,
 }
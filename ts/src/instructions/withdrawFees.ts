@@ -0,0 +1,75 @@
+import { u8 } from "@solana/buffer-layout";
+import {
+  createAssociatedTokenAccountInstruction,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { PublicKey, TransactionInstruction } from "@solana/web3.js";
+
+import { PROGRAM_ID } from "@/consts";
+import { SimpleDexInstruction } from "@/instructions/types";
+import { findFeeAccount, findFeeAuthorityPda } from "@/pda";
+
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111",
+);
+
+/**
+ * Withdraws all fees collected in `mint` to `withdrawTo`.
+ * Must be signed by the program's upgrade authority.
+ *
+ * @param upgradeAuthority
+ * @param mint
+ * @param withdrawTo token account of `mint` to withdraw the fees to
+ * @param programId
+ */
+export async function withdrawFeesInstruction(
+  upgradeAuthority: PublicKey,
+  mint: PublicKey,
+  withdrawTo: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): Promise<TransactionInstruction> {
+  const [programData] = await PublicKey.findProgramAddress(
+    [programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+  );
+  const [feeAuthority] = await findFeeAuthorityPda(programId);
+  const feeAccount = await findFeeAccount(mint, programId);
+
+  const keys = [
+    { pubkey: upgradeAuthority, isSigner: true, isWritable: false },
+    { pubkey: programData, isSigner: false, isWritable: false },
+    { pubkey: feeAuthority, isSigner: false, isWritable: false },
+    { pubkey: feeAccount, isSigner: false, isWritable: true },
+    { pubkey: withdrawTo, isSigner: false, isWritable: true },
+    { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+  ];
+
+  const data = Buffer.alloc(1);
+  u8().encode(SimpleDexInstruction.WithdrawFees, data);
+
+  return new TransactionInstruction({ keys, programId, data });
+}
+
+/**
+ * Creates the fee account TakeOffer credits its fees in `mint` to.
+ * Run it once per accept mint when deploying,
+ * TakeOffer fails for offers accepting `mint` until it exists.
+ *
+ * @param payer
+ * @param mint
+ * @param programId
+ */
+export async function createFeeAccountInstruction(
+  payer: PublicKey,
+  mint: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): Promise<TransactionInstruction> {
+  const [feeAuthority] = await findFeeAuthorityPda(programId);
+  const feeAccount = await findFeeAccount(mint, programId);
+  return createAssociatedTokenAccountInstruction(
+    payer,
+    feeAccount,
+    feeAuthority,
+    mint,
+  );
+}
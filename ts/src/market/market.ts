@@ -10,11 +10,13 @@ import {
   isCancelOffer,
   isCreateOffer,
   isMatchOffers,
+  isTakeOffer,
   MatchOffers,
   OfferFields,
   SimpleDexEvent,
+  TakeOffer,
 } from "@/eventFilter/eventTypes";
-import { parseLog } from "@/eventFilter/parse";
+import { parseLogs } from "@/eventFilter/parse";
 import { createOfferInstruction as _createOfferInstruction } from "@/instructions";
 import { AllOfferSeedsUsedError, MarketOutOfSyncError } from "@/market/err";
 import {
@@ -23,7 +25,7 @@ import {
   MarketOfferInfo,
   Side,
 } from "@/market/types";
-import { Offer, OFFER_LAYOUT } from "@/state";
+import { decodeOffer, Offer, OFFER_LAYOUT, OFFER_SIZE } from "@/state";
 
 function sortHighestBidFirst(a: OfferFields, b: OfferFields): number {
   // higher: o1 / a1 > o2 / a2 => o1*a2 > o2*a1
@@ -246,6 +248,8 @@ export class Market {
   ): Promise<Offer[]> {
     const accs = await this.connection.getProgramAccounts(this.programId, {
       filters: [
+        // legacy offers have different offsets and can't be matched until migrated
+        { dataSize: OFFER_SIZE },
         {
           memcmp: {
             bytes: offerMint.toBase58(),
@@ -262,7 +266,7 @@ export class Market {
     });
     const allOffersPromises = accs.map(
       async ({ pubkey, account: { data } }) => {
-        const rawOffer = OFFER_LAYOUT.decode(data);
+        const rawOffer = decodeOffer(data);
         const holdingAddress = await Offer.holdingAddress(offerMint, pubkey);
         return new Offer(rawOffer, pubkey, holdingAddress);
       },
@@ -335,6 +339,21 @@ export class Market {
     });
   }
 
+  private takeOfferFilter(): EventFilterASTNode<SimpleDexEvent, TakeOffer> {
+    return SDF.narrowType(isTakeOffer).filter((e) =>
+      this.isOfMarketPredicate(e.updatedOffer),
+    );
+  }
+
+  private registerTakeOfferCallback() {
+    this.onEvent((event) => {
+      const takeEvent = this.takeOfferFilter().execute(event);
+      if (takeEvent) {
+        this.updateOffers([takeEvent.updatedOffer]);
+      }
+    });
+  }
+
   public onEvent(callback: (event: SimpleDexEvent) => void): number {
     let id = this.eventCallbacks.size;
     while (this.eventCallbacks.has(id)) {
@@ -350,11 +369,11 @@ export class Market {
 
   public registerAllEventsListener(): void {
     this.eventListener = this.connection.onLogs(this.programId, (l) => {
-      l.logs.forEach((log) => {
-        const event = parseLog(log);
-        if (event !== null) {
-          Array.from(this.eventCallbacks.values()).forEach((cb) => cb(event));
-        }
+      if (l.err !== null) {
+        return;
+      }
+      parseLogs(l.logs, this.programId).forEach((event) => {
+        Array.from(this.eventCallbacks.values()).forEach((cb) => cb(event));
       });
     });
   }
@@ -370,6 +389,7 @@ export class Market {
     this.registerCreateOfferCallback();
     this.registerCancelOfferCallback();
     this.registerMatchOffersCallback();
+    this.registerTakeOfferCallback();
     this.registerAllEventsListener();
   }
 
@@ -430,8 +450,18 @@ export class Market {
       refundTo: undefined,
       creditTo: undefined,
       refundRentTo: undefined,
+      clientOrderId: undefined,
+      memo: undefined,
     };
-    const { payer, payFrom, refundTo, creditTo, refundRentTo } = acceptedOpts;
+    const {
+      payer,
+      payFrom,
+      refundTo,
+      creditTo,
+      refundRentTo,
+      clientOrderId,
+      memo,
+    } = acceptedOpts;
     const payerAddr = payer ?? owner;
     const payFromAddr =
       payFrom ?? (await getAssociatedTokenAddress(offerMint, owner, true));
@@ -452,6 +482,8 @@ export class Market {
       seed,
       offering,
       acceptAtLeast,
+      clientOrderId,
+      memo,
       this.programId,
     );
   }
@@ -33,6 +33,8 @@ export type MarketCreateOfferOptions = {
   refundTo?: PublicKey;
   creditTo?: PublicKey;
   refundRentTo?: PublicKey;
+  clientOrderId?: bigint;
+  memo?: Uint8Array;
 };
 
 export type MarketOfferInfo = {
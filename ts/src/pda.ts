@@ -1,4 +1,5 @@
 import { u16 } from "@solana/buffer-layout";
+import { getAssociatedTokenAddress } from "@solana/spl-token";
 import { PublicKey } from "@solana/web3.js";
 
 import { PROGRAM_ID } from "@/consts";
@@ -47,3 +48,35 @@ export function createOfferPda(
     programId,
   );
 }
+
+const TRADER_PDA_PREFIX = Buffer.from("trader");
+
+export function findTraderPda(
+  owner: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): Promise<[PublicKey, number]> {
+  return PublicKey.findProgramAddress(
+    [TRADER_PDA_PREFIX, owner.toBuffer()],
+    programId,
+  );
+}
+
+const FEE_AUTHORITY_PDA_PREFIX = Buffer.from("fee_authority");
+
+export function findFeeAuthorityPda(
+  programId: PublicKey = PROGRAM_ID,
+): Promise<[PublicKey, number]> {
+  return PublicKey.findProgramAddress([FEE_AUTHORITY_PDA_PREFIX], programId);
+}
+
+/**
+ * The only token account TakeOffer accepts as its fee account for fees in `mint`:
+ * the fee authority PDA's ATA
+ */
+export async function findFeeAccount(
+  mint: PublicKey,
+  programId: PublicKey = PROGRAM_ID,
+): Promise<PublicKey> {
+  const [feeAuthority] = await findFeeAuthorityPda(programId);
+  return getAssociatedTokenAddress(mint, feeAuthority, true);
+}
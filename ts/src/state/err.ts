@@ -3,3 +3,13 @@ export class OfferNotFoundError extends Error {}
 export class OfferAccountInvalidOwnerError extends Error {}
 
 export class OfferAccountInvalidSizeError extends Error {}
+
+export class OfferAccountInvalidDiscriminatorError extends Error {}
+
+export class TraderNotFoundError extends Error {}
+
+export class TraderAccountInvalidOwnerError extends Error {}
+
+export class TraderAccountInvalidError extends Error {}
+
+export class TraderSeedsExhaustedError extends Error {}
@@ -1,2 +1,3 @@
 export * from "@/state/err";
 export * from "@/state/offer";
+export * from "@/state/trader";
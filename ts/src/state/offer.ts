@@ -1,4 +1,4 @@
-import { struct, u8, u16 } from "@solana/buffer-layout";
+import { blob, struct, u8, u16, u32 } from "@solana/buffer-layout";
 import { bigInt, publicKey, u64 } from "@solana/buffer-layout-utils";
 import { getAssociatedTokenAddress } from "@solana/spl-token";
 import {
@@ -11,14 +11,29 @@ import {
 import { PROGRAM_ID } from "@/consts";
 import { cancelOfferInstruction as _cancelOfferInstruction } from "@/instructions/cancelOffer";
 import { matchOffersInstruction as _matchOffersInstruction } from "@/instructions/matchOffers";
+import { migrateOfferInstruction as _migrateOfferInstruction } from "@/instructions/migrateOffer";
+import { takeOfferInstruction as _takeOfferInstruction } from "@/instructions/takeOffer";
 import { createOfferPda, findOfferPda } from "@/pda";
 import {
+  OfferAccountInvalidDiscriminatorError,
   OfferAccountInvalidOwnerError,
   OfferAccountInvalidSizeError,
   OfferNotFoundError,
 } from "@/state/err";
 
+export const OFFER_DISCRIMINATOR = "O".charCodeAt(0);
+
+export enum OfferVersion {
+  Legacy = 0,
+  V1 = 1,
+  V2 = 2,
+}
+
+export const CURRENT_OFFER_VERSION = OfferVersion.V2;
+
 export interface RawOffer {
+  discriminator: number;
+  version: OfferVersion;
   slot: bigint;
   offering: bigint;
   acceptAtLeast: bigint;
@@ -30,9 +45,20 @@ export interface RawOffer {
   refundTo: PublicKey;
   creditTo: PublicKey;
   refundRentTo: PublicKey;
+  clientOrderId: bigint;
+  memo: Uint8Array;
+  originalOffering: bigint;
+  originalAcceptAtLeast: bigint;
+  filledOfferAmount: bigint;
+  receivedAmount: bigint;
+  fillCount: number;
 }
 
+export const MEMO_LEN = 16;
+
 export const OFFER_LAYOUT = struct<RawOffer>([
+  u8("discriminator"),
+  u8("version"),
   u64("slot"),
   u64("offering"),
   u64("acceptAtLeast"),
@@ -44,11 +70,120 @@ export const OFFER_LAYOUT = struct<RawOffer>([
   publicKey("refundTo"),
   publicKey("creditTo"),
   publicKey("refundRentTo"),
+  u64("clientOrderId"),
+  blob(MEMO_LEN, "memo"),
+  u64("originalOffering"),
+  u64("originalAcceptAtLeast"),
+  u64("filledOfferAmount"),
+  u64("receivedAmount"),
+  u32("fillCount"),
 ]);
 
 export const OFFER_SIZE = OFFER_LAYOUT.span;
 
+type FillStats =
+  | "originalOffering"
+  | "originalAcceptAtLeast"
+  | "filledOfferAmount"
+  | "receivedAmount"
+  | "fillCount";
+
+type RawLegacyOffer = Omit<
+  RawOffer,
+  "discriminator" | "version" | "clientOrderId" | "memo" | FillStats
+>;
+
+type RawV1Offer = Omit<RawOffer, FillStats>;
+
+const V1_OFFER_LAYOUT = struct<RawV1Offer>([
+  u8("discriminator"),
+  u8("version"),
+  u64("slot"),
+  u64("offering"),
+  u64("acceptAtLeast"),
+  u16("seed"),
+  u8("bump"),
+  publicKey("owner"),
+  publicKey("offerMint"),
+  publicKey("acceptMint"),
+  publicKey("refundTo"),
+  publicKey("creditTo"),
+  publicKey("refundRentTo"),
+  u64("clientOrderId"),
+  blob(MEMO_LEN, "memo"),
+]);
+
+/**
+ * Unprefixed layout of offers created before versioning
+ */
+export const LEGACY_OFFER_LAYOUT = struct<RawLegacyOffer>([
+  u64("slot"),
+  u64("offering"),
+  u64("acceptAtLeast"),
+  u16("seed"),
+  u8("bump"),
+  publicKey("owner"),
+  publicKey("offerMint"),
+  publicKey("acceptMint"),
+  publicKey("refundTo"),
+  publicKey("creditTo"),
+  publicKey("refundRentTo"),
+]);
+
+export const LEGACY_OFFER_SIZE = 221;
+
+function withInitialFillStats(
+  rawOffer: Omit<RawOffer, FillStats>,
+): RawOffer {
+  return {
+    ...rawOffer,
+    originalOffering: rawOffer.offering,
+    originalAcceptAtLeast: rawOffer.acceptAtLeast,
+    filledOfferAmount: BigInt(0),
+    receivedAmount: BigInt(0),
+    fillCount: 0,
+  };
+}
+
+/**
+ *
+ * @param data offer account data of any layout version.
+ *             Fields missing from older layouts are zeroed,
+ *             except originalOffering and originalAcceptAtLeast,
+ *             which are set to the current values
+ * @throws if data is not a valid offer account
+ */
+export function decodeOffer(data: Buffer): RawOffer {
+  if (data.length === LEGACY_OFFER_SIZE) {
+    return withInitialFillStats({
+      ...LEGACY_OFFER_LAYOUT.decode(data),
+      discriminator: OFFER_DISCRIMINATOR,
+      version: OfferVersion.Legacy,
+      clientOrderId: BigInt(0),
+      memo: new Uint8Array(MEMO_LEN),
+    });
+  }
+  if (data[0] !== OFFER_DISCRIMINATOR) {
+    throw new OfferAccountInvalidDiscriminatorError();
+  }
+  switch (data[1]) {
+    case OfferVersion.V1:
+      if (data.length !== V1_OFFER_LAYOUT.span)
+        throw new OfferAccountInvalidSizeError();
+      return withInitialFillStats(V1_OFFER_LAYOUT.decode(data));
+    case OfferVersion.V2:
+      if (data.length !== OFFER_SIZE) throw new OfferAccountInvalidSizeError();
+      return OFFER_LAYOUT.decode(data);
+    default:
+      throw new OfferAccountInvalidDiscriminatorError();
+  }
+}
+
 export class Offer implements RawOffer {
+  public discriminator: number;
+
+  public version: OfferVersion;
+
   public slot: bigint;
 
   public offering: bigint;
@@ -71,6 +206,20 @@ export class Offer implements RawOffer {
 
   public refundRentTo: PublicKey;
 
+  public clientOrderId: bigint;
+
+  public memo: Uint8Array;
+
+  public originalOffering: bigint;
+
+  public originalAcceptAtLeast: bigint;
+
+  public filledOfferAmount: bigint;
+
+  public receivedAmount: bigint;
+
+  public fillCount: number;
+
   // cache PDA and ATA
   public address: PublicKey;
 
@@ -96,9 +245,8 @@ export class Offer implements RawOffer {
     if (!info) throw new OfferNotFoundError();
     if (!info.owner.equals(programId))
       throw new OfferAccountInvalidOwnerError();
-    if (info.data.length < OFFER_SIZE) throw new OfferAccountInvalidSizeError();
 
-    const rawOffer = OFFER_LAYOUT.decode(info.data.slice(0, OFFER_SIZE));
+    const rawOffer = decodeOffer(info.data);
 
     const address = await createOfferPda(
       rawOffer.owner,
@@ -144,8 +292,9 @@ export class Offer implements RawOffer {
 
   cancelOfferInstruction(
     programId: PublicKey = PROGRAM_ID,
+    trader?: PublicKey,
   ): TransactionInstruction {
-    return _cancelOfferInstruction(this, programId);
+    return _cancelOfferInstruction(this, programId, trader);
   }
 
   matchOffersInstruction(
@@ -163,6 +312,58 @@ export class Offer implements RawOffer {
     );
   }
 
+  takeOfferInstruction(
+    taker: PublicKey,
+    payFrom: PublicKey,
+    receiveTo: PublicKey,
+    feeAccount: PublicKey,
+    amountIn: bigint,
+    minAmountOut: bigint,
+    programId: PublicKey = PROGRAM_ID,
+  ): TransactionInstruction {
+    return _takeOfferInstruction(
+      this,
+      taker,
+      payFrom,
+      receiveTo,
+      feeAccount,
+      amountIn,
+      minAmountOut,
+      programId,
+    );
+  }
+
+  migrateOfferInstruction(
+    payer: PublicKey,
+    programId: PublicKey = PROGRAM_ID,
+  ): TransactionInstruction {
+    return _migrateOfferInstruction(this, payer, programId);
+  }
+
+  /**
+   * @returns true if this offer needs to be migrated before it can be matched
+   */
+  isOutdated(): boolean {
+    return this.version !== CURRENT_OFFER_VERSION;
+  }
+
+  /**
+   * @returns fraction of originalOffering filled so far, in [0, 1]
+   */
+  fillRatio(): number {
+    if (this.originalOffering === BigInt(0)) return 0;
+    return Number(this.filledOfferAmount) / Number(this.originalOffering);
+  }
+
+  /**
+   * @returns average accept_mint atomics received per offer_mint atomic filled,
+   *          undefined if never filled
+   */
+  averagePrice(): number | undefined {
+    if (this.filledOfferAmount === BigInt(0)) return undefined;
+    return Number(this.receivedAmount) / Number(this.filledOfferAmount);
+  }
+
   isClosed(): boolean {
     const zero = BigInt(0);
     return this.offering === zero || this.acceptAtLeast === zero;
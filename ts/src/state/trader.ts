@@ -0,0 +1,129 @@
+import { seq, struct, u8, u16, u32 } from "@solana/buffer-layout";
+import { publicKey } from "@solana/buffer-layout-utils";
+import { Commitment, Connection, PublicKey } from "@solana/web3.js";
+
+import { PROGRAM_ID } from "@/consts";
+import { findTraderPda } from "@/pda";
+import {
+  TraderAccountInvalidError,
+  TraderAccountInvalidOwnerError,
+  TraderNotFoundError,
+  TraderSeedsExhaustedError,
+} from "@/state/err";
+
+export const TRADER_DISCRIMINATOR = "T".charCodeAt(0);
+
+/**
+ * Number of pairs a trader account can track open offers in at once
+ */
+export const TRADER_PAIRS = 8;
+
+export interface TraderPair {
+  offerMint: PublicKey;
+  acceptMint: PublicKey;
+  /**
+   * Offers created minus offers canceled with the trader account supplied.
+   * Offers closed by being completely filled are not subtracted.
+   */
+  openOffers: number;
+}
+
+export const TRADER_PAIR_LAYOUT = struct<TraderPair>([
+  publicKey("offerMint"),
+  publicKey("acceptMint"),
+  u32("openOffers"),
+]);
+
+export interface RawTrader {
+  discriminator: number;
+  owner: PublicKey;
+  bump: number;
+  nextSeed: number;
+  pairs: TraderPair[];
+}
+
+export const TRADER_LAYOUT = struct<RawTrader>([
+  u8("discriminator"),
+  publicKey("owner"),
+  u8("bump"),
+  u16("nextSeed"),
+  seq(TRADER_PAIR_LAYOUT, TRADER_PAIRS, "pairs"),
+]);
+
+export const TRADER_SIZE = TRADER_LAYOUT.span;
+
+export class Trader implements RawTrader {
+  public discriminator: number;
+
+  public owner: PublicKey;
+
+  public bump: number;
+
+  public nextSeed: number;
+
+  public pairs: TraderPair[];
+
+  public address: PublicKey;
+
+  constructor(rawTrader: RawTrader, address: PublicKey) {
+    Object.assign(this, rawTrader);
+    this.address = address;
+  }
+
+  static async load(
+    connection: Connection,
+    owner: PublicKey,
+    commitment?: Commitment,
+    programId: PublicKey = PROGRAM_ID,
+  ): Promise<Trader> {
+    const [address] = await findTraderPda(owner, programId);
+    const info = await connection.getAccountInfo(address, commitment);
+    if (!info) throw new TraderNotFoundError();
+    if (!info.owner.equals(programId))
+      throw new TraderAccountInvalidOwnerError();
+    if (
+      info.data.length !== TRADER_SIZE ||
+      info.data[0] !== TRADER_DISCRIMINATOR
+    )
+      throw new TraderAccountInvalidError();
+    return new Trader(TRADER_LAYOUT.decode(info.data), address);
+  }
+
+  /**
+   * @returns the owner's open offers of `offerMint` for `acceptMint`
+   */
+  openOffers(offerMint: PublicKey, acceptMint: PublicKey): number {
+    const pair = this.pairs.find(
+      (p) =>
+        p.openOffers > 0 &&
+        p.offerMint.equals(offerMint) &&
+        p.acceptMint.equals(acceptMint),
+    );
+    return pair ? pair.openOffers : 0;
+  }
+}
+
+/**
+ *
+ * @param connection
+ * @param owner
+ * @returns the seed `owner`'s next offer should use, 0 if owner has no trader account.
+ *          Create offers with `trader` supplied to advance it.
+ * @throws TraderSeedsExhaustedError if the trader account has handed out every seed
+ */
+export async function nextOfferSeed(
+  connection: Connection,
+  owner: PublicKey,
+  commitment?: Commitment,
+  programId: PublicKey = PROGRAM_ID,
+): Promise<number> {
+  try {
+    const trader = await Trader.load(connection, owner, commitment, programId);
+    // the program doesn't wrap around to seeds that may still be in use
+    if (trader.nextSeed === 0xffff) throw new TraderSeedsExhaustedError();
+    return trader.nextSeed;
+  } catch (e) {
+    if (e instanceof TraderNotFoundError) return 0;
+    throw e;
+  }
+}